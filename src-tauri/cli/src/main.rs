@@ -0,0 +1,600 @@
+use std::error::Error;
+use std::fs;
+use std::io::{ self, Write };
+use std::path::{ Path, PathBuf };
+use std::process::ExitCode;
+
+use clap::{ Parser, Subcommand, ValueEnum };
+
+use serde::Serialize;
+
+use smartypants_core::{ BinType, BinSize, FormatError, get_card_size };
+use smartypants_core::data_view::DataView;
+use smartypants_core::data_pack::DataPack;
+use smartypants_core::sprite_pack::SpritePack;
+use smartypants_core::smacard::{ CardHeader, CardType, HeaderIssue, TamaSmaCard, read_card, save_card, new_card, check_card_header, repair_card_header };
+use smartypants_core::firmware::{ Firmware, read_firmware_with_layout, save_firmware };
+use smartypants_core::layout::{ FirmwareLayout, read_layout_file, detect_firmware_layout };
+use smartypants_core::text::{ CharEncoding, get_default_char_codes, check_char_codes };
+use smartypants_core::export::{ export_strings_to, export_images_to };
+use smartypants_core::import::{ import_strings_from, import_images_from };
+use smartypants_core::verify::verify_round_trip;
+use smartypants_core::project::{ ProjectInfo, PROJECT_VERSION, export_project_to, import_project_from };
+use smartypants_core::diff::compare_bins;
+use smartypants_core::patch::{ create_patch, apply_patch };
+use smartypants_core::safe_write::write_file;
+use smartypants_core::size_report::get_card_size_report;
+use smartypants_core::library::scan_library;
+use smartypants_core::detect::detect_bin_type;
+use smartypants_core::known_dumps::{ Identification, PartHash, read_known_dumps, identify_dump, get_part_hashes };
+
+#[derive(Parser)]
+#[command(name = "smartypants-cli", version, about = "Inspect, unpack and repack Tamagotchi Smart firmware and TamaSma card bins")]
+struct Cli {
+	/// Text encoding JSON (as exported from the app); defaults to the Japanese encoding
+	#[arg(long, global = true)]
+	encoding: Option<PathBuf>,
+
+	/// Firmware layout TOML or JSON to try before the built-in layouts; can be given more than once
+	#[arg(long, global = true)]
+	layout: Vec<PathBuf>,
+
+	/// Read bins as this type instead of detecting it, for bins that could be either
+	#[arg(long = "type", global = true, value_enum)]
+	bin_type: Option<BinTypeArg>,
+
+	#[command(subcommand)]
+	command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+	/// Report whether a bin looks like firmware or a TamaSma card and what was recognized, as JSON
+	Detect {
+		bin: PathBuf
+	},
+	/// Print the MD5 and CRC32 of the whole bin and of each part (code, data pack, sprite pack) as JSON,
+	/// and name the matching dump if --known-dumps is given
+	Identify {
+		bin: PathBuf,
+		#[arg(long)]
+		known_dumps: Option<PathBuf>
+	},
+	/// Print the firmware layout (data pack, sprite pack and menu string offsets and sizes) found for a bin, as JSON
+	Layout {
+		bin: PathBuf
+	},
+	/// Print card header, header problems, card ID, bin size and entity counts as JSON
+	Info {
+		bin: PathBuf
+	},
+	/// Export all strings to a CSV file
+	ExportStrings {
+		bin: PathBuf,
+		csv: PathBuf
+	},
+	/// Import strings from a CSV file and save the result to a new bin
+	ImportStrings {
+		bin: PathBuf,
+		csv: PathBuf,
+		output: PathBuf
+	},
+	/// Export every image set as a spritesheet, named <png stem>-<index>.png
+	ExportImages {
+		bin: PathBuf,
+		png: PathBuf
+	},
+	/// Import spritesheets named <name>-<index>.png from a folder and save the result to a new bin
+	ImportImages {
+		bin: PathBuf,
+		folder: PathBuf,
+		output: PathBuf
+	},
+	/// Save a bin again without edits and report every byte range that differs from the original, as JSON
+	Verify {
+		bin: PathBuf
+	},
+	/// Unpack a bin into a project folder of JSON files and PNG spritesheets
+	ExportProject {
+		bin: PathBuf,
+		folder: PathBuf
+	},
+	/// Rebuild a bin from a project folder; firmware projects need the original firmware as --base
+	ImportProject {
+		folder: PathBuf,
+		output: PathBuf,
+		#[arg(long)]
+		base: Option<PathBuf>
+	},
+	/// Compare two bins entity by entity and print what changed, as JSON or Markdown
+	Diff {
+		old: PathBuf,
+		new: PathBuf,
+		#[arg(long)]
+		markdown: bool
+	},
+	/// Write the changes from an original bin to a modified one as an IPS patch (BPS for files of 16 MB or more)
+	CreatePatch {
+		original: PathBuf,
+		modified: PathBuf,
+		patch: PathBuf
+	},
+	/// Apply an IPS or BPS patch to a bin and save the result to a new bin
+	ApplyPatch {
+		bin: PathBuf,
+		patch: PathBuf,
+		output: PathBuf
+	},
+	/// Create an empty TamaSma card with no items, characters or images
+	NewCard {
+		output: PathBuf,
+		#[arg(long)]
+		card_id: u8,
+		/// Padded with spaces to 16 characters
+		#[arg(long, default_value = "")]
+		vendor_id: String,
+		/// Padded with spaces to 16 characters
+		#[arg(long, default_value = "")]
+		product_id: String,
+		#[arg(long, value_enum, default_value = "tamasma")]
+		card_type: NewCardType,
+		#[arg(long, default_value_t = 0)]
+		year: u16,
+		#[arg(long, default_value_t = 0)]
+		month: u16,
+		#[arg(long, default_value_t = 0)]
+		day: u16,
+		#[arg(long, default_value_t = 0)]
+		revision: u16,
+		#[arg(long, value_enum, default_value = "128kb")]
+		size: CardSize
+	},
+	/// Fix a card's sector count, checksum, MD5 and signature without changing anything else
+	RepairHeader {
+		bin: PathBuf,
+		output: PathBuf
+	},
+	/// Print the bytes used by each part of a card and the space left at each card size, as JSON
+	SizeReport {
+		bin: PathBuf
+	},
+	/// List the header of every card in a folder and flag card IDs used by more than one card, as JSON;
+	/// the index is cached in <folder>/.smartypants-library.json unless --index is given
+	Library {
+		folder: PathBuf,
+		#[arg(long)]
+		index: Option<PathBuf>
+	},
+	/// Read a bin and save it again, optionally changing the card size
+	Repack {
+		bin: PathBuf,
+		output: PathBuf,
+		#[arg(long, value_enum)]
+		size: Option<CardSize>
+	}
+}
+
+#[derive(Clone, ValueEnum)]
+enum BinTypeArg {
+	Firmware,
+	Card
+}
+
+impl BinTypeArg {
+	fn to_bin_type(&self) -> BinType {
+		match self {
+			BinTypeArg::Firmware => BinType::Firmware,
+			BinTypeArg::Card => BinType::SmaCard
+		}
+	}
+}
+
+#[derive(Clone, ValueEnum)]
+enum CardSize {
+	#[value(name = "128kb")]
+	Card128KB,
+	#[value(name = "1mb")]
+	Card1MB,
+	#[value(name = "2mb")]
+	Card2MB,
+	/// The smallest size the card fits in
+	Auto
+}
+
+impl CardSize {
+	fn to_bin_size(&self) -> BinSize {
+		match self {
+			CardSize::Card128KB => BinSize::Card128KB,
+			CardSize::Card1MB => BinSize::Card1MB,
+			CardSize::Card2MB => BinSize::Card2MB,
+			CardSize::Auto => BinSize::Auto
+		}
+	}
+}
+
+#[derive(Clone, ValueEnum)]
+enum NewCardType {
+	#[value(name = "tamasma")]
+	TamaSmaCard,
+	#[value(name = "promo-treasure")]
+	PromoTreasure,
+	#[value(name = "promo-item")]
+	PromoItem
+}
+
+enum Bin {
+	SmaCard(TamaSmaCard, BinSize),
+	Firmware(Firmware, Vec<u8>)
+}
+
+impl Bin {
+	fn data_pack(&self) -> &DataPack {
+		match self {
+			Bin::SmaCard(card, _) => &card.data_pack,
+			Bin::Firmware(firmware, _) => &firmware.data_pack
+		}
+	}
+
+	fn sprite_pack(&self) -> &SpritePack {
+		match self {
+			Bin::SmaCard(card, _) => &card.sprite_pack,
+			Bin::Firmware(firmware, _) => &firmware.sprite_pack
+		}
+	}
+
+	fn sprite_pack_mut(&mut self) -> &mut SpritePack {
+		match self {
+			Bin::SmaCard(card, _) => &mut card.sprite_pack,
+			Bin::Firmware(firmware, _) => &mut firmware.sprite_pack
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct BinInfo {
+	bin_type: BinType,
+	bin_size: BinSize,
+	file_size: usize,
+	card_header: Option<CardHeader>,
+	header_issues: Option<Vec<HeaderIssue>>,
+	card_id: u16,
+	use_patch_header: Option<bool>,
+	entity_counts: EntityCounts
+}
+
+#[derive(Serialize)]
+struct IdentifyReport {
+	bin_type: BinType,
+	identification: Option<Identification>,
+	hashes: Vec<PartHash>
+}
+
+#[derive(Serialize)]
+struct EntityCounts {
+	table1: usize,
+	particle_emitters: usize,
+	scenes: usize,
+	tamastrings: usize,
+	table9: usize,
+	items: usize,
+	characters: usize,
+	graphics_nodes: usize,
+	frame_groups: usize,
+	image_sets: usize,
+	menu_strings: Option<usize>
+}
+
+fn main() -> ExitCode {
+	let cli = Cli::parse();
+	match run(cli) {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(why) => match why.downcast_ref::<FormatError>() {
+			Some(format_error) => {
+				eprintln!("Error ({}): {}", format_error.kind(), format_error);
+				get_exit_code(format_error)
+			},
+			None => {
+				eprintln!("Error: {}", why);
+				ExitCode::FAILURE
+			}
+		}
+	}
+}
+
+// 2 means the bin itself is broken, 3 that it doesn't fit, 4 that a patch doesn't apply
+fn get_exit_code(format_error: &FormatError) -> ExitCode {
+	match format_error {
+		FormatError::SizeOverflow { .. } => ExitCode::from(3),
+		FormatError::Patch { .. } => ExitCode::from(4),
+		FormatError::Io { .. } | FormatError::Parse { .. } | FormatError::Layout { .. } | FormatError::Project { .. }
+			| FormatError::KnownDumps { .. } | FormatError::Other { .. } => ExitCode::FAILURE,
+		_ => ExitCode::from(2)
+	}
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+	let char_codes: Vec<CharEncoding> = match &cli.encoding {
+		Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+		None => get_default_char_codes()
+	};
+	check_char_codes(&char_codes)?;
+	let layouts = cli.layout.iter()
+		.map(|path| read_layout_file(path))
+		.collect::<Result<Vec<FirmwareLayout>, FormatError>>()?;
+	let bin_type_arg = cli.bin_type.map(|bin_type| bin_type.to_bin_type());
+
+	match cli.command {
+		Command::Detect { bin } => {
+			let detection = detect_bin_type(&DataView::new(&fs::read(&bin)?));
+			print_json(&detection)?;
+		},
+
+		Command::Identify { bin, known_dumps } => {
+			let raw_data = fs::read(&bin)?;
+			let bin_type = choose_bin_type(&raw_data, &bin_type_arg);
+			let known_dumps = match known_dumps {
+				Some(path) => read_known_dumps(&path)?,
+				None => Vec::new()
+			};
+			let report = IdentifyReport {
+				identification: identify_dump(&raw_data, &bin_type, &known_dumps, &layouts),
+				hashes: get_part_hashes(&raw_data, &bin_type, &layouts),
+				bin_type
+			};
+			print_json(&report)?;
+		},
+
+		Command::Layout { bin } => {
+			let layout = detect_firmware_layout(&fs::read(&bin)?, &layouts)?;
+			print_json(&layout)?;
+		},
+
+		Command::Info { bin } => {
+			let raw_data = fs::read(&bin)?;
+			let bin = read_bin(&raw_data, &bin_type_arg, &char_codes, &layouts)?;
+			let info = get_info(&bin, &raw_data);
+			print_json(&info)?;
+		},
+
+		Command::ExportStrings { bin, csv } => {
+			let bin = read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)?;
+			let menu_strings = match &bin {
+				Bin::Firmware(firmware, _) => Some(firmware.menu_strings.as_slice()),
+				Bin::SmaCard(..) => None
+			};
+			export_strings_to(&csv, menu_strings, Some(bin.data_pack()))?;
+		},
+
+		Command::ImportStrings { bin, csv, output } => {
+			let mut bin = read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)?;
+			match &mut bin {
+				Bin::SmaCard(card, _) => import_strings_from(&csv, &char_codes, None, Some(&mut card.data_pack))?,
+				Bin::Firmware(firmware, _) => import_strings_from(&csv, &char_codes, Some(&mut firmware.menu_strings), Some(&mut firmware.data_pack))?
+			}
+			write_bin(&bin, &output)?;
+		},
+
+		Command::ExportImages { bin, png } => {
+			let bin = read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)?;
+			export_images_to(bin.sprite_pack(), &png)?;
+		},
+
+		Command::ImportImages { bin, folder, output } => {
+			let mut bin = read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)?;
+			let image_indexes = import_images_from(bin.sprite_pack_mut(), &folder)?;
+			eprintln!("Imported {} image set(s)", image_indexes.len());
+			write_bin(&bin, &output)?;
+		},
+
+		Command::Verify { bin } => {
+			let raw_data = fs::read(&bin)?;
+			let report = verify_round_trip(&raw_data, &choose_bin_type(&raw_data, &bin_type_arg), &char_codes, &layouts)?;
+			print_json(&report)?;
+			if !report.is_identical() {
+				return Err(format!("{} differing byte range(s)", report.differences.len()).into());
+			}
+		},
+
+		Command::ExportProject { bin, folder } => {
+			let bin = read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)?;
+			let (bin_type, bin_size, card_header, pack_table, use_patch_header, menu_strings) = match &bin {
+				Bin::SmaCard(card, bin_size) => (BinType::SmaCard, bin_size.clone(), Some(&card.header), Some(&card.pack_table), None, None),
+				Bin::Firmware(firmware, _) => (BinType::Firmware, BinSize::Firmware, None, None, Some(firmware.use_patch_header), Some(firmware.menu_strings.as_slice()))
+			};
+			let info = ProjectInfo { version: PROJECT_VERSION, bin_type, bin_size, use_patch_header };
+			export_project_to(&folder, &info, card_header, pack_table, bin.data_pack(), bin.sprite_pack(), menu_strings)?;
+		},
+
+		Command::ImportProject { folder, output, base } => {
+			let project = import_project_from(&folder, &char_codes)?;
+			let bin_type = project.info.bin_type.clone();
+			let bin = match project.info.bin_type {
+				BinType::SmaCard => {
+					let header = project.card_header.ok_or("Project is missing its card header")?;
+					let pack_table = project.pack_table.unwrap_or_default();
+					let card = TamaSmaCard { header, pack_table, data_pack: project.data_pack, sprite_pack: project.sprite_pack };
+					Bin::SmaCard(card, project.info.bin_size)
+				},
+				BinType::Firmware => {
+					let base = base.ok_or("Firmware projects need the original firmware: use --base <firmware.bin>")?;
+					let base_data = fs::read(base)?;
+					let firmware = Firmware {
+						data_pack: project.data_pack,
+						sprite_pack: project.sprite_pack,
+						menu_strings: project.menu_strings.ok_or("Project is missing its menu strings")?,
+						use_patch_header: project.info.use_patch_header.unwrap_or(false),
+						layout: detect_firmware_layout(&base_data, &layouts)?
+					};
+					Bin::Firmware(firmware, base_data)
+				}
+			};
+			let new_data = write_bin(&bin, &output)?;
+			let report = verify_round_trip(&new_data, &bin_type, &char_codes, &layouts)?;
+			if !report.is_identical() {
+				eprintln!("{}", serde_json::to_string_pretty(&report)?);
+				return Err(format!("Rebuilt bin doesn't save the same way it reads: {} differing byte range(s)", report.differences.len()).into());
+			}
+		},
+
+		Command::Diff { old, new, markdown } => {
+			let diff = compare_bins(&fs::read(&old)?, &fs::read(&new)?, bin_type_arg.as_ref(), &char_codes, &layouts)?;
+			if markdown {
+				print_text(&diff.to_markdown(&old.display().to_string(), &new.display().to_string()))?;
+			} else {
+				print_json(&diff)?;
+			}
+		},
+
+		Command::CreatePatch { original, modified, patch } => {
+			let (patch_format, patch_data) = create_patch(&fs::read(original)?, &fs::read(modified)?)?;
+			fs::write(&patch, patch_data)?;
+			eprintln!("Wrote {:?} patch to {}", patch_format, patch.display());
+		},
+
+		Command::ApplyPatch { bin, patch, output } => {
+			let new_data = apply_patch(&fs::read(bin)?, &fs::read(patch)?)?;
+			write_file(&output, &new_data, 0)?;
+		},
+
+		Command::NewCard { output, card_id, vendor_id, product_id, card_type, year, month, day, revision, size } => {
+			let card_type = match card_type {
+				NewCardType::TamaSmaCard => CardType::TamaSmaCard,
+				NewCardType::PromoTreasure => CardType::PromoTreasure,
+				NewCardType::PromoItem => CardType::PromoItem
+			};
+			let mut header = CardHeader::new(card_id, card_type, &format!("{:<16}", vendor_id), &format!("{:<16}", product_id))?;
+			header.year = year;
+			header.month = month;
+			header.day = day;
+			header.revision = revision;
+			write_bin(&Bin::SmaCard(new_card(header), size.to_bin_size()), &output)?;
+		},
+
+		Command::RepairHeader { bin, output } => {
+			let raw_data = fs::read(&bin)?;
+			if !matches!(choose_bin_type(&raw_data, &bin_type_arg), BinType::SmaCard) {
+				return Err("Only TamaSma card headers can be repaired".into());
+			}
+			let issues = check_card_header(&raw_data);
+			for issue in &issues {
+				eprintln!("{}", issue.describe());
+			}
+			write_file(&output, &repair_card_header(&raw_data)?, 0)?;
+			eprintln!("Fixed {} header problem(s)", issues.len());
+		},
+
+		Command::SizeReport { bin } => {
+			match read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)? {
+				Bin::SmaCard(card, _) => print_json(&get_card_size_report(&card)?)?,
+				Bin::Firmware(..) => return Err("Size reports are only available for TamaSma cards".into())
+			}
+		},
+
+		Command::Library { folder, index } => {
+			let index = index.unwrap_or_else(|| folder.join(".smartypants-library.json"));
+			let library = scan_library(&folder, &index)?;
+			print_json(&library)?;
+			eprintln!("Read {} of {} card(s)", library.rescanned_count, library.entries.len());
+		},
+
+		Command::Repack { bin, output, size } => {
+			let mut bin = read_bin(&fs::read(&bin)?, &bin_type_arg, &char_codes, &layouts)?;
+			if let Some(size) = size {
+				match &mut bin {
+					Bin::SmaCard(_, bin_size) => {
+						*bin_size = size.to_bin_size();
+					},
+					Bin::Firmware(..) => return Err("Card size can only be changed for TamaSma cards".into())
+				}
+			}
+			write_bin(&bin, &output)?;
+		}
+	}
+
+	Ok(())
+}
+
+// --type wins over detection, so a bin that could be either is read the same way by every command
+fn choose_bin_type(raw_data: &[u8], bin_type_arg: &Option<BinType>) -> BinType {
+	if let Some(bin_type) = bin_type_arg {
+		return bin_type.clone();
+	}
+	let detection = detect_bin_type(&DataView::new(raw_data));
+	if detection.is_ambiguous {
+		eprintln!("Warning: unsure whether this is firmware or a TamaSma card; reading it as {:?} (use --type to choose)\n{}", detection.bin_type, detection.describe());
+	}
+	detection.bin_type
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), Box<dyn Error>> {
+	print_text(&format!("{}\n", serde_json::to_string_pretty(value)?))
+}
+
+// stdout may be closed early (e.g. piped into head), which shouldn't panic or change the exit code
+fn print_text(text: &str) -> Result<(), Box<dyn Error>> {
+	match io::stdout().lock().write_all(text.as_bytes()) {
+		Err(why) if why.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+		result => Ok(result?)
+	}
+}
+
+fn read_bin(raw_data: &[u8], bin_type_arg: &Option<BinType>, char_codes: &[CharEncoding], layouts: &[FirmwareLayout]) -> Result<Bin, Box<dyn Error>> {
+	let data = DataView::new(raw_data);
+	match choose_bin_type(raw_data, bin_type_arg) {
+		BinType::SmaCard => {
+			let card = read_card(&data, char_codes)?;
+			Ok(Bin::SmaCard(card, get_card_size(&data)))
+		},
+		BinType::Firmware => {
+			let layout = detect_firmware_layout(raw_data, layouts)?;
+			let firmware = read_firmware_with_layout(&data, char_codes, &layout)?;
+			Ok(Bin::Firmware(firmware, raw_data.to_vec()))
+		}
+	}
+}
+
+fn write_bin(bin: &Bin, path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+	let new_data = match bin {
+		Bin::SmaCard(card, bin_size) => save_card(card, bin_size)?,
+		Bin::Firmware(firmware, original_data) => {
+			let new_data = save_firmware(firmware, original_data, None)?;
+			if new_data.len() != original_data.len() {
+				return Err(format!("Firmware size has changed from {} to {} bytes", original_data.len(), new_data.len()).into());
+			}
+			new_data
+		}
+	};
+	write_file(path, &new_data, 0)?;
+	Ok(new_data)
+}
+
+fn get_info(bin: &Bin, raw_data: &[u8]) -> BinInfo {
+	let data_pack = bin.data_pack();
+	let (bin_type, bin_size, card_header, use_patch_header, menu_strings) = match bin {
+		Bin::SmaCard(card, bin_size) => (BinType::SmaCard, bin_size.clone(), Some(card.header.clone()), None, None),
+		Bin::Firmware(firmware, _) => (BinType::Firmware, BinSize::Firmware, None, Some(firmware.use_patch_header), Some(firmware.menu_strings.len()))
+	};
+
+	BinInfo {
+		bin_type,
+		bin_size,
+		file_size: raw_data.len(),
+		header_issues: card_header.as_ref().map(|_| check_card_header(raw_data)),
+		card_header,
+		card_id: data_pack.card_id,
+		use_patch_header,
+		entity_counts: EntityCounts {
+			table1: data_pack.table1.len(),
+			particle_emitters: data_pack.particle_emitters.len(),
+			scenes: data_pack.scenes.len(),
+			tamastrings: data_pack.tamastrings.len(),
+			table9: data_pack.table9.len(),
+			items: data_pack.items.len(),
+			characters: data_pack.characters.len(),
+			graphics_nodes: data_pack.graphics_nodes.len(),
+			frame_groups: data_pack.frame_groups.len(),
+			image_sets: bin.sprite_pack().image_sets.len(),
+			menu_strings
+		}
+	}
+}
@@ -0,0 +1,337 @@
+use serde::{ Serialize, Deserialize };
+
+use crate::data_view::DataView;
+use crate::error::{ FormatError, DataContext };
+use crate::text::CharEncoding;
+
+pub mod table1;
+pub mod particle_emitter;
+pub mod scene;
+pub mod tamastring;
+pub mod table9;
+pub mod item;
+pub mod character;
+pub mod graphics_node;
+pub mod frame;
+
+// tables 13 and 18 are never filled in; they only pad the next table to an 8 byte boundary
+pub const TABLE_NAMES: [&str; 20] = [
+	"table 1 offsets",
+	"table 1",
+	"particle emitters",
+	"scene offsets",
+	"scene layer offsets",
+	"scene layers",
+	"strings",
+	"string offsets",
+	"table 9 offsets",
+	"table 9",
+	"items",
+	"characters",
+	"table 13 (padding)",
+	"graphics node offsets",
+	"graphics nodes",
+	"frame layers",
+	"frame layer offsets",
+	"table 18 (padding)",
+	"frame groups",
+	"card ID"
+];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityId {
+	pub card_id: Option<u8>,
+	pub entity_id: u16
+}
+
+impl EntityId {
+	pub fn new(word: u16) -> EntityId {
+		let is_card = (word >> 15) > 0;
+		if is_card {
+			EntityId {
+				card_id: Some(((word >> 8) & 0x7f) as u8),
+				entity_id: word & 0xff
+			}
+		} else {
+			EntityId {
+				card_id: None,
+				entity_id: word & 0x7fff
+			}
+		}
+	}
+
+	pub fn to_word(&self) -> u16 {
+		match self.card_id {
+			Some(card_id) => (1 << 15) | ((card_id as u16) << 8) | self.entity_id,
+			None => self.entity_id
+		}
+	}
+
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		if self.card_id.is_some_and(|id| id == old_card_id){
+			self.card_id = Some(new_card_id);
+		}
+	}
+}
+
+// new IDs go after the highest one already in the namespace, so an ID freed by deleting something
+// isn't handed out again while other data may still point at it; gaps are only reused once the namespace is full
+pub fn get_next_entity_id<'a>(ids: impl IntoIterator<Item = &'a EntityId>, card_id: Option<u8>) -> Option<EntityId> {
+	let max_entity_id = if card_id.is_some() { 0xff } else { 0x7fff };
+	let used_ids: Vec<u16> = ids.into_iter()
+		.filter(|id| id.card_id == card_id)
+		.map(|id| id.entity_id)
+		.collect();
+	let next_id = used_ids.iter().max().map(|id| id + 1).unwrap_or(0);
+	let entity_id = if next_id <= max_entity_id {
+		next_id
+	} else {
+		(0..=max_entity_id).find(|id| !used_ids.contains(id))?
+	};
+	Some(EntityId { card_id, entity_id })
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct DataPack {
+	pub table1: Vec<Vec<u16>>,
+	pub particle_emitters: Vec<particle_emitter::ParticleEmitter>,
+	pub scenes: Vec<scene::Scene>,
+	pub tamastrings: Vec<tamastring::TamaString>,
+	pub table9: Vec<Vec<u16>>,
+	pub items: Vec<item::Item>,
+	pub characters: Vec<character::Character>,
+	pub graphics_nodes: Vec<graphics_node::GraphicsNode>,
+	pub frame_groups: Vec<frame::FrameGroup>,
+	pub card_id: u16
+}
+
+impl DataPack {
+	pub fn new(card_id: u16) -> DataPack {
+		DataPack {
+			table1: Vec::new(),
+			particle_emitters: Vec::new(),
+			scenes: Vec::new(),
+			tamastrings: Vec::new(),
+			table9: Vec::new(),
+			items: Vec::new(),
+			characters: Vec::new(),
+			graphics_nodes: Vec::new(),
+			frame_groups: Vec::new(),
+			card_id
+		}
+	}
+
+	// the unknown tables aren't decoded, so any word in them that equals the ID might be a reference to it
+	pub fn find_id_in_unknown_tables(&self, id: &EntityId) -> Vec<String> {
+		if id.card_id.is_none() {
+			return Vec::new();
+		}
+		let word = id.to_word();
+		let mut references = Vec::new();
+		for (table_name, table) in [("Unknown 1", &self.table1), ("Unknown 2", &self.table9)] {
+			for (i, entity) in table.iter().enumerate() {
+				if entity.contains(&word) {
+					references.push(format!("{} entry {} may refer to it", table_name, i));
+				}
+			}
+		}
+		references
+	}
+
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		self.card_id = new_card_id as u16;
+		for scene in self.scenes.iter_mut() {
+			scene.set_card_id(old_card_id, new_card_id);
+		}
+		for tamastring in self.tamastrings.iter_mut() {
+			tamastring.set_card_id(old_card_id, new_card_id);
+		}
+		for item in self.items.iter_mut() {
+			item.set_card_id(old_card_id, new_card_id);
+		}
+		for character in self.characters.iter_mut() {
+			character.set_card_id(old_card_id, new_card_id);
+		}
+		for frame_group in self.frame_groups.iter_mut() {
+			frame_group.set_card_id(old_card_id, new_card_id);
+		}
+	}
+}
+
+pub fn get_data_pack(data: &DataView, char_codes: &[CharEncoding]) -> Result<DataPack, FormatError> {
+	let (table_offsets, table_sizes) = get_table_offsets(data)?;
+
+	let get_table_data = |i: usize| -> Result<DataView, FormatError> {
+		Ok(data.chunk(table_offsets[i], table_sizes[i])?.with_context(DataContext::DataPackTable(i)))
+	};
+
+	let table1_offsets = table1::get_entity_offsets(&get_table_data(0)?)?;
+	let table1 = table1::get_entities(&get_table_data(1)?, table1_offsets)?;
+
+	let particle_emitters = particle_emitter::get_particle_emitters(&get_table_data(2)?)?;
+
+	let (scene_offsets, scene_sizes) = scene::get_scene_offsets(&get_table_data(3)?)?;
+	let scene_layer_offsets = scene::get_scene_layer_offsets(&get_table_data(4)?, scene_offsets, scene_sizes)?;
+	let scenes = scene::get_scenes(&get_table_data(5)?, scene_layer_offsets)?;
+
+	let tamastrings = tamastring::get_tamastrings(&get_table_data(6)?, char_codes)?;
+
+	let (table9_offsets, table9_sizes) = table9::get_entity_offsets(&get_table_data(8)?)?;
+	let table9 = table9::get_entities(&get_table_data(9)?, table9_offsets, table9_sizes)?;
+
+	let items = item::get_items(&get_table_data(10)?, char_codes)?;
+
+	let characters = character::get_characters(&get_table_data(11)?, char_codes)?;
+
+	let graphics_nodes_offsets = graphics_node::get_graphics_nodes_offsets(&get_table_data(13)?)?;
+	let graphics_nodes = graphics_node::get_graphics_nodes(&get_table_data(14)?, graphics_nodes_offsets)?;
+
+	let frame_layers = frame::get_frame_layers(&get_table_data(15)?)?;
+	let frame_groups = frame::get_frame_groups(&get_table_data(18)?, frame_layers)?;
+
+	let card_id = get_table_data(19)?.get_u16(0)?;
+
+	let data_pack = DataPack {
+		table1,
+		particle_emitters,
+		scenes,
+		tamastrings,
+		table9,
+		items,
+		characters,
+		graphics_nodes,
+		frame_groups,
+		card_id
+	};
+
+	Ok(data_pack)
+}
+
+pub fn get_table_offsets(data: &DataView) -> Result<(Vec<usize>, Vec<usize>), FormatError> {
+	if data.len() < 80 {
+		return Err(data.error(0, None, "too short for table offsets"));
+	}
+
+	let mut table_offsets = Vec::new();
+	let mut table_sizes = Vec::new();
+	for i in 0..20 {
+		let offset = data.get_u32(i*4)? as usize * 2;
+		table_offsets.push(offset);
+	}
+
+	for i in 0..20 {
+		if i < 19 {
+			if table_offsets[i+1] < table_offsets[i] {
+				return Err(data.error(i*4, None, &format!("table {} at 0x{:X} ends before it starts", i, data.offset + table_offsets[i])));
+			}
+			table_sizes.push(table_offsets[i+1] - table_offsets[i]);
+		} else {
+			if data.len() < table_offsets[i] {
+				return Err(data.error(i*4, None, &format!("table {} at 0x{:X} is past the end of the data pack", i, data.offset + table_offsets[i])));
+			}
+			table_sizes.push(2);
+		}
+	}
+
+	Ok((table_offsets, table_sizes))
+}
+
+pub fn save_data_pack(data_pack: &DataPack, offset: usize) -> Result<Vec<u8>, FormatError> {
+	let mut tables: Vec<Vec<u8>> = vec![vec![]; 20];
+
+	let (table1_offsets, table1_data) = table1::save_entities(&data_pack.table1)?;
+	tables[0] = table1_offsets;
+	tables[1] = table1_data;
+
+	tables[2] = particle_emitter::save_particle_emitters(&data_pack.particle_emitters)?;
+
+	let (scene_offsets, scene_layer_offsets, scene_data) = scene::save_scenes(&data_pack.scenes)?;
+	tables[3] = scene_offsets;
+	tables[4] = scene_layer_offsets;
+	tables[5] = scene_data;
+
+	let (string_offsets, string_data) = tamastring::save_tamastrings(&data_pack.tamastrings)?;
+	tables[6] = string_data;
+	tables[7] = string_offsets;
+
+	let (table9_offsets, table9_data) = table9::save_entities(&data_pack.table9)?;
+	tables[8] = table9_offsets;
+	tables[9] = table9_data;
+
+	tables[10] = item::save_items(&data_pack.items)?;
+
+	tables[11] = character::save_characters(&data_pack.characters)?;
+
+	let (graphics_node_offsets, graphics_node_data) = graphics_node::save_graphics_nodes(&data_pack.graphics_nodes)?;
+	tables[13] = graphics_node_offsets;
+	tables[14] = graphics_node_data;
+
+	let (frame_layer_offsets, frame_layer_data, frame_group_data) = frame::save_frame_groups(&data_pack.frame_groups)?;
+	tables[15] = frame_layer_data;
+	tables[16] = frame_layer_offsets;
+	tables[18] = frame_group_data;
+
+	tables[19] = data_pack.card_id.to_le_bytes().to_vec();
+
+	let mut offsets = Vec::new();
+	let mut data = vec![0; 80];
+	for (i, table) in tables.iter().enumerate() {
+		offsets.extend_from_slice(&(data.len() as u32 / 2).to_le_bytes());
+		if i == 12 || i == 17 {
+			let mut next_table_offset = data.len();
+			while (offset + next_table_offset) % 8 != 0 {
+				next_table_offset += 1;
+			}
+			let padding_size = next_table_offset - data.len();
+			data.extend_from_slice(&vec![0; padding_size]);
+		} else {
+			data.extend_from_slice(table);
+		}
+	}
+
+	data.splice(0..80, offsets);
+
+	Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn card_ids(card_id: u8, entity_ids: impl IntoIterator<Item = u16>) -> Vec<EntityId> {
+		entity_ids.into_iter().map(|entity_id| EntityId { card_id: Some(card_id), entity_id }).collect()
+	}
+
+	#[test]
+	fn next_entity_id_starts_at_zero() {
+		assert_eq!(get_next_entity_id(&[], Some(3)), Some(EntityId { card_id: Some(3), entity_id: 0 }));
+		assert_eq!(get_next_entity_id(&[], None), Some(EntityId { card_id: None, entity_id: 0 }));
+	}
+
+	#[test]
+	fn next_entity_id_goes_after_the_highest() {
+		let ids = card_ids(3, [0, 1, 5]);
+		assert_eq!(get_next_entity_id(&ids, Some(3)), Some(EntityId { card_id: Some(3), entity_id: 6 }));
+	}
+
+	#[test]
+	fn next_entity_id_ignores_other_namespaces() {
+		let mut ids = card_ids(3, [0, 1]);
+		ids.extend(card_ids(4, [200]));
+		ids.push(EntityId { card_id: None, entity_id: 300 });
+		assert_eq!(get_next_entity_id(&ids, Some(3)), Some(EntityId { card_id: Some(3), entity_id: 2 }));
+		assert_eq!(get_next_entity_id(&ids, None), Some(EntityId { card_id: None, entity_id: 301 }));
+	}
+
+	#[test]
+	fn next_entity_id_reuses_a_gap_once_full() {
+		let ids = card_ids(3, (0..=0xff).filter(|id| *id != 42));
+		assert_eq!(get_next_entity_id(&ids, Some(3)), Some(EntityId { card_id: Some(3), entity_id: 42 }));
+	}
+
+	#[test]
+	fn next_entity_id_runs_out() {
+		let ids = card_ids(3, 0..=0xff);
+		assert_eq!(get_next_entity_id(&ids, Some(3)), None);
+	}
+}
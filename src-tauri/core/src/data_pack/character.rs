@@ -0,0 +1,305 @@
+use serde::{ Serialize, Deserialize };
+
+use super::{ DataPack, EntityId, get_next_entity_id };
+use crate::data_view::{ DataView, words_to_bytes, resize_words };
+use crate::error::FormatError;
+use crate::sprite_pack::SpritePack;
+use crate::text::{ Text, CharEncoding };
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CharacterType {
+	Unknown,
+	Egg,
+	Baby,
+	Child,
+	Teen,
+	Adult,
+	Npc
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Gender {
+	Female,
+	Male
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Character {
+	pub id: EntityId,
+	pub character_type: CharacterType,
+	pub name: Text,
+	pub profile_image_id: EntityId,
+	pub icon_image_id: EntityId,
+	pub composition_id: EntityId,
+	pub unknown1: EntityId,
+	pub pronoun: Text,
+	pub statement: Text,
+	pub question1: Text,
+	pub question2: Text,
+	pub unknown2: u16,
+	pub unknown3: u16,
+	pub global_id: EntityId,
+	pub unknown4: u16,
+	pub unknown5: u16,
+	pub unknown6: u16,
+	pub unknown7: u16,
+	pub gender: Gender
+}
+
+impl Character {
+	// the format has no "unset" image or frame group ID (image set 0 is a real image on cards),
+	// so a new character points at image set 0 and frame group 0 until others are picked
+	pub fn new(id: EntityId, global_id: EntityId) -> Character {
+		let unset_id = EntityId { card_id: id.card_id, entity_id: 0 };
+		let empty_text = || Text { data: Vec::new(), string: String::new() };
+		Character {
+			character_type: CharacterType::Adult,
+			name: empty_text(),
+			profile_image_id: unset_id.clone(),
+			icon_image_id: unset_id.clone(),
+			composition_id: unset_id.clone(),
+			unknown1: unset_id,
+			pronoun: empty_text(),
+			statement: empty_text(),
+			question1: empty_text(),
+			question2: empty_text(),
+			unknown2: 0,
+			unknown3: 0,
+			global_id,
+			unknown4: 0,
+			unknown5: 0,
+			unknown6: 0,
+			unknown7: 0,
+			gender: Gender::Female,
+			id
+		}
+	}
+
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		self.id.set_card_id(old_card_id, new_card_id);
+		self.profile_image_id.set_card_id(old_card_id, new_card_id);
+		self.icon_image_id.set_card_id(old_card_id, new_card_id);
+		self.composition_id.set_card_id(old_card_id, new_card_id);
+		self.unknown1.set_card_id(old_card_id, new_card_id);
+		self.global_id.set_card_id(old_card_id, new_card_id);
+	}
+}
+
+fn id_exhausted(entity: &str) -> FormatError {
+	FormatError::IdExhausted { entity: entity.to_string() }
+}
+
+// global IDs aren't tied to a card, so this can only avoid the ones used in the same bin
+pub fn get_next_global_id(data_pack: &DataPack) -> Option<EntityId> {
+	get_next_entity_id(data_pack.characters.iter().map(|character| &character.global_id), None)
+}
+
+pub fn add_new_character(data_pack: &mut DataPack, card_id: Option<u8>) -> Result<usize, FormatError> {
+	let id = get_next_entity_id(data_pack.characters.iter().map(|character| &character.id), card_id)
+		.ok_or_else(|| id_exhausted("character"))?;
+	let global_id = get_next_global_id(data_pack).ok_or_else(|| id_exhausted("global"))?;
+	data_pack.characters.push(Character::new(id, global_id));
+	Ok(data_pack.characters.len() - 1)
+}
+
+// with copy_assets, the copy gets its own frame group and profile/icon images, so it can be
+// reworked into a new evolution without changing the original character
+pub fn copy_character(data_pack: &mut DataPack, sprite_pack: &mut SpritePack, index: usize, card_id: Option<u8>, copy_assets: bool) -> Result<usize, FormatError> {
+	let mut character = data_pack.characters.get(index)
+		.ok_or_else(|| FormatError::NotFound { entity: "Character".to_string(), index })?.clone();
+	character.id = get_next_entity_id(data_pack.characters.iter().map(|character| &character.id), card_id)
+		.ok_or_else(|| id_exhausted("character"))?;
+	character.global_id = get_next_global_id(data_pack).ok_or_else(|| id_exhausted("global"))?;
+
+	if copy_assets {
+		let max_entity_id = if card_id.is_some() { 0xff } else { 0x7fff };
+
+		let composition_id = &mut character.composition_id;
+		if composition_id.card_id == card_id {
+			if let Some(frame_group) = data_pack.frame_groups.get(composition_id.entity_id as usize) {
+				if data_pack.frame_groups.len() > max_entity_id {
+					return Err(id_exhausted("frame group"));
+				}
+				composition_id.entity_id = data_pack.frame_groups.len() as u16;
+				data_pack.frame_groups.push(frame_group.clone());
+			}
+		}
+
+		let mut copied_images: Vec<(u16, u16)> = Vec::new();
+		for image_id in [&mut character.profile_image_id, &mut character.icon_image_id] {
+			if image_id.card_id != card_id {
+				continue;
+			}
+			if let Some((_, new_entity_id)) = copied_images.iter().find(|(old_entity_id, _)| *old_entity_id == image_id.entity_id) {
+				image_id.entity_id = *new_entity_id;
+				continue;
+			}
+			if let Some(image_set) = sprite_pack.image_sets.get(image_id.entity_id as usize) {
+				if sprite_pack.image_sets.len() > max_entity_id {
+					return Err(id_exhausted("image"));
+				}
+				let new_entity_id = sprite_pack.image_sets.len() as u16;
+				let mut image_set = image_set.clone();
+				image_set.original_index = new_entity_id as usize;
+				sprite_pack.image_sets.push(image_set);
+				copied_images.push((image_id.entity_id, new_entity_id));
+				image_id.entity_id = new_entity_id;
+			}
+		}
+	}
+
+	data_pack.characters.push(character);
+	Ok(data_pack.characters.len() - 1)
+}
+
+// items unlock characters by index, so later characters move down one and items that unlocked this one unlock nothing
+pub fn remove_character(data_pack: &mut DataPack, index: usize) -> Result<Character, FormatError> {
+	if index >= data_pack.characters.len() {
+		return Err(FormatError::NotFound { entity: "Character".to_string(), index });
+	}
+	let character = data_pack.characters.remove(index);
+	for item in data_pack.items.iter_mut() {
+		if let Some(character_index) = item.unlocked_character {
+			let character_index = character_index as usize;
+			if character_index == index {
+				item.unlocked_character = None;
+			} else if character_index > index {
+				item.unlocked_character = Some(character_index as u16 - 1);
+			}
+		}
+	}
+	Ok(character)
+}
+
+pub fn get_character_references(data_pack: &DataPack, index: usize) -> Vec<String> {
+	let Some(character) = data_pack.characters.get(index) else { return Vec::new() };
+	let mut references: Vec<String> = data_pack.items.iter().enumerate()
+		.filter(|(_, item)| item.unlocked_character == Some(index as u16))
+		.map(|(i, item)| format!("Item {} ({}) unlocks it", i, item.name.string))
+		.collect();
+	references.extend(data_pack.find_id_in_unknown_tables(&character.id));
+	references
+}
+
+pub fn get_characters(data: &DataView, char_codes: &[CharEncoding]) -> Result<Vec<Character>, FormatError> {
+	let mut characters = Vec::new();
+
+	let mut i = 0;
+	while i + 96 <= data.len() {
+		let id = EntityId::new(data.get_u16(i)?);
+		let character_type = match data.get_u16(i + 2)? {
+			1 => CharacterType::Egg,
+			2 => CharacterType::Baby,
+			3 => CharacterType::Child,
+			4 => CharacterType::Teen,
+			5 => CharacterType::Adult,
+			6 => CharacterType::Npc,
+			_ => CharacterType::Unknown,
+		};
+		let name = data.get_text(char_codes, i + 4, 10);
+		let profile_image_id = EntityId::new(data.get_u16(i + 24)?);
+		let icon_image_id = EntityId::new(data.get_u16(i + 26)?);
+		let composition_id = EntityId::new(data.get_u16(i + 28)?);
+		let unknown1 = EntityId::new(data.get_u16(i + 30)?);
+		let pronoun = data.get_text(char_codes, i + 32, 6);
+		let statement = data.get_text(char_codes, i + 44, 6);
+		let question1 = data.get_text(char_codes, i + 56, 6);
+		let question2 = data.get_text(char_codes, i + 68, 6);
+		let unknown2 = data.get_u16(i + 80)?;
+		let unknown3 = data.get_u16(i + 82)?;
+		let global_id = EntityId::new(data.get_u16(i + 84)?);
+		let unknown4 = data.get_u16(i + 86)?;
+		let unknown5 = data.get_u16(i + 88)?;
+		let unknown6 = data.get_u16(i + 90)?;
+		let unknown7 = data.get_u16(i + 92)?;
+		let gender = match data.get_u16(i + 94)? {
+			0 => Gender::Female,
+			_ => Gender::Male
+		};
+
+		characters.push(Character {
+			id,
+			character_type,
+			name,
+			profile_image_id,
+			icon_image_id,
+			composition_id,
+			unknown1,
+			pronoun,
+			statement,
+			question1,
+			question2,
+			unknown2,
+			unknown3,
+			global_id,
+			unknown4,
+			unknown5,
+			unknown6,
+			unknown7,
+			gender
+		});
+
+		i += 96;
+	}
+
+	Ok(characters)
+}
+
+pub fn save_characters(characters: &[Character]) -> Result<Vec<u8>, FormatError> {
+	let mut words: Vec<u16> = Vec::new();
+
+	for character in characters {
+		words.push(character.id.to_word());
+		words.push(match character.character_type {
+			CharacterType::Unknown => 0,
+			CharacterType::Egg => 1,
+			CharacterType::Baby => 2,
+			CharacterType::Child => 3,
+			CharacterType::Teen => 4,
+			CharacterType::Adult => 5,
+			CharacterType::Npc => 6
+		});
+		words = [words, resize_words(&character.name.data, 10)].concat();
+		words.push(character.profile_image_id.to_word());
+		words.push(character.icon_image_id.to_word());
+		words.push(character.composition_id.to_word());
+		words.push(character.unknown1.to_word());
+		words = [words, resize_words(&character.pronoun.data, 6)].concat();
+		words = [words, resize_words(&character.statement.data, 6)].concat();
+		words = [words, resize_words(&character.question1.data, 6)].concat();
+		words = [words, resize_words(&character.question2.data, 6)].concat();
+		words.push(character.unknown2);
+		words.push(character.unknown3);
+		words.push(character.global_id.to_word());
+		words.push(character.unknown4);
+		words.push(character.unknown5);
+		words.push(character.unknown6);
+		words.push(character.unknown7);
+		words.push(match character.gender {
+			Gender::Female => 0,
+			Gender::Male => 1
+		});
+	}
+
+	Ok(words_to_bytes(&words))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_util::get_test_image_set;
+
+	#[test]
+	fn copy_gets_its_own_image_set_zero() {
+		let mut data_pack = DataPack::new(5);
+		let mut sprite_pack = SpritePack { image_sets: vec![get_test_image_set()], colors: Vec::new() };
+		add_new_character(&mut data_pack, Some(5)).unwrap();
+
+		let index = copy_character(&mut data_pack, &mut sprite_pack, 0, Some(5), true).unwrap();
+		let copy = &data_pack.characters[index];
+		assert_eq!(sprite_pack.image_sets.len(), 2);
+		assert_eq!(copy.profile_image_id.entity_id, 1);
+		assert_eq!(copy.icon_image_id.entity_id, 1);
+		assert_eq!(data_pack.characters[0].profile_image_id.entity_id, 0);
+	}
+}
@@ -0,0 +1,350 @@
+use serde::{ Serialize, Deserialize };
+
+use super::EntityId;
+use crate::data_view::DataView;
+use crate::error::FormatError;
+use crate::sprite_pack::SpritePack;
+
+pub const FRAMES_PER_GROUP: usize = 53;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum FrameLayerType {
+	Unknown,
+	Face,
+	Npc,
+	Body,
+	HeadAccessory,
+	FaceAccessory,
+	BodyAccessory,
+	DirtClouds,
+	HandAccessory
+}
+
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct FrameLayer {
+	pub x: Option<i16>,
+	pub y: Option<i16>,
+	pub subimage_index: u16,
+	pub layer_type: Option<FrameLayerType>,
+	pub image_id: Option<EntityId>,
+	pub unknown1: u16,
+	pub unknown2: u16,
+	pub unknown3: u16
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum Frame {
+	Implicit,
+	Explicit(Vec<FrameLayer>)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FrameGroup {
+	pub frames: Vec<Frame>
+}
+
+impl FrameGroup {
+	pub fn new() -> FrameGroup {
+		FrameGroup { frames: vec![Frame::Implicit; FRAMES_PER_GROUP] }
+	}
+
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		for frame in self.frames.iter_mut() {
+			if let Frame::Explicit(layers) = frame {
+				for layer in layers.iter_mut() {
+					if let Some(image_id) = &mut layer.image_id {
+						image_id.set_card_id(old_card_id, new_card_id);
+					}
+				}
+			}
+		}
+	}
+}
+
+impl Default for FrameGroup {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+// accessory and dirt cloud layers are placeholders the game fills in, and images from another
+// card or the firmware aren't in this sprite pack, so only this bin's own images can be checked
+pub fn check_frame(frame: &Frame, sprite_pack: &SpritePack, card_id: Option<u8>) -> Result<(), FormatError> {
+	let Frame::Explicit(layers) = frame else { return Ok(()) };
+	for (i, layer) in layers.iter().enumerate() {
+		let is_placeholder = matches!(layer.layer_type, Some(FrameLayerType::HeadAccessory | FrameLayerType::FaceAccessory
+			| FrameLayerType::BodyAccessory | FrameLayerType::HandAccessory | FrameLayerType::DirtClouds));
+		let Some(image_id) = &layer.image_id else { continue };
+		if is_placeholder || image_id.card_id != card_id {
+			continue;
+		}
+		let image_set = sprite_pack.image_sets.get(image_id.entity_id as usize)
+			.ok_or_else(|| FormatError::FrameLayer {
+				layer_index: i,
+				reason: format!("uses image {}, but there are only {} images", image_id.entity_id, sprite_pack.image_sets.len())
+			})?;
+		if layer.subimage_index as usize >= image_set.subimages.len() {
+			return Err(FormatError::FrameLayer {
+				layer_index: i,
+				reason: format!("uses subimage {} of image {}, but it only has {}", layer.subimage_index, image_id.entity_id, image_set.subimages.len())
+			});
+		}
+	}
+	Ok(())
+}
+
+pub fn get_frame_layers(data: &DataView) -> Result<Vec<FrameLayer>, FormatError> {
+	let mut frame_layers = Vec::new();
+
+	let mut i = 0;
+	while i + 2 <= data.len() {
+		let mut layer = FrameLayer::default();
+
+		let bitmask = data.get_u16(i)?;
+		let mut flags = [false; 16];
+		for (i, flag) in flags.iter_mut().enumerate() {
+			*flag = bitmask & (1 << i) != 0;
+		}
+
+		if flags[0] {
+			i += 2;
+			if i < data.len() {
+				layer.x = Some(data.get_i16(i)?);
+			}
+		}
+
+		if flags[1] {
+			i += 2;
+			if i < data.len() {
+				layer.y = Some(data.get_i16(i)?);
+			}
+		}
+
+		if flags[2] {
+			i += 2;
+			if i < data.len() {
+				layer.subimage_index = data.get_u16(i)?;
+			}
+		}
+
+		if flags[4] {
+			i += 2;
+			if i < data.len() {
+				layer.unknown1 = data.get_u16(i)?;
+			}
+		}
+
+		if flags[5] {
+			i += 2;
+			if i < data.len() {
+				layer.unknown2 = data.get_u16(i)?;
+			}
+		}
+
+		if flags[8] {
+			i += 2;
+			if i < data.len() {
+				layer.unknown3 = data.get_u16(i)?;
+			}
+		}
+
+		if flags[9] {
+			i += 2;
+			if i < data.len() {
+				layer.layer_type = Some(match data.get_u16(i)? {
+					1 => FrameLayerType::Face,
+					2 => FrameLayerType::Npc,
+					3 => FrameLayerType::Body,
+					4 => FrameLayerType::HeadAccessory,
+					6 => FrameLayerType::FaceAccessory,
+					8 => FrameLayerType::BodyAccessory,
+					9 => FrameLayerType::DirtClouds,
+					10 => FrameLayerType::HandAccessory,
+					_ => FrameLayerType::Unknown,
+				});
+			}
+		}
+
+		if flags[10] {
+			i += 2;
+			if i < data.len() {
+				layer.image_id = Some(EntityId::new(data.get_u16(i)?));
+			}
+		}
+
+		frame_layers.push(layer);
+
+		i += 2;
+	}
+
+	Ok(frame_layers)
+}
+
+pub fn get_frame_groups(data: &DataView, layers: Vec<FrameLayer>) -> Result<Vec<FrameGroup>, FormatError> {
+	let mut all_frames = Vec::new();
+	let mut i = 0;
+	while i + 4 <= data.len() {
+		let layer_index = data.get_u16(i)? as usize;
+		let frame = if layer_index == 0xffff {
+			Frame::Implicit
+		} else {
+			let num_layers = data.get_u16(i + 2)? as usize;
+			let frame_layers = layers.get(layer_index..(layer_index + num_layers))
+				.ok_or_else(|| data.error(i, Some(i / 4), &format!("uses frame layers {}..{} but only {} exist",
+					layer_index, layer_index + num_layers, layers.len())))?;
+			Frame::Explicit(frame_layers.to_vec())
+		};
+		all_frames.push(frame);
+		i += 4;
+	}
+
+	let mut frame_groups = Vec::new();
+	for i in 0..(all_frames.len() / FRAMES_PER_GROUP) {
+		let frames = all_frames[(i*FRAMES_PER_GROUP)..((i+1)*FRAMES_PER_GROUP)].to_vec();
+		frame_groups.push(FrameGroup { frames })
+	}
+
+	Ok(frame_groups)
+}
+
+pub fn save_frame_groups(frame_groups: &[FrameGroup]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), FormatError> {
+	let mut frame_layer_offsets = Vec::new();
+	let mut frame_layer_data = Vec::new();
+	let mut frame_group_data = Vec::new();
+	let mut frame_layer_index = 0_u16;
+
+	for frame_group in frame_groups {
+		for frame in &frame_group.frames {
+			match frame {
+				Frame::Implicit => {
+					frame_group_data.extend_from_slice(&[0xFF, 0xFF, 0, 0]);
+				},
+				Frame::Explicit(frame_layers) => {
+					frame_group_data.extend_from_slice(&frame_layer_index.to_le_bytes());
+					frame_group_data.extend_from_slice(&(frame_layers.len() as u16).to_le_bytes());
+					for frame_layer in frame_layers {
+						frame_layer_offsets.extend_from_slice(&(frame_layer_data.len() as u32 / 2).to_le_bytes());
+						let this_layer_data = save_frame_layer(frame_layer)?;
+						frame_layer_data.extend_from_slice(&this_layer_data);
+						frame_layer_index += 1;
+					}
+				}
+			}
+		}
+	}
+
+	frame_layer_offsets.extend_from_slice(&(frame_layer_data.len() as u32 / 2).to_le_bytes());
+
+	Ok((frame_layer_offsets, frame_layer_data, frame_group_data))
+}
+
+fn save_frame_layer(frame_layer: &FrameLayer) -> Result<Vec<u8>, FormatError> {
+	let mut bitmask: u16 = 0;
+	let mut data = vec![0, 0];
+
+	if let Some(x) = frame_layer.x {
+		bitmask |= 1 << 0;
+		data.extend_from_slice(&x.to_le_bytes());
+	}
+
+	if let Some(y) = frame_layer.y {
+		bitmask |= 1 << 1;
+		data.extend_from_slice(&y.to_le_bytes());
+	}
+
+	if frame_layer.subimage_index > 0 {
+		bitmask |= 1 << 2;
+		data.extend_from_slice(&frame_layer.subimage_index.to_le_bytes());
+	}
+
+	if frame_layer.unknown1 > 0 {
+		bitmask |= 1 << 4;
+		data.extend_from_slice(&frame_layer.unknown1.to_le_bytes());
+	}
+
+	if frame_layer.unknown2 > 0 {
+		bitmask |= 1 << 5;
+		data.extend_from_slice(&frame_layer.unknown2.to_le_bytes());
+	}
+
+	if frame_layer.unknown3 > 0 {
+		bitmask |= 1 << 8;
+		data.extend_from_slice(&frame_layer.unknown3.to_le_bytes());
+	}
+
+	if let Some(layer_type) = &frame_layer.layer_type {
+		bitmask |= 1 << 9;
+		let layer_id: u16 = match layer_type {
+			FrameLayerType::Face => 1,
+			FrameLayerType::Npc => 2,
+			FrameLayerType::Body => 3,
+			FrameLayerType::HeadAccessory => 4,
+			FrameLayerType::FaceAccessory => 6,
+			FrameLayerType::BodyAccessory => 8,
+			FrameLayerType::DirtClouds => 9,
+			FrameLayerType::HandAccessory => 10,
+			FrameLayerType::Unknown => 0,
+		};
+		data.extend_from_slice(&layer_id.to_le_bytes());
+	}
+
+	if let Some(image_id) = &frame_layer.image_id {
+		bitmask |= 1 << 10;
+		data.extend_from_slice(&image_id.to_word().to_le_bytes());
+	}
+
+	data.splice(0..2, bitmask.to_le_bytes());
+
+	Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_util::get_test_image_set;
+
+	fn get_test_sprite_pack() -> SpritePack {
+		SpritePack { image_sets: vec![get_test_image_set()], colors: Vec::new() }
+	}
+
+	fn get_layer(layer_type: Option<FrameLayerType>, card_id: Option<u8>, entity_id: u16, subimage_index: u16) -> FrameLayer {
+		FrameLayer { layer_type, image_id: Some(EntityId { card_id, entity_id }), subimage_index, ..Default::default() }
+	}
+
+	fn get_error_layer(frame: &Frame) -> Option<usize> {
+		match check_frame(frame, &get_test_sprite_pack(), Some(5)) {
+			Err(FormatError::FrameLayer { layer_index, .. }) => Some(layer_index),
+			_ => None
+		}
+	}
+
+	#[test]
+	fn valid_frames_pass() {
+		let frame = Frame::Explicit(vec![FrameLayer::default(), get_layer(Some(FrameLayerType::Body), Some(5), 0, 1)]);
+		assert!(check_frame(&frame, &get_test_sprite_pack(), Some(5)).is_ok());
+		assert!(check_frame(&Frame::Implicit, &get_test_sprite_pack(), Some(5)).is_ok());
+	}
+
+	#[test]
+	fn missing_image_is_caught() {
+		let frame = Frame::Explicit(vec![FrameLayer::default(), get_layer(Some(FrameLayerType::Body), Some(5), 1, 0)]);
+		assert_eq!(get_error_layer(&frame), Some(1));
+	}
+
+	#[test]
+	fn missing_subimage_is_caught() {
+		let frame = Frame::Explicit(vec![get_layer(Some(FrameLayerType::Face), Some(5), 0, 2)]);
+		assert_eq!(get_error_layer(&frame), Some(0));
+	}
+
+	// placeholders and images from the firmware or another card can't be checked against this sprite pack
+	#[test]
+	fn other_images_are_skipped() {
+		let frame = Frame::Explicit(vec![
+			get_layer(Some(FrameLayerType::HeadAccessory), Some(5), 9, 9),
+			get_layer(Some(FrameLayerType::Body), None, 9, 9),
+			get_layer(Some(FrameLayerType::Body), Some(6), 9, 9)
+		]);
+		assert!(check_frame(&frame, &get_test_sprite_pack(), Some(5)).is_ok());
+	}
+}
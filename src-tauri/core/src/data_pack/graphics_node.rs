@@ -1,43 +1,40 @@
-use std::error::Error;
-
 use crate::data_view::{ DataView, words_to_bytes };
+use crate::error::FormatError;
 
-#[derive(Clone, serde::Serialize)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub struct GraphicsNode {
 	pub data: Vec<u16>
 }
 
-pub fn get_graphics_nodes_offsets(data: &DataView) -> Vec<usize> {
+pub fn get_graphics_nodes_offsets(data: &DataView) -> Result<Vec<usize>, FormatError> {
 	let mut offsets = Vec::new();
 	for i in 0..(data.len()/2) {
-		let offset = data.get_u16(i*2) as usize * 4;
+		let offset = data.get_u16(i*2)? as usize * 4;
 		offsets.push(offset);
 	}
 
-	offsets
+	Ok(offsets)
 }
 
-pub fn get_graphics_nodes(data: &DataView, offsets: Vec<usize>) -> Vec<GraphicsNode> {
+pub fn get_graphics_nodes(data: &DataView, offsets: Vec<usize>) -> Result<Vec<GraphicsNode>, FormatError> {
 	let mut graphics_nodes = Vec::new();
 
 	for (i, offset) in offsets.iter().enumerate() {
-		let size = if i+1 < offsets.len() {
-			offsets[i+1] - offset
-		} else {
-			data.len() - offset
-		};
-		let graphics_node_data = data.chunk(offsets[i], size);
+		let end = if i+1 < offsets.len() { offsets[i+1] } else { data.len() };
+		let size = end.checked_sub(*offset)
+			.ok_or_else(|| data.error(*offset, Some(i), "graphics node starts past the end of the table"))?;
+		let graphics_node_data = data.chunk(*offset, size)?;
 		let mut data_u16s = Vec::new();
 		for j in 0..(graphics_node_data.len()/2) {
-			data_u16s.push(graphics_node_data.get_u16(j*2));
+			data_u16s.push(graphics_node_data.get_u16(j*2)?);
 		}
 		graphics_nodes.push(GraphicsNode { data: data_u16s })
 	}
 
-	graphics_nodes
+	Ok(graphics_nodes)
 }
 
-pub fn save_graphics_nodes(graphics_nodes: &[GraphicsNode]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+pub fn save_graphics_nodes(graphics_nodes: &[GraphicsNode]) -> Result<(Vec<u8>, Vec<u8>), FormatError> {
 	let mut offsets = Vec::new();
 	let mut data = Vec::new();
 
@@ -0,0 +1,308 @@
+use serde::{ Serialize, Deserialize };
+
+use super::{ DataPack, EntityId };
+use crate::data_view::{ DataView, words_to_bytes, resize_words };
+use crate::error::FormatError;
+use crate::text::{ Text, CharEncoding };
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Item {
+	pub id: EntityId,
+	pub item_type: ItemType,
+	pub name: Text,
+	pub image_id: Option<EntityId>,
+	pub worn_image_id: Option<EntityId>,
+	pub close_image_id: Option<EntityId>,
+	pub animation_id: Option<EntityId>,
+	pub price: u16,
+	pub unknown1: u16,
+	pub unknown2: u16,
+	pub unknown3: u16,
+	pub unlocked_character: Option<u16>,
+	pub game_type: Option<GameType>
+}
+
+impl Item {
+	pub fn new(id: EntityId) -> Item {
+		Item {
+			id,
+			item_type: ItemType::Meal,
+			name: Text { data: Vec::new(), string: String::new() },
+			image_id: None,
+			worn_image_id: None,
+			close_image_id: None,
+			animation_id: None,
+			price: 0,
+			unknown1: 0,
+			unknown2: 0,
+			unknown3: 0,
+			unlocked_character: None,
+			game_type: None
+		}
+	}
+
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		self.id.set_card_id(old_card_id, new_card_id);
+		if let Some(image_id) = &mut self.image_id {
+			image_id.set_card_id(old_card_id, new_card_id);
+		}
+		if let Some(worn_image_id) = &mut self.worn_image_id {
+			worn_image_id.set_card_id(old_card_id, new_card_id);
+		}
+		if let Some(close_image_id) = &mut self.close_image_id {
+			close_image_id.set_card_id(old_card_id, new_card_id);
+		}
+		if let Some(animation_id) = &mut self.animation_id {
+			animation_id.set_card_id(old_card_id, new_card_id);
+		}
+	}
+}
+
+// everything that points at the item's ID; none of these fields are decoded, so a matching word is only a likely
+// reference, and firmware IDs are too small to tell apart from ordinary numbers
+pub fn get_item_references(data_pack: &DataPack, index: usize) -> Vec<String> {
+	let Some(item) = data_pack.items.get(index) else { return Vec::new() };
+	if item.id.card_id.is_none() {
+		return Vec::new();
+	}
+	let word = item.id.to_word();
+	let mut references = Vec::new();
+
+	for (i, other) in data_pack.items.iter().enumerate() {
+		if i != index && [other.unknown1, other.unknown2, other.unknown3].contains(&word) {
+			references.push(format!("Item {} ({}) may refer to it", i, other.name.string));
+		}
+	}
+	for (i, character) in data_pack.characters.iter().enumerate() {
+		let words = [character.unknown1.to_word(), character.unknown2, character.unknown3, character.unknown4,
+			character.unknown5, character.unknown6, character.unknown7];
+		if words.contains(&word) {
+			references.push(format!("Character {} ({}) may refer to it", i, character.name.string));
+		}
+	}
+	for tamastring in data_pack.tamastrings.iter() {
+		if tamastring.field1 == word || tamastring.field2 == word {
+			references.push(format!("Dialog string {} may refer to it", tamastring.id.entity_id));
+		}
+	}
+	for (i, graphics_node) in data_pack.graphics_nodes.iter().enumerate() {
+		if graphics_node.data.contains(&word) {
+			references.push(format!("Graphics node {} may refer to it", i));
+		}
+	}
+	for (i, particle_emitter) in data_pack.particle_emitters.iter().enumerate() {
+		if particle_emitter.data.contains(&word) {
+			references.push(format!("Particle emitter {} may refer to it", i));
+		}
+	}
+
+	references.extend(data_pack.find_id_in_unknown_tables(&item.id));
+	references
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub enum ItemType {
+	Unknown,
+	Meal,
+	Snack,
+	Toy,
+	AccessoryHead,
+	AccessoryFace,
+	AccessoryBody,
+	AccessoryHand,
+	Room,
+	Game
+}
+
+impl ItemType {
+	pub fn is_accessory(&self) -> bool {
+		*self == ItemType::AccessoryHead || *self == ItemType::AccessoryFace || *self == ItemType::AccessoryBody || *self == ItemType::AccessoryHand
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum GameType {
+	Unknown,
+	GuessingGame,
+	TimingGame,
+	MemoryGame,
+	DodgingGame,
+	ShakingGame,
+	SwipingGame
+}
+
+pub fn get_items(data: &DataView, char_codes: &[CharEncoding]) -> Result<Vec<Item>, FormatError> {
+	let mut items = Vec::new();
+
+	let mut i = 0;
+	while i + 42 <= data.len() {
+		let id = EntityId::new(data.get_u16(i)?);
+		let item_type = match data.get_u16(i + 2)? {
+			0 => ItemType::Meal,
+			1 => ItemType::Snack,
+			2 => ItemType::Toy,
+			3 => ItemType::AccessoryHead,
+			4 => ItemType::AccessoryFace,
+			5 => ItemType::AccessoryBody,
+			6 => ItemType::AccessoryHand,
+			7 => ItemType::Room,
+			8 => ItemType::Game,
+			_ => ItemType::Unknown
+		};
+		let name = data.get_text(char_codes, i + 4, 10);
+		let image_id = if data.get_u16(i + 24)? > 0 {
+			Some(EntityId::new(data.get_u16(i + 24)?))
+		} else {
+			None
+		};
+		let worn_image_id = if data.get_u16(i + 26)? > 0 {
+			Some(EntityId::new(data.get_u16(i + 26)?))
+		} else {
+			None
+		};
+		let close_image_id = if data.get_u16(i + 28)? > 0 {
+			Some(EntityId::new(data.get_u16(i + 28)?))
+		} else {
+			None
+		};
+		let animation_id = if data.get_u16(i + 30)? > 0 {
+			Some(EntityId::new(data.get_u16(i + 30)?))
+		} else {
+			None
+		};
+		let price = data.get_u16(i + 32)?;
+		let unknown1 = data.get_u16(i + 34)?;
+		let unknown2 = data.get_u16(i + 36)?;
+		let unknown3 = data.get_u16(i + 38)?;
+		let unlocked_character = if item_type == ItemType::Game || data.get_u16(i + 40)? == 0 {
+			None
+		} else {
+			Some(data.get_u16(i + 40)?)
+		};
+		let game_type = if item_type == ItemType::Game {
+			Some(match data.get_u16(i + 40)? {
+				10 => GameType::GuessingGame,
+				11 => GameType::TimingGame,
+				12 => GameType::MemoryGame,
+				13 => GameType::DodgingGame,
+				14 => GameType::ShakingGame,
+				15 => GameType::SwipingGame,
+				_ => GameType::Unknown
+			})
+		} else {
+			None
+		};
+
+		items.push(Item {
+			id,
+			item_type,
+			name,
+			image_id,
+			worn_image_id,
+			close_image_id,
+			animation_id,
+			price,
+			unknown1,
+			unknown2,
+			unknown3,
+			unlocked_character,
+			game_type
+		});
+
+		i += 42;
+	}
+
+	Ok(items)
+}
+
+pub fn save_items(items: &[Item]) -> Result<Vec<u8>, FormatError> {
+	let mut words: Vec<u16> = Vec::new();
+
+	for item in items {
+		words.push(item.id.to_word());
+		words.push(match item.item_type {
+			ItemType::Meal => 0,
+			ItemType::Snack => 1,
+			ItemType::Toy => 2,
+			ItemType::AccessoryHead => 3,
+			ItemType::AccessoryFace => 4,
+			ItemType::AccessoryBody => 5,
+			ItemType::AccessoryHand => 6,
+			ItemType::Room => 7,
+			ItemType::Game => 8,
+			ItemType::Unknown => 9,
+		});
+		words = [words, resize_words(&item.name.data, 10)].concat();
+		words.push(match &item.image_id {
+			Some(id) => id.to_word(),
+			None => 0
+		});
+		words.push(match &item.worn_image_id {
+			Some(id) => id.to_word(),
+			None => 0
+		});
+		words.push(match &item.close_image_id {
+			Some(id) => id.to_word(),
+			None => 0
+		});
+		words.push(match &item.animation_id {
+			Some(id) => id.to_word(),
+			None => 0
+		});
+		words.push(item.price);
+		words.push(item.unknown1);
+		words.push(item.unknown2);
+		words.push(item.unknown3);
+		words.push(match item.item_type {
+			ItemType::Game => match &item.game_type {
+				Some(game_type) => match game_type {
+					GameType::GuessingGame => 10,
+					GameType::TimingGame => 11,
+					GameType::MemoryGame => 12,
+					GameType::DodgingGame => 13,
+					GameType::ShakingGame => 14,
+					GameType::SwipingGame => 15,
+					GameType::Unknown => 0
+				},
+				None => 0
+			},
+			_ => item.unlocked_character.unwrap_or(0)
+		});
+	}
+
+	Ok(words_to_bytes(&words))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data_pack::tamastring::TamaString;
+
+	fn get_test_data_pack() -> DataPack {
+		let mut data_pack = DataPack::new(5);
+		for entity_id in 0..2 {
+			data_pack.items.push(Item::new(EntityId { card_id: Some(5), entity_id }));
+		}
+		data_pack
+	}
+
+	#[test]
+	fn unused_item_has_no_references() {
+		let mut data_pack = get_test_data_pack();
+		data_pack.items[0].unlocked_character = Some(0);
+		assert!(get_item_references(&data_pack, 0).is_empty());
+	}
+
+	#[test]
+	fn references_to_the_item_are_found() {
+		let mut data_pack = get_test_data_pack();
+		let word = data_pack.items[1].id.to_word();
+		data_pack.items[0].unknown2 = word;
+		let mut tamastring = TamaString::new(EntityId { card_id: Some(5), entity_id: 3 });
+		tamastring.field1 = word;
+		data_pack.tamastrings.push(tamastring);
+
+		assert_eq!(get_item_references(&data_pack, 1), ["Item 0 () may refer to it", "Dialog string 3 may refer to it"]);
+		assert!(get_item_references(&data_pack, 0).is_empty());
+	}
+}
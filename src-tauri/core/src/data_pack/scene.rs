@@ -0,0 +1,358 @@
+use serde::{ Serialize, Deserialize };
+
+use super::{ DataPack, EntityId };
+use super::item::ItemType;
+use crate::data_view::DataView;
+use crate::error::FormatError;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scene {
+	pub layers: Vec<SceneLayer>
+}
+
+impl Scene {
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		for layer in self.layers.iter_mut() {
+			if let Some(image_id) = &mut layer.image_id {
+				image_id.set_card_id(old_card_id, new_card_id);
+			}
+		}
+	}
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SceneLayer {
+	pub x: i16,
+	pub y: i16,
+	pub image_id: Option<EntityId>,
+	pub subimage_index: u16,
+	pub unknown1: i16,
+	pub unknown2: u16,
+	pub unknown3: u16,
+	pub unknown4: u16,
+	pub unknown5: u16,
+	pub unknown6: u16,
+	pub unknown7: u16,
+	pub unknown8: u16,
+	pub flag1: bool,
+	pub flag2: bool,
+	pub flag3: bool,
+	pub flag4: bool
+}
+
+// scenes have no ID of their own: game items and anything else refer to them by their position
+pub fn get_scene_references(data_pack: &DataPack, index: usize, card_id: Option<u8>) -> Vec<String> {
+	let scene_id = EntityId { card_id, entity_id: index as u16 };
+	let mut references: Vec<String> = data_pack.items.iter().enumerate()
+		.filter(|(_, item)| item.item_type == ItemType::Game && item.image_id.as_ref() == Some(&scene_id))
+		.map(|(i, item)| format!("Item {} ({}) is a game in this scene", i, item.name.string))
+		.collect();
+	references.extend(data_pack.find_id_in_unknown_tables(&scene_id));
+	if index + 1 < data_pack.scenes.len() {
+		references.push(format!("Scenes {} to {} will be renumbered", index + 1, data_pack.scenes.len() - 1));
+	}
+	references
+}
+
+// later scenes move down one, so game items are updated to match; games in the removed scene are left without one
+pub fn remove_scene(data_pack: &mut DataPack, index: usize, card_id: Option<u8>) -> Result<Scene, FormatError> {
+	if index >= data_pack.scenes.len() {
+		return Err(FormatError::NotFound { entity: "Scene".to_string(), index });
+	}
+	let scene = data_pack.scenes.remove(index);
+	for item in data_pack.items.iter_mut().filter(|item| item.item_type == ItemType::Game) {
+		let Some(scene_id) = &mut item.image_id else { continue };
+		if scene_id.card_id != card_id {
+			continue;
+		}
+		let scene_index = scene_id.entity_id as usize;
+		if scene_index == index {
+			item.image_id = None;
+		} else if scene_index > index {
+			scene_id.entity_id -= 1;
+		}
+	}
+	Ok(scene)
+}
+
+pub fn get_scene_offsets(data: &DataView) -> Result<(Vec<usize>, Vec<usize>), FormatError> {
+	let mut offsets = Vec::new();
+	for i in 0..(data.len()/2) {
+		let offset = data.get_u16(i*2)? as usize * 2;
+		offsets.push(offset);
+	}
+
+	let mut sizes = Vec::new();
+	if !offsets.is_empty() {
+		for i in 0..(offsets.len() - 1) {
+			let size = offsets[i+1].checked_sub(offsets[i])
+				.ok_or_else(|| data.error(i*2, Some(i), "scene starts after the next one"))?;
+			sizes.push(size);
+		}
+		let _ = offsets.pop();
+	}
+
+	Ok((offsets, sizes))
+}
+
+pub fn get_scene_layer_offsets(data: &DataView, offsets: Vec<usize>, sizes: Vec<usize>) -> Result<Vec<Vec<usize>>, FormatError> {
+	let mut scene_layer_offsets = Vec::new();
+
+	for i in 0..offsets.len() {
+		let mut layer_offsets = Vec::new();
+		let layer_offset_data = data.chunk(offsets[i], sizes[i])?;
+		for j in 0..(layer_offset_data.len()/2) {
+			let layer_offset = layer_offset_data.get_u16(j*2)? as usize * 2;
+			layer_offsets.push(layer_offset);
+		}
+		scene_layer_offsets.push(layer_offsets);
+	}
+
+	Ok(scene_layer_offsets)
+}
+
+pub fn get_scenes(data: &DataView, offsets: Vec<Vec<usize>>) -> Result<Vec<Scene>, FormatError> {
+	let mut scenes = Vec::new();
+
+	for layer_offsets in offsets {
+		let mut layers = Vec::new();
+
+		for i in layer_offsets {
+			if i + 2 < data.len() {
+				let mut local_i = i;
+
+				let bitmask = data.get_u16(i)?;
+				let mut flags = [false; 16];
+				for (i, flag) in flags.iter_mut().enumerate() {
+					*flag = bitmask & (1 << i) != 0;
+				}
+
+				let x = if flags[0] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_i16(local_i)?
+				} else {
+					0
+				};
+
+				let y = if flags[1] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_i16(local_i)?
+				} else {
+					0
+				};
+
+				let image_id = if flags[2] && local_i + 2 < data.len() {
+					local_i += 2;
+					Some(EntityId::new(data.get_u16(local_i)?))
+				} else {
+					None
+				};
+
+				let unknown1 = if flags[3] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_i16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown2 = if flags[4] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown3 = if flags[5] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let subimage_index = if flags[6] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown4 = if flags[7] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown5 = if flags[8] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown6 = if flags[9] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown7 = if flags[10] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let unknown8 = if flags[11] && local_i + 2 < data.len() {
+					local_i += 2;
+					data.get_u16(local_i)?
+				} else {
+					0
+				};
+
+				let flag1 = flags[12];
+
+				let flag2 = flags[13];
+
+				let flag3 = flags[14];
+
+				let flag4 = flags[15];
+
+				layers.push(SceneLayer {
+					x,
+					y,
+					image_id,
+					subimage_index,
+					unknown1,
+					unknown2,
+					unknown3,
+					unknown4,
+					unknown5,
+					unknown6,
+					unknown7,
+					unknown8,
+					flag1,
+					flag2,
+					flag3,
+					flag4
+				});
+			}
+		}
+
+		scenes.push(Scene { layers });
+	}
+
+	Ok(scenes)
+}
+
+pub fn save_scenes(scenes: &[Scene]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), FormatError> {
+	let mut scene_offsets = Vec::new();
+	let mut layer_offsets = Vec::new();
+	let mut layer_data = Vec::new();
+
+	// offsets are counted in words and have to fit in a u16
+	let check_offset = |region: &str, size: usize| -> Result<(), FormatError> {
+		if size / 2 > u16::MAX as usize {
+			return Err(FormatError::SizeOverflow { region: region.to_string(), size, limit: u16::MAX as usize * 2 });
+		}
+		Ok(())
+	};
+
+	for scene in scenes {
+		check_offset("Scene layer offsets", layer_offsets.len())?;
+		scene_offsets.extend_from_slice(&((layer_offsets.len() / 2) as u16).to_le_bytes());
+		for layer in &scene.layers {
+			let mut bitmask: u16 = 0;
+			let mut this_layer_data = vec![0, 0];
+			check_offset("Scene layers", layer_data.len())?;
+			layer_offsets.extend_from_slice(&((layer_data.len() / 2) as u16).to_le_bytes());
+
+			if layer.x != 0 {
+				bitmask |= 1 << 0;
+				this_layer_data.extend_from_slice(&layer.x.to_le_bytes());
+			}
+
+			if layer.y != 0 {
+				bitmask |= 1 << 1;
+				this_layer_data.extend_from_slice(&layer.y.to_le_bytes());
+			}
+
+			if let Some(image_id) = &layer.image_id {
+				bitmask |= 1 << 2;
+				this_layer_data.extend_from_slice(&image_id.to_word().to_le_bytes());
+			}
+
+			if layer.unknown1 != 0 {
+				bitmask |= 1 << 3;
+				this_layer_data.extend_from_slice(&layer.unknown1.to_le_bytes());
+			}
+
+			if layer.unknown2 != 0 {
+				bitmask |= 1 << 4;
+				this_layer_data.extend_from_slice(&layer.unknown2.to_le_bytes());
+			}
+
+			if layer.unknown3 != 0 {
+				bitmask |= 1 << 5;
+				this_layer_data.extend_from_slice(&layer.unknown3.to_le_bytes());
+			}
+
+			if layer.subimage_index != 0 {
+				bitmask |= 1 << 6;
+				this_layer_data.extend_from_slice(&layer.subimage_index.to_le_bytes());
+			}
+
+			if layer.unknown4 != 0 {
+				bitmask |= 1 << 7;
+				this_layer_data.extend_from_slice(&layer.unknown4.to_le_bytes());
+			}
+
+			if layer.unknown5 != 0 {
+				bitmask |= 1 << 8;
+				this_layer_data.extend_from_slice(&layer.unknown5.to_le_bytes());
+			}
+
+			if layer.unknown6 != 0 {
+				bitmask |= 1 << 9;
+				this_layer_data.extend_from_slice(&layer.unknown6.to_le_bytes());
+			}
+
+			if layer.unknown7 != 0 {
+				bitmask |= 1 << 10;
+				this_layer_data.extend_from_slice(&layer.unknown7.to_le_bytes());
+			}
+
+			if layer.unknown8 != 0 {
+				bitmask |= 1 << 11;
+				this_layer_data.extend_from_slice(&layer.unknown8.to_le_bytes());
+			}
+
+			if layer.flag1 {
+				bitmask |= 1 << 12;
+			}
+
+			if layer.flag2 {
+				bitmask |= 1 << 13;
+			}
+
+			if layer.flag3 {
+				bitmask |= 1 << 14;
+			}
+
+			if layer.flag4 {
+				bitmask |= 1 << 15;
+			}
+
+			this_layer_data.splice(0..2, bitmask.to_le_bytes());
+			layer_data.extend_from_slice(&this_layer_data);
+		}
+
+		layer_data.extend_from_slice(&[0, 0]);
+	}
+
+	check_offset("Scene layer offsets", layer_offsets.len())?;
+	scene_offsets.extend_from_slice(&((layer_offsets.len() / 2) as u16).to_le_bytes());
+
+	Ok((scene_offsets, layer_offsets, layer_data))
+}
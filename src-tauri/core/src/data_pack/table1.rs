@@ -1,39 +1,35 @@
-use std::error::Error;
-
 use crate::data_view::{ DataView, words_to_bytes };
+use crate::error::FormatError;
 
-pub fn get_entity_offsets(data: &DataView) -> Vec<usize> {
+pub fn get_entity_offsets(data: &DataView) -> Result<Vec<usize>, FormatError> {
 	let mut offsets = Vec::new();
 	for i in 0..(data.len()/2) {
-		let offset = data.get_u16(i*2) as usize * 2;
+		let offset = data.get_u16(i*2)? as usize * 2;
 		offsets.push(offset);
 	}
 
-	offsets
+	Ok(offsets)
 }
 
-pub fn get_entities(data: &DataView, offsets: Vec<usize>) -> Vec<Vec<u16>> {
+pub fn get_entities(data: &DataView, offsets: Vec<usize>) -> Result<Vec<Vec<u16>>, FormatError> {
 	let mut entities = Vec::new();
 
 	for i in 0..offsets.len() {
-		let size = if i+1 < offsets.len() {
-			offsets[i+1] - offsets[i]
-		} else {
-			data.len() - offsets[i]
-		};
-		let entity_data = data.chunk(offsets[i], size);
+		let end = if i+1 < offsets.len() { offsets[i+1] } else { data.len() };
+		let size = end.checked_sub(offsets[i])
+			.ok_or_else(|| data.error(offsets[i], Some(i), "entity starts after the next one"))?;
+		let entity_data = data.chunk(offsets[i], size)?;
 		let mut entity_words = Vec::new();
 		for j in 0..(entity_data.len()/2) {
-			entity_words.push(entity_data.get_u16(j*2));
+			entity_words.push(entity_data.get_u16(j*2)?);
 		}
 		entities.push(entity_words)
 	}
 
-
-	entities
+	Ok(entities)
 }
 
-pub fn save_entities(entities: &[Vec<u16>]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+pub fn save_entities(entities: &[Vec<u16>]) -> Result<(Vec<u8>, Vec<u8>), FormatError> {
 	let mut offsets = Vec::new();
 	let mut data = Vec::new();
 
@@ -1,41 +1,42 @@
-use std::error::Error;
-
 use crate::data_view::{ DataView, words_to_bytes };
+use crate::error::FormatError;
 
-pub fn get_entity_offsets(data: &DataView) -> (Vec<usize>, Vec<usize>) {
+pub fn get_entity_offsets(data: &DataView) -> Result<(Vec<usize>, Vec<usize>), FormatError> {
 	let mut offsets = Vec::new();
 	for i in 0..(data.len()/2) {
-		let offset = data.get_u16(i*2) as usize * 2;
+		let offset = data.get_u16(i*2)? as usize * 2;
 		offsets.push(offset);
 	}
 
 	let mut sizes = Vec::new();
 	if !offsets.is_empty() {
 		for i in 0..(offsets.len() - 1) {
-			sizes.push(offsets[i+1] - offsets[i]);
+			let size = offsets[i+1].checked_sub(offsets[i])
+				.ok_or_else(|| data.error(i*2, Some(i), "entity starts after the next one"))?;
+			sizes.push(size);
 		}
 		let _ = offsets.pop();
 	}
 
-	(offsets, sizes)
+	Ok((offsets, sizes))
 }
 
-pub fn get_entities(data: &DataView, offsets: Vec<usize>, sizes: Vec<usize>) -> Vec<Vec<u16>> {
+pub fn get_entities(data: &DataView, offsets: Vec<usize>, sizes: Vec<usize>) -> Result<Vec<Vec<u16>>, FormatError> {
 	let mut entities = Vec::new();
 
 	for i in 0..offsets.len() {
-		let entity_data = data.chunk(offsets[i], sizes[i]);
+		let entity_data = data.chunk(offsets[i], sizes[i])?;
 		let mut entity_bytes = Vec::new();
 		for j in 0..(entity_data.len()/2) {
-			entity_bytes.push(entity_data.get_u16(j*2));
+			entity_bytes.push(entity_data.get_u16(j*2)?);
 		}
 		entities.push(entity_bytes)
 	}
 
-	entities
+	Ok(entities)
 }
 
-pub fn save_entities(entities: &[Vec<u16>]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
+pub fn save_entities(entities: &[Vec<u16>]) -> Result<(Vec<u8>, Vec<u8>), FormatError> {
 	let mut offsets = Vec::new();
 	let mut data = Vec::new();
 
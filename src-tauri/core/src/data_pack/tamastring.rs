@@ -0,0 +1,120 @@
+use serde::{ Serialize, Deserialize };
+
+use super::{ DataPack, EntityId };
+use crate::data_view::{ DataView, words_to_bytes };
+use crate::error::FormatError;
+use crate::text::{ Text, CharEncoding };
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TamaString {
+	pub id: EntityId,
+	pub expression: u16,
+	pub field1: u16,
+	pub field2: u16,
+	pub value: Text
+}
+
+impl TamaString {
+	pub fn new(id: EntityId) -> TamaString {
+		TamaString {
+			id,
+			expression: 0,
+			field1: 0,
+			field2: 0,
+			value: Text { data: Vec::new(), string: String::new() }
+		}
+	}
+
+	pub fn to_words(&self) -> Vec<u16> {
+		let mut data: Vec<u16> = vec![
+			self.id.to_word(),
+			self.expression,
+			self.field1,
+			self.field2
+		];
+		for word in &self.value.data {
+			data.push(*word);
+		}
+		data.push(0);
+		data
+	}
+
+	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
+		self.id.set_card_id(old_card_id, new_card_id);
+	}
+}
+
+// strings are only ever looked up by ID, so this is where anything still pointing at one would be
+pub fn get_tamastring_references(data_pack: &DataPack, index: usize) -> Vec<String> {
+	let Some(tamastring) = data_pack.tamastrings.get(index) else { return Vec::new() };
+	let mut references = Vec::new();
+	if tamastring.id.card_id.is_some() {
+		let word = tamastring.id.to_word();
+		for (i, other) in data_pack.tamastrings.iter().enumerate() {
+			if i != index && (other.field1 == word || other.field2 == word) {
+				references.push(format!("Dialog string {} may refer to it", other.id.entity_id));
+			}
+		}
+	}
+	references.extend(data_pack.find_id_in_unknown_tables(&tamastring.id));
+	references
+}
+
+pub fn get_tamastrings(data: &DataView, char_codes: &[CharEncoding]) -> Result<Vec<TamaString>, FormatError> {
+	let mut strings = Vec::new();
+
+	let mut i = 0;
+	while i + 10 <= data.len() {
+		let id = EntityId::new(data.get_u16(i)?);
+
+		let expression = data.get_u16(i+2)?;
+		let field1 = data.get_u16(i+4)?;
+		let field2 = data.get_u16(i+6)?;
+
+		let mut text_data = Vec::new();
+		let mut str_len = 0;
+
+		while i + 8 + str_len*2 < data.len() {
+			let word = data.get_u16(i + 8 + str_len*2)?;
+			if word == 0 {
+				break;
+			}
+			text_data.push(word);
+			str_len += 1;
+		}
+		i += 10 + str_len*2;
+
+		strings.push(TamaString {
+			id,
+			expression,
+			field1,
+			field2,
+			value: Text::from_data(char_codes, &text_data)
+		});
+	}
+
+	Ok(strings)
+}
+
+pub fn save_tamastrings(tamastrings: &[TamaString]) -> Result<(Vec<u8>, Vec<u8>), FormatError> {
+	let mut words: Vec<u16> = Vec::new();
+	let mut offsets: Vec<u16> = Vec::new();
+
+	for tamastring in tamastrings {
+		// 0xFFFF marks the end of the offsets, so it can't be used as one
+		if words.len() >= 0xFFFF {
+			return Err(FormatError::SizeOverflow { region: "Dialog strings".to_string(), size: words.len() * 2, limit: 0xFFFE * 2 });
+		}
+		offsets.push(words.len() as u16);
+		words.push(tamastring.id.to_word());
+		words.push(tamastring.expression);
+		words.push(tamastring.field1);
+		words.push(tamastring.field2);
+		words.extend_from_slice(&tamastring.value.data);
+		words.push(0);
+	}
+
+	offsets.push(0xFFFF);
+
+	Ok((words_to_bytes(&offsets), words_to_bytes(&words)))
+}
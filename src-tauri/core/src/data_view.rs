@@ -1,60 +1,88 @@
-use tauri::{ State, AppHandle, Manager };
-
-use crate::text::{ Text, FontState };
+use crate::text::{ Text, CharEncoding };
+use crate::error::{ FormatError, DataContext };
 
 #[derive(serde::Serialize)]
 pub struct DataView {
-	pub data: Vec<u8>
+	pub data: Vec<u8>,
+	pub offset: usize,
+	pub context: DataContext
 }
 
 impl DataView {
 	pub fn new(data: &[u8]) -> DataView {
-		DataView { data: data.to_owned() }
+		DataView { data: data.to_owned(), offset: 0, context: DataContext::File }
+	}
+
+	pub fn with_context(mut self, context: DataContext) -> DataView {
+		self.context = context;
+		self
 	}
 
 	pub fn len(&self) -> usize {
 		self.data.len()
 	}
 
-	pub fn chunk(&self, start: usize, size: usize) -> DataView {
-		DataView { data: self.data[start..(start+size)].to_owned() }
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	pub fn error(&self, i: usize, index: Option<usize>, reason: &str) -> FormatError {
+		FormatError::at(self.context, self.offset.saturating_add(i), index, reason)
+	}
+
+	fn out_of_bounds(&self, i: usize, len: usize) -> FormatError {
+		self.error(i, None, &format!("{} byte(s) run past the end of the data at 0x{:X}", len, self.offset + self.len()))
+	}
+
+	pub fn get_bytes(&self, i: usize, len: usize) -> Result<&[u8], FormatError> {
+		i.checked_add(len)
+			.and_then(|end| self.data.get(i..end))
+			.ok_or_else(|| self.out_of_bounds(i, len))
 	}
 
-	pub fn get_u8(&self, i: usize) -> u8 {
-		self.data[i]
+	pub fn chunk(&self, start: usize, size: usize) -> Result<DataView, FormatError> {
+		Ok(DataView {
+			data: self.get_bytes(start, size)?.to_owned(),
+			offset: self.offset + start,
+			context: self.context
+		})
 	}
 
-	pub fn get_u16(&self, i: usize) -> u16 {
-		u16::from_le_bytes([self.data[i], self.data[i+1]])
+	pub fn get_u8(&self, i: usize) -> Result<u8, FormatError> {
+		Ok(self.get_bytes(i, 1)?[0])
 	}
 
-	pub fn get_i16(&self, i: usize) -> i16 {
-		i16::from_le_bytes([self.data[i], self.data[i+1]])
+	pub fn get_u16(&self, i: usize) -> Result<u16, FormatError> {
+		let bytes = self.get_bytes(i, 2)?;
+		Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
 	}
 
-	pub fn get_u32(&self, i: usize) -> u32 {
-		u32::from_le_bytes([self.data[i], self.data[i+1], self.data[i+2], self.data[i+3]])
+	pub fn get_i16(&self, i: usize) -> Result<i16, FormatError> {
+		let bytes = self.get_bytes(i, 2)?;
+		Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
 	}
 
-	pub fn get_bits(&self, i: usize, len: usize) -> Vec<u8> {
+	pub fn get_u32(&self, i: usize) -> Result<u32, FormatError> {
+		let bytes = self.get_bytes(i, 4)?;
+		Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	}
+
+	pub fn get_bits(&self, i: usize, len: usize) -> Result<Vec<u8>, FormatError> {
 		let mut bits = Vec::new();
-		for j in 0..len {
-			let byte = self.data[i+j];
+		for byte in self.get_bytes(i, len)? {
 			for b in (0..8).rev() {
 				let bit = (byte >> b) & 1;
 				bits.push(bit);
 			}
 		}
-		bits
+		Ok(bits)
 	}
 
-	pub fn get_text(&self, handle: &AppHandle, i: usize, len: usize) -> Text {
-		let font_state: State<FontState> = handle.state();
-		let char_codes = &font_state.char_codes.lock().unwrap();
+	// reads up to len words, stopping early at the end of the data
+	pub fn get_text(&self, char_codes: &[CharEncoding], i: usize, len: usize) -> Text {
 		let mut words: Vec<u16> = Vec::new();
 		for j in 0..len {
-			if i + j*2 < self.len() {
-				let word = self.get_u16(i + j*2);
+			if let Ok(word) = self.get_u16(i + j*2) {
 				if word > 0 {
 					words.push(word);
 				}
@@ -95,6 +123,7 @@ pub fn resize_words(words: &[u16], len: usize) -> Vec<u16> {
 	word_vec
 }
 
+#[derive(Default)]
 pub struct BitWriter {
 	pub bytes: Vec<u8>,
 	pub byte_in_progress: u8,
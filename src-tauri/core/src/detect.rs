@@ -0,0 +1,158 @@
+use serde::Serialize;
+
+use crate::BinType;
+use crate::data_view::DataView;
+use crate::data_pack::get_table_offsets;
+use crate::firmware::{ PATCH_HEADER_START, MENU_STRINGS_START };
+use crate::layout::find_firmware_layout;
+use crate::smacard::{ CARD_SIGNATURE, calc_md5 };
+
+const FIRMWARE_SIZE: usize = 0x1000000;
+
+#[derive(Clone, Serialize)]
+pub struct Detection {
+	pub bin_type: BinType,
+	pub is_ambiguous: bool,
+	pub firmware_findings: Vec<String>,
+	pub card_findings: Vec<String>
+}
+
+impl Detection {
+	pub fn describe(&self) -> String {
+		let mut lines = Vec::new();
+		let findings = [("Firmware", &self.firmware_findings), ("TamaSma card", &self.card_findings)];
+		for (name, findings) in findings {
+			if findings.is_empty() {
+				lines.push(format!("{}: nothing recognized", name));
+			} else {
+				lines.push(format!("{}: {}", name, findings.join("; ")));
+			}
+		}
+		lines.join("\n")
+	}
+}
+
+// the data pack table offsets are the strongest sign of either format, since they're checked
+// for order and bounds; the other checks only break ties or add detail to the report
+pub fn detect_bin_type(data: &DataView) -> Detection {
+	let mut firmware_findings = Vec::new();
+	let mut card_findings = Vec::new();
+
+	let has_patch_header = data.data.starts_with(&PATCH_HEADER_START);
+	if has_patch_header {
+		firmware_findings.push("patch header at 0x0".to_string());
+	}
+
+	let firmware_layout = find_firmware_layout(&data.data, &[]);
+	let firmware_data_pack_ok = firmware_layout.is_some();
+	if let Some(layout) = &firmware_layout {
+		let shift = layout.get_shift(&data.data);
+		firmware_findings.push(format!("data pack table offsets at 0x{:X} and sprite pack at 0x{:X} ({} layout)", layout.data_pack_start + shift, layout.sprite_pack_start + shift, layout.name));
+	}
+
+	if data.len() == FIRMWARE_SIZE {
+		firmware_findings.push("file is exactly 16 MiB".to_string());
+	}
+
+	// a card is small enough that the menu string magic turning up by chance isn't worth the search
+	if firmware_data_pack_ok || has_patch_header {
+		if let Some(offset) = data.find_bytes(&MENU_STRINGS_START) {
+			firmware_findings.push(format!("menu strings at 0x{:X}", offset));
+		}
+	}
+
+	let card_data_pack_ok = check_card_layout(data, &mut card_findings);
+
+	let (bin_type, is_ambiguous) = match (firmware_data_pack_ok, card_data_pack_ok) {
+		(true, false) => (BinType::Firmware, false),
+		(false, true) => (BinType::SmaCard, false),
+		_ => {
+			let firmware_score = firmware_findings.len();
+			let card_score = card_findings.len();
+			if firmware_score > card_score || (firmware_score == card_score && data.len() == FIRMWARE_SIZE) {
+				(BinType::Firmware, true)
+			} else {
+				(BinType::SmaCard, true)
+			}
+		}
+	};
+
+	Detection { bin_type, is_ambiguous, firmware_findings, card_findings }
+}
+
+fn check_card_layout(data: &DataView, findings: &mut Vec<String>) -> bool {
+	if data.len() < 0x1000 + 68 {
+		return false;
+	}
+
+	let card_type = u16::from_le_bytes([data.data[48], data.data[49]]);
+	let card_id = u16::from_le_bytes([data.data[50], data.data[51]]);
+	if card_type <= 2 && card_id <= 0x7f {
+		findings.push(format!("card header with card type {} and card ID {}", card_type, card_id));
+	}
+
+	if calc_md5(&data.data) == data.data[64..80] {
+		findings.push("header MD5 matches".to_string());
+	}
+
+	let sector_count = u16::from_le_bytes([data.data[0], data.data[1]]) as usize;
+	if data.len() % 0x1000 == 0 && sector_count == data.len() / 0x1000 - 1 {
+		findings.push(format!("sector count {} matches the file size", sector_count));
+	}
+
+	if data.data.ends_with(&CARD_SIGNATURE) {
+		findings.push("card signature at the end".to_string());
+	}
+
+	let Ok(pack_table) = data.chunk(0x1000, 68) else { return false };
+	let pack_count = pack_table.get_u16(2).unwrap_or(0);
+	if pack_count < 2 {
+		return false;
+	}
+	findings.push(format!("pack table with {} packs", pack_count));
+
+	let data_pack_offset = pack_table.get_u32(8).unwrap_or(0) as usize;
+	let data_pack_size = pack_table.get_u32(16).unwrap_or(0) as usize;
+	let data_pack_ok = data.chunk(0x1000 + data_pack_offset, data_pack_size).is_ok_and(|chunk| has_valid_table_offsets(&chunk));
+	if data_pack_ok {
+		findings.push(format!("data pack table offsets at 0x{:X}", 0x1000 + data_pack_offset));
+	}
+	data_pack_ok
+}
+
+// the first table always starts right after the 20 table offsets
+fn has_valid_table_offsets(data: &DataView) -> bool {
+	get_table_offsets(data).is_ok_and(|(table_offsets, _)| table_offsets.first() == Some(&80))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_util::get_saved_test_card;
+
+	#[test]
+	fn detects_a_card() {
+		let data = get_saved_test_card();
+		let detection = detect_bin_type(&DataView::new(&data));
+		assert_eq!(detection.bin_type, BinType::SmaCard);
+		assert!(!detection.is_ambiguous);
+		assert!(detection.firmware_findings.is_empty());
+	}
+
+	// without a data pack to go on, a 16 MiB file with a patch header is most likely firmware
+	#[test]
+	fn falls_back_to_firmware_findings() {
+		let mut data = vec![0; FIRMWARE_SIZE];
+		data[..PATCH_HEADER_START.len()].copy_from_slice(&PATCH_HEADER_START);
+		let detection = detect_bin_type(&DataView::new(&data));
+		assert_eq!(detection.bin_type, BinType::Firmware);
+		assert!(detection.is_ambiguous);
+	}
+
+	#[test]
+	fn unrecognized_data_is_ambiguous() {
+		let detection = detect_bin_type(&DataView::new(&[0xFF; 0x100]));
+		assert!(detection.is_ambiguous);
+		assert!(detection.firmware_findings.is_empty() && detection.card_findings.is_empty());
+	}
+}
@@ -0,0 +1,385 @@
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{ BinType, get_bin_type };
+use crate::error::FormatError;
+use crate::data_view::DataView;
+use crate::text::CharEncoding;
+use crate::smacard::{ CardHeader, read_card };
+use crate::firmware::read_firmware_with_layout;
+use crate::layout::{ FirmwareLayout, detect_firmware_layout };
+use crate::data_pack::DataPack;
+use crate::sprite_pack::SpritePack;
+use crate::sprite_pack::image_def::ImageSet;
+use crate::sprite_pack::palette::Color;
+
+#[derive(Clone, Debug, Serialize)]
+pub enum ChangeKind {
+	Added,
+	Removed,
+	Changed
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldChange {
+	pub field: String,
+	pub old: Value,
+	pub new: Value,
+	// used instead of old and new for data that is too large to show, like pixel data
+	pub detail: Option<String>
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EntityChange {
+	pub section: String,
+	pub index: usize,
+	pub name: Option<String>,
+	pub kind: ChangeKind,
+	pub fields: Vec<FieldChange>
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BinDiff {
+	pub old_bin_type: BinType,
+	pub new_bin_type: BinType,
+	pub old_size: usize,
+	pub new_size: usize,
+	pub changes: Vec<EntityChange>
+}
+
+struct BinContents {
+	card_header: Option<CardHeader>,
+	data_pack: DataPack,
+	sprite_pack: SpritePack,
+	menu_strings: Vec<String>
+}
+
+// reads both bins (as bin_type if given, otherwise as detected) and lists what changed from the old one to the new one, entity by entity
+pub fn compare_bins(old_data: &[u8], new_data: &[u8], bin_type: Option<&BinType>, char_codes: &[CharEncoding], layouts: &[FirmwareLayout]) -> Result<BinDiff, FormatError> {
+	let old_view = DataView::new(old_data);
+	let new_view = DataView::new(new_data);
+	let old_bin_type = bin_type.cloned().unwrap_or_else(|| get_bin_type(&old_view));
+	let new_bin_type = bin_type.cloned().unwrap_or_else(|| get_bin_type(&new_view));
+	let old_contents = read_contents(&old_view, &old_bin_type, char_codes, layouts)
+		.map_err(|why| FormatError::Other { reason: format!("Unable to read old bin: {}", why) })?;
+	let new_contents = read_contents(&new_view, &new_bin_type, char_codes, layouts)
+		.map_err(|why| FormatError::Other { reason: format!("Unable to read new bin: {}", why) })?;
+
+	let mut changes = Vec::new();
+
+	if old_contents.card_header.is_some() || new_contents.card_header.is_some() {
+		let old_header: Vec<Value> = old_contents.card_header.iter().map(header_value).collect::<Result<_, _>>()?;
+		let new_header: Vec<Value> = new_contents.card_header.iter().map(header_value).collect::<Result<_, _>>()?;
+		compare_entities(&mut changes, "card_header", &old_header, &new_header, |_| None)?;
+	}
+	compare_entities(&mut changes, "menu_strings", &old_contents.menu_strings, &new_contents.menu_strings, |s| Some(s.clone()))?;
+
+	let old_pack = &old_contents.data_pack;
+	let new_pack = &new_contents.data_pack;
+	compare_entities(&mut changes, "card_id", &[old_pack.card_id], &[new_pack.card_id], |_| None)?;
+	compare_entities(&mut changes, "table1", &old_pack.table1, &new_pack.table1, |_| None)?;
+	compare_entities(&mut changes, "particle_emitters", &old_pack.particle_emitters, &new_pack.particle_emitters, |_| None)?;
+	compare_entities(&mut changes, "scenes", &old_pack.scenes, &new_pack.scenes, |_| None)?;
+	compare_entities(&mut changes, "tamastrings", &old_pack.tamastrings, &new_pack.tamastrings, |s| Some(s.value.string.clone()))?;
+	compare_entities(&mut changes, "table9", &old_pack.table9, &new_pack.table9, |_| None)?;
+	compare_entities(&mut changes, "items", &old_pack.items, &new_pack.items, |item| Some(item.name.string.clone()))?;
+	compare_entities(&mut changes, "characters", &old_pack.characters, &new_pack.characters, |character| Some(character.name.string.clone()))?;
+	compare_entities(&mut changes, "graphics_nodes", &old_pack.graphics_nodes, &new_pack.graphics_nodes, |_| None)?;
+	compare_entities(&mut changes, "frame_groups", &old_pack.frame_groups, &new_pack.frame_groups, |_| None)?;
+
+	compare_image_sets(&mut changes, &old_contents.sprite_pack.image_sets, &new_contents.sprite_pack.image_sets);
+
+	Ok(BinDiff {
+		old_bin_type,
+		new_bin_type,
+		old_size: old_data.len(),
+		new_size: new_data.len(),
+		changes
+	})
+}
+
+fn read_contents(data: &DataView, bin_type: &BinType, char_codes: &[CharEncoding], layouts: &[FirmwareLayout]) -> Result<BinContents, FormatError> {
+	match bin_type {
+		BinType::SmaCard => {
+			let card = read_card(data, char_codes)?;
+			Ok(BinContents {
+				card_header: Some(card.header),
+				data_pack: card.data_pack,
+				sprite_pack: card.sprite_pack,
+				menu_strings: Vec::new()
+			})
+		},
+		BinType::Firmware => {
+			let layout = detect_firmware_layout(&data.data, layouts)?;
+			let firmware = read_firmware_with_layout(data, char_codes, &layout)?;
+			Ok(BinContents {
+				card_header: None,
+				data_pack: firmware.data_pack,
+				sprite_pack: firmware.sprite_pack,
+				menu_strings: firmware.menu_strings.into_iter().map(|text| text.string).collect()
+			})
+		}
+	}
+}
+
+// the checksum and MD5 change along with everything else, so they aren't worth reporting
+fn header_value(header: &CardHeader) -> Result<Value, FormatError> {
+	let mut value = serde_json::to_value(header)?;
+	if let Value::Object(map) = &mut value {
+		map.remove("checksum");
+		map.remove("md5");
+	}
+	Ok(value)
+}
+
+fn compare_entities<T: Serialize>(changes: &mut Vec<EntityChange>, section: &str, old: &[T], new: &[T], get_name: impl Fn(&T) -> Option<String>) -> Result<(), FormatError> {
+	for i in 0..old.len().max(new.len()) {
+		let change = match (old.get(i), new.get(i)) {
+			(Some(old_entity), Some(new_entity)) => {
+				let mut fields = Vec::new();
+				compare_values("", &serde_json::to_value(old_entity)?, &serde_json::to_value(new_entity)?, &mut fields);
+				if fields.is_empty() {
+					continue;
+				}
+				let name = get_name(new_entity).or_else(|| get_name(old_entity));
+				EntityChange { section: section.to_string(), index: i, name, kind: ChangeKind::Changed, fields }
+			},
+			(Some(old_entity), None) => {
+				EntityChange { section: section.to_string(), index: i, name: get_name(old_entity), kind: ChangeKind::Removed, fields: Vec::new() }
+			},
+			(None, Some(new_entity)) => {
+				EntityChange { section: section.to_string(), index: i, name: get_name(new_entity), kind: ChangeKind::Added, fields: Vec::new() }
+			},
+			(None, None) => continue
+		};
+		changes.push(change);
+	}
+	Ok(())
+}
+
+fn is_text(value: &serde_json::Map<String, Value>) -> bool {
+	value.len() == 2 && value.contains_key("data") && value.contains_key("string")
+}
+
+fn is_scalar_array(values: &[Value]) -> bool {
+	values.iter().all(|value| !value.is_array() && !value.is_object())
+}
+
+fn join_field(path: &str, field: &str) -> String {
+	if path.is_empty() {
+		field.to_string()
+	} else {
+		format!("{}.{}", path, field)
+	}
+}
+
+// walks both values and records the deepest fields that differ;
+// text is compared by its string, and lists of plain values are reported as a whole
+fn compare_values(path: &str, old: &Value, new: &Value, fields: &mut Vec<FieldChange>) {
+	if old == new {
+		return;
+	}
+	match (old, new) {
+		(Value::Object(old_map), Value::Object(new_map)) if is_text(old_map) && is_text(new_map) => {
+			let (old, new) = if old_map["string"] != new_map["string"] {
+				(old_map["string"].clone(), new_map["string"].clone())
+			} else {
+				(old_map["data"].clone(), new_map["data"].clone())
+			};
+			fields.push(FieldChange { field: path.to_string(), old, new, detail: None });
+		},
+		(Value::Object(old_map), Value::Object(new_map)) => {
+			for (key, old_value) in old_map {
+				let new_value = new_map.get(key).unwrap_or(&Value::Null);
+				compare_values(&join_field(path, key), old_value, new_value, fields);
+			}
+			for (key, new_value) in new_map {
+				if !old_map.contains_key(key) {
+					compare_values(&join_field(path, key), &Value::Null, new_value, fields);
+				}
+			}
+		},
+		(Value::Array(old_values), Value::Array(new_values)) if !(is_scalar_array(old_values) && is_scalar_array(new_values)) => {
+			for i in 0..old_values.len().max(new_values.len()) {
+				let field = format!("{}[{}]", path, i);
+				match (old_values.get(i), new_values.get(i)) {
+					(Some(old_value), Some(new_value)) => compare_values(&field, old_value, new_value, fields),
+					(Some(old_value), None) => fields.push(FieldChange { field, old: old_value.clone(), new: Value::Null, detail: Some("removed".to_string()) }),
+					(None, Some(new_value)) => fields.push(FieldChange { field, old: Value::Null, new: new_value.clone(), detail: Some("added".to_string()) }),
+					(None, None) => {}
+				}
+			}
+		},
+		_ => {
+			fields.push(FieldChange { field: path.to_string(), old: old.clone(), new: new.clone(), detail: None });
+		}
+	}
+}
+
+fn format_color(color: &Color) -> String {
+	let rgba = color.as_rgba();
+	if rgba[3] == 0 {
+		"transparent".to_string()
+	} else {
+		format!("#{:02x}{:02x}{:02x}", rgba[0], rgba[1], rgba[2])
+	}
+}
+
+fn palette_value(palette: &[Color]) -> Value {
+	Value::from(palette.iter().map(format_color).collect::<Vec<String>>())
+}
+
+fn compare_image_sets(changes: &mut Vec<EntityChange>, old: &[ImageSet], new: &[ImageSet]) {
+	for i in 0..old.len().max(new.len()) {
+		let (old_set, new_set) = match (old.get(i), new.get(i)) {
+			(Some(old_set), Some(new_set)) => (old_set, new_set),
+			(Some(_), None) => {
+				changes.push(EntityChange { section: "image_sets".to_string(), index: i, name: None, kind: ChangeKind::Removed, fields: Vec::new() });
+				continue;
+			},
+			(None, Some(_)) => {
+				changes.push(EntityChange { section: "image_sets".to_string(), index: i, name: None, kind: ChangeKind::Added, fields: Vec::new() });
+				continue;
+			},
+			(None, None) => continue
+		};
+
+		let mut fields = Vec::new();
+		compare_values("width", &old_set.width.into(), &new_set.width.into(), &mut fields);
+		compare_values("height", &old_set.height.into(), &new_set.height.into(), &mut fields);
+		compare_values("width_in_sprites", &old_set.width_in_sprites.into(), &new_set.width_in_sprites.into(), &mut fields);
+		compare_values("height_in_sprites", &old_set.height_in_sprites.into(), &new_set.height_in_sprites.into(), &mut fields);
+		compare_values("is_quadrupled", &old_set.is_quadrupled.into(), &new_set.is_quadrupled.into(), &mut fields);
+		compare_values("first_palette_index", &old_set.first_palette_index.into(), &new_set.first_palette_index.into(), &mut fields);
+
+		for p in 0..old_set.palettes.len().max(new_set.palettes.len()) {
+			let old_palette = old_set.palettes.get(p).map(|palette| palette_value(palette)).unwrap_or(Value::Null);
+			let new_palette = new_set.palettes.get(p).map(|palette| palette_value(palette)).unwrap_or(Value::Null);
+			compare_values(&format!("palettes[{}]", p), &old_palette, &new_palette, &mut fields);
+		}
+
+		for s in 0..old_set.subimages.len().max(new_set.subimages.len()) {
+			let field = format!("subimages[{}]", s);
+			match (old_set.subimages.get(s), new_set.subimages.get(s)) {
+				(Some(old_subimage), Some(new_subimage)) => {
+					compare_values(&format!("{}.offset_x", field), &old_subimage.offset_x.into(), &new_subimage.offset_x.into(), &mut fields);
+					compare_values(&format!("{}.offset_y", field), &old_subimage.offset_y.into(), &new_subimage.offset_y.into(), &mut fields);
+					let changed_pixels = (0..old_subimage.pixel_data.len().max(new_subimage.pixel_data.len()))
+						.filter(|&p| old_subimage.pixel_data.get(p) != new_subimage.pixel_data.get(p))
+						.count();
+					if changed_pixels > 0 {
+						fields.push(FieldChange {
+							field: format!("{}.pixel_data", field),
+							old: Value::Null,
+							new: Value::Null,
+							detail: Some(format!("{} of {} pixels changed", changed_pixels, new_subimage.pixel_data.len()))
+						});
+					}
+				},
+				(Some(_), None) => fields.push(FieldChange { field, old: Value::Null, new: Value::Null, detail: Some("removed".to_string()) }),
+				(None, Some(_)) => fields.push(FieldChange { field, old: Value::Null, new: Value::Null, detail: Some("added".to_string()) }),
+				(None, None) => {}
+			}
+		}
+
+		if !fields.is_empty() {
+			changes.push(EntityChange { section: "image_sets".to_string(), index: i, name: None, kind: ChangeKind::Changed, fields });
+		}
+	}
+}
+
+pub fn get_section_title(section: &str) -> &str {
+	match section {
+		"card_header" => "Card Header",
+		"menu_strings" => "Menu Strings",
+		"card_id" => "Card ID",
+		"table1" => "Table 1",
+		"particle_emitters" => "Particle Emitters",
+		"scenes" => "Scenes",
+		"tamastrings" => "TamaStrings",
+		"table9" => "Table 9",
+		"items" => "Items",
+		"characters" => "Characters",
+		"graphics_nodes" => "Graphics Nodes",
+		"frame_groups" => "Frame Groups",
+		"image_sets" => "Image Sets",
+		_ => section
+	}
+}
+
+fn get_entity_title(section: &str) -> &str {
+	match section {
+		"card_header" => "Card header",
+		"menu_strings" => "Menu string",
+		"card_id" => "Card ID",
+		"table1" => "Table 1 entry",
+		"particle_emitters" => "Particle emitter",
+		"scenes" => "Scene",
+		"tamastrings" => "TamaString",
+		"table9" => "Table 9 entry",
+		"items" => "Item",
+		"characters" => "Character",
+		"graphics_nodes" => "Graphics node",
+		"frame_groups" => "Frame group",
+		"image_sets" => "Image set",
+		_ => section
+	}
+}
+
+impl EntityChange {
+	pub fn title(&self) -> String {
+		let title = match self.section.as_str() {
+			"card_header" | "card_id" => get_entity_title(&self.section).to_string(),
+			section => format!("{} {}", get_entity_title(section), self.index)
+		};
+		match &self.name {
+			Some(name) if !name.is_empty() => format!("{} ({})", title, name),
+			_ => title
+		}
+	}
+}
+
+impl FieldChange {
+	pub fn describe(&self) -> String {
+		match &self.detail {
+			Some(detail) if self.old.is_null() && self.new.is_null() => format!("{}: {}", self.field, detail),
+			Some(detail) if self.new.is_null() => format!("{}: {} {}", self.field, detail, self.old),
+			Some(detail) => format!("{}: {} {}", self.field, detail, self.new),
+			None => format!("{}: {} → {}", self.field, self.old, self.new)
+		}
+	}
+}
+
+impl BinDiff {
+	pub fn to_markdown(&self, old_name: &str, new_name: &str) -> String {
+		let mut lines = vec![
+			format!("# Comparing {} with {}", old_name, new_name),
+			String::new(),
+			format!("- Old: {:?}, {} bytes", self.old_bin_type, self.old_size),
+			format!("- New: {:?}, {} bytes", self.new_bin_type, self.new_size),
+			format!("- {} changed entities", self.changes.len())
+		];
+
+		let mut current_section = "";
+		for change in &self.changes {
+			if change.section != current_section {
+				current_section = &change.section;
+				lines.push(String::new());
+				lines.push(format!("## {}", get_section_title(current_section)));
+				lines.push(String::new());
+			}
+			let kind = match change.kind {
+				ChangeKind::Added => "added",
+				ChangeKind::Removed => "removed",
+				ChangeKind::Changed => "changed"
+			};
+			lines.push(format!("- **{}** {}", change.title(), kind));
+			for field in &change.fields {
+				lines.push(format!("  - `{}`", field.describe()));
+			}
+		}
+
+		lines.push(String::new());
+		lines.join("\n")
+	}
+}
@@ -0,0 +1,273 @@
+use std::error::Error;
+use std::fmt;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum SpritePackSection {
+	ImageDefs,
+	SpriteDefs,
+	Palettes,
+	PixelData
+}
+
+impl fmt::Display for SpritePackSection {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SpritePackSection::ImageDefs => write!(f, "image defs"),
+			SpritePackSection::SpriteDefs => write!(f, "sprite defs"),
+			SpritePackSection::Palettes => write!(f, "palettes"),
+			SpritePackSection::PixelData => write!(f, "pixel data")
+		}
+	}
+}
+
+// where in the bin a DataView was taken from, so read errors can say which part is broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DataContext {
+	File,
+	CardHeader,
+	PackTable,
+	DataPack,
+	DataPackTable(usize),
+	SpritePack,
+	SpritePackSection(SpritePackSection),
+	MenuStrings
+}
+
+impl fmt::Display for DataContext {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			DataContext::File => write!(f, "file"),
+			DataContext::CardHeader => write!(f, "card header"),
+			DataContext::PackTable => write!(f, "pack table"),
+			DataContext::DataPack => write!(f, "data pack"),
+			DataContext::DataPackTable(table) => write!(f, "data pack table {}", table),
+			DataContext::SpritePack => write!(f, "sprite pack"),
+			DataContext::SpritePackSection(section) => write!(f, "sprite pack {}", section),
+			DataContext::MenuStrings => write!(f, "menu strings")
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum FormatError {
+	File { offset: usize, reason: String },
+	CardHeader { offset: usize, reason: String },
+	PackTable { offset: usize, pack_index: Option<usize>, reason: String },
+	DataPack { offset: usize, reason: String },
+	DataPackTable { table: usize, offset: usize, entity_index: Option<usize>, reason: String },
+	SpritePack { offset: usize, reason: String },
+	SpritePackSection { section: SpritePackSection, offset: usize, entry_index: Option<usize>, reason: String },
+	MenuStrings { offset: usize, string_index: Option<usize>, reason: String },
+	ImageSet { image_index: usize, reason: String },
+	Sprite { sprite_index: usize, image_index: Option<usize>, reason: String },
+	Encoding { char_code_index: Option<usize>, reason: String },
+	SizeOverflow { region: String, size: usize, limit: usize },
+	FrameLayer { layer_index: usize, reason: String },
+	HeaderField { field: String, reason: String },
+	IdExhausted { entity: String },
+	NotFound { entity: String, index: usize },
+	Patch { reason: String },
+	Layout { source: String, reason: String },
+	Project { path: String, reason: String },
+	KnownDumps { reason: String },
+	Io { reason: String },
+	Parse { reason: String },
+	Image { reason: String },
+	Other { reason: String }
+}
+
+impl FormatError {
+	pub fn at(context: DataContext, offset: usize, index: Option<usize>, reason: &str) -> FormatError {
+		let reason = reason.to_string();
+		match context {
+			DataContext::File => FormatError::File { offset, reason },
+			DataContext::CardHeader => FormatError::CardHeader { offset, reason },
+			DataContext::PackTable => FormatError::PackTable { offset, pack_index: index, reason },
+			DataContext::DataPack => FormatError::DataPack { offset, reason },
+			DataContext::DataPackTable(table) => FormatError::DataPackTable { table, offset, entity_index: index, reason },
+			DataContext::SpritePack => FormatError::SpritePack { offset, reason },
+			DataContext::SpritePackSection(section) => FormatError::SpritePackSection { section, offset, entry_index: index, reason },
+			DataContext::MenuStrings => FormatError::MenuStrings { offset, string_index: index, reason }
+		}
+	}
+
+	pub fn kind(&self) -> &'static str {
+		match self {
+			FormatError::File { .. } => "file",
+			FormatError::CardHeader { .. } => "card header",
+			FormatError::PackTable { .. } => "pack table",
+			FormatError::DataPack { .. } => "data pack",
+			FormatError::DataPackTable { .. } => "data pack table",
+			FormatError::SpritePack { .. } => "sprite pack",
+			FormatError::SpritePackSection { .. } => "sprite pack section",
+			FormatError::MenuStrings { .. } => "menu strings",
+			FormatError::ImageSet { .. } => "image set",
+			FormatError::Sprite { .. } => "sprite",
+			FormatError::Encoding { .. } => "encoding",
+			FormatError::SizeOverflow { .. } => "size overflow",
+			FormatError::FrameLayer { .. } => "frame layer",
+			FormatError::HeaderField { .. } => "card header",
+			FormatError::IdExhausted { .. } => "id exhausted",
+			FormatError::NotFound { .. } => "not found",
+			FormatError::Patch { .. } => "patch",
+			FormatError::Layout { .. } => "layout",
+			FormatError::Project { .. } => "project",
+			FormatError::KnownDumps { .. } => "known dumps",
+			FormatError::Io { .. } => "io",
+			FormatError::Parse { .. } => "parse",
+			FormatError::Image { .. } => "image",
+			FormatError::Other { .. } => "other"
+		}
+	}
+
+	pub fn title(&self) -> String {
+		match self {
+			FormatError::File { .. } => "Invalid file".to_string(),
+			FormatError::CardHeader { .. } => "Invalid card header".to_string(),
+			FormatError::PackTable { .. } => "Invalid pack table".to_string(),
+			FormatError::DataPack { .. } => "Invalid data pack".to_string(),
+			FormatError::DataPackTable { table, .. } => format!("Invalid data pack table {}", table),
+			FormatError::SpritePack { .. } => "Invalid sprite pack".to_string(),
+			FormatError::SpritePackSection { section, .. } => format!("Invalid sprite pack {}", section),
+			FormatError::MenuStrings { .. } => "Invalid menu strings".to_string(),
+			FormatError::ImageSet { image_index, .. } => format!("Invalid image {}", image_index),
+			FormatError::Sprite { sprite_index, .. } => format!("Invalid sprite {}", sprite_index),
+			FormatError::Encoding { .. } => "Invalid text encoding".to_string(),
+			FormatError::SizeOverflow { region, .. } => format!("{} too large", region),
+			FormatError::FrameLayer { layer_index, .. } => format!("Invalid frame layer {}", layer_index),
+			FormatError::HeaderField { field, .. } => format!("Invalid {}", field),
+			FormatError::IdExhausted { entity } => format!("No free {} IDs", entity),
+			FormatError::NotFound { entity, .. } => format!("{} not found", entity),
+			FormatError::Patch { .. } => "Invalid patch".to_string(),
+			FormatError::Layout { .. } => "Invalid layout".to_string(),
+			FormatError::Project { .. } => "Invalid project".to_string(),
+			FormatError::KnownDumps { .. } => "Invalid known dumps file".to_string(),
+			FormatError::Io { .. } => "Unable to access file".to_string(),
+			FormatError::Parse { .. } => "Unable to parse file".to_string(),
+			FormatError::Image { .. } => "Invalid image".to_string(),
+			FormatError::Other { .. } => "Error".to_string()
+		}
+	}
+}
+
+impl fmt::Display for FormatError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			FormatError::CardHeader { offset, reason } => {
+				write!(f, "Invalid card header at offset 0x{:X}: {}", offset, reason)
+			},
+			FormatError::File { offset, reason } |
+			FormatError::DataPack { offset, reason } |
+			FormatError::SpritePack { offset, reason } => {
+				write!(f, "Unable to read {} at offset 0x{:X}: {}", self.kind(), offset, reason)
+			},
+			FormatError::PackTable { offset, pack_index, reason } => {
+				match pack_index {
+					Some(pack_index) => write!(f, "Unable to read pack table entry {} at offset 0x{:X}: {}", pack_index, offset, reason),
+					None => write!(f, "Unable to read pack table at offset 0x{:X}: {}", offset, reason)
+				}
+			},
+			FormatError::DataPackTable { table, offset, entity_index, reason } => {
+				match entity_index {
+					Some(entity_index) => write!(f, "Unable to read data pack table {}, entry {} at offset 0x{:X}: {}", table, entity_index, offset, reason),
+					None => write!(f, "Unable to read data pack table {} at offset 0x{:X}: {}", table, offset, reason)
+				}
+			},
+			FormatError::SpritePackSection { section, offset, entry_index, reason } => {
+				match entry_index {
+					Some(entry_index) => write!(f, "Unable to read sprite pack {}, entry {} at offset 0x{:X}: {}", section, entry_index, offset, reason),
+					None => write!(f, "Unable to read sprite pack {} at offset 0x{:X}: {}", section, offset, reason)
+				}
+			},
+			FormatError::MenuStrings { offset, string_index, reason } => {
+				match string_index {
+					Some(string_index) => write!(f, "Unable to read menu string {} at offset 0x{:X}: {}", string_index, offset, reason),
+					None => write!(f, "Unable to read menu strings at offset 0x{:X}: {}", offset, reason)
+				}
+			},
+			FormatError::ImageSet { image_index, reason } => {
+				write!(f, "Image {}: {}", image_index, reason)
+			},
+			FormatError::Sprite { sprite_index, image_index, reason } => {
+				match image_index {
+					Some(image_index) => write!(f, "Sprite {} of image {}: {}", sprite_index, image_index, reason),
+					None => write!(f, "Sprite {}: {}", sprite_index, reason)
+				}
+			},
+			FormatError::Encoding { char_code_index, reason } => {
+				match char_code_index {
+					Some(char_code_index) => write!(f, "Text encoding entry {}: {}", char_code_index, reason),
+					None => write!(f, "Text encoding: {}", reason)
+				}
+			},
+			FormatError::SizeOverflow { region, size, limit } => {
+				write!(f, "{} is {} bytes over budget ({} bytes, limit is {} bytes)", region, size.saturating_sub(*limit), size, limit)
+			},
+			FormatError::FrameLayer { layer_index, reason } => {
+				write!(f, "Layer {} {}", layer_index, reason)
+			},
+			FormatError::HeaderField { field, reason } => {
+				write!(f, "Invalid {}: {}", field, reason)
+			},
+			FormatError::IdExhausted { entity } => {
+				write!(f, "There are no free {} IDs left", entity)
+			},
+			FormatError::NotFound { entity, index } => {
+				write!(f, "{} {} not found", entity, index)
+			},
+			FormatError::Layout { source, reason } => {
+				write!(f, "Layout {}: {}", source, reason)
+			},
+			FormatError::Project { path, reason } => {
+				write!(f, "Unable to read {}: {}", path, reason)
+			},
+			FormatError::Patch { reason } |
+			FormatError::KnownDumps { reason } |
+			FormatError::Io { reason } |
+			FormatError::Parse { reason } |
+			FormatError::Image { reason } |
+			FormatError::Other { reason } => {
+				write!(f, "{}", reason)
+			}
+		}
+	}
+}
+
+impl Error for FormatError {}
+
+impl From<std::io::Error> for FormatError {
+	fn from(why: std::io::Error) -> Self {
+		FormatError::Io { reason: why.to_string() }
+	}
+}
+
+impl From<serde_json::Error> for FormatError {
+	fn from(why: serde_json::Error) -> Self {
+		FormatError::Parse { reason: why.to_string() }
+	}
+}
+
+// csv errors are either from the file itself or from a record that doesn't parse
+impl From<csv::Error> for FormatError {
+	fn from(why: csv::Error) -> Self {
+		if why.is_io_error() {
+			FormatError::Io { reason: why.to_string() }
+		} else {
+			FormatError::Parse { reason: why.to_string() }
+		}
+	}
+}
+
+impl From<toml::de::Error> for FormatError {
+	fn from(why: toml::de::Error) -> Self {
+		FormatError::Parse { reason: why.to_string() }
+	}
+}
+
+impl From<image::ImageError> for FormatError {
+	fn from(why: image::ImageError) -> Self {
+		FormatError::Image { reason: why.to_string() }
+	}
+}
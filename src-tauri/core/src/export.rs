@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use crate::data_pack::DataPack;
+use crate::sprite_pack::SpritePack;
+use crate::text::Text;
+use crate::error::FormatError;
+
+pub fn export_strings_to(path: &Path, menu_strings: Option<&[Text]>, data_pack: Option<&DataPack>) -> Result<(), FormatError> {
+	let mut wtr = csv::Writer::from_path(path)?;
+
+	let blank_line = ["", "", ""];
+
+	wtr.write_record(["ID", "Field", "Original Text"])?;
+
+	if let Some(menu_strings) = menu_strings {
+
+		wtr.write_record(blank_line)?;
+		wtr.write_record(["MENUS", "", ""])?;
+		wtr.write_record(blank_line)?;
+
+		for (i, str) in menu_strings.iter().enumerate() {
+			let mut id_written = false;
+			let pages = str.string.split("<hr>");
+			for page in pages {
+				let lines = page.split("<br>");
+				for line in lines {
+					if id_written {
+						wtr.write_record(["", "", line])?;
+					} else {
+						wtr.write_record([&i.to_string(), "", line])?;
+						id_written = true;
+					}
+				}
+				wtr.write_record(blank_line)?;
+			}
+			wtr.write_record(blank_line)?;
+		}
+	}
+
+	if let Some(data_pack) = data_pack {
+
+		wtr.write_record(blank_line)?;
+		wtr.write_record(["STRINGS", "", ""])?;
+		wtr.write_record(blank_line)?;
+
+		for (i, tamastring) in data_pack.tamastrings.iter().enumerate() {
+			let mut id_written = false;
+			let pages = tamastring.value.string.split("<hr>");
+			for page in pages {
+				let lines = page.split("<br>");
+				for line in lines {
+					if id_written {
+						wtr.write_record(["", "", line])?;
+					} else {
+						wtr.write_record([&i.to_string(), "", line])?;
+						id_written = true;
+					}
+				}
+				wtr.write_record(blank_line)?;
+			}
+			wtr.write_record(blank_line)?;
+		}
+
+		wtr.write_record(blank_line)?;
+		wtr.write_record(["ITEMS", "", ""])?;
+		wtr.write_record(blank_line)?;
+
+		for (i, item) in data_pack.items.iter().enumerate() {
+			wtr.write_record([&i.to_string(), "Name:", &item.name.string])?;
+			wtr.write_record(blank_line)?;
+		}
+
+		wtr.write_record(blank_line)?;
+		wtr.write_record(["CHARACTERS", "", ""])?;
+		wtr.write_record(blank_line)?;
+
+		for (i, char) in data_pack.characters.iter().enumerate() {
+			wtr.write_record([&i.to_string(), "Name:", &char.name.string])?;
+			wtr.write_record(["", "Pronoun:", &char.pronoun.string])?;
+			wtr.write_record(["", "Statement Ending:", &char.statement.string])?;
+			wtr.write_record(["", "Question Ending 1:", &char.question1.string])?;
+			wtr.write_record(["", "Question Ending 2:", &char.question2.string])?;
+			wtr.write_record(blank_line)?;
+		}
+	}
+
+	wtr.flush()?;
+	Ok(())
+}
+
+pub fn export_images_to(sprite_pack: &SpritePack, path: &Path) -> Result<(), FormatError> {
+	let base_name = path.file_stem().ok_or_else(|| FormatError::Io { reason: "Invalid file name for image export".to_string() })?.to_string_lossy();
+	for i in 0..sprite_pack.image_sets.len() {
+		let image_path = path.with_file_name(format!("{}-{}", base_name, i)).with_extension("png");
+		export_image_spritesheet_to(sprite_pack, i, &image_path)?;
+	}
+	Ok(())
+}
+
+pub fn export_image_spritesheet_to(sprite_pack: &SpritePack, image_index: usize, path: &Path) -> Result<(), FormatError> {
+	let image_set = sprite_pack.image_sets.get(image_index)
+		.ok_or_else(|| FormatError::ImageSet { image_index, reason: "image not found".to_string() })?;
+	let spritesheet = image_set.to_spritesheet()
+		.map_err(|why| FormatError::ImageSet { image_index, reason: why.to_string() })?;
+	spritesheet.save(path)?;
+	Ok(())
+}
@@ -0,0 +1,181 @@
+use crate::data_view::{ DataView, words_to_bytes };
+use crate::error::{ FormatError, DataContext };
+use crate::data_pack::{ DataPack, get_data_pack, save_data_pack };
+use crate::sprite_pack::SpritePack;
+use crate::text::{ Text, CharEncoding };
+use crate::layout::{ FirmwareLayout, detect_firmware_layout };
+use crate::size_report::SizeBudget;
+
+pub(crate) const PATCH_HEADER_START: [u8; 8] = [0x4F, 0x86, 0xA0, 0x86, 0x0A, 0xFE, 0x84, 0x30];
+pub(crate) const MENU_STRINGS_START: [u8; 4] = [0xF9, 0x01, 0xFB, 0x01];
+
+#[derive(Clone)]
+pub struct Firmware {
+	pub data_pack: DataPack,
+	pub sprite_pack: SpritePack,
+	pub menu_strings: Vec<Text>,
+	pub use_patch_header: bool,
+	pub layout: FirmwareLayout
+}
+
+pub fn read_firmware(data: &DataView, char_codes: &[CharEncoding]) -> Result<Firmware, FormatError> {
+	let layout = detect_firmware_layout(&data.data, &[])?;
+	read_firmware_with_layout(data, char_codes, &layout)
+}
+
+pub fn read_firmware_with_layout(data: &DataView, char_codes: &[CharEncoding], layout: &FirmwareLayout) -> Result<Firmware, FormatError> {
+	let use_patch_header = data.data.starts_with(&PATCH_HEADER_START);
+	let shift = layout.get_shift(&data.data);
+
+	let data_pack_start = layout.data_pack_start + shift;
+	let sprite_pack_start = layout.sprite_pack_start + shift;
+	let sprite_pack_size = layout.get_sprite_pack_size(data.len(), shift)
+		.ok_or_else(|| FormatError::SpritePack { offset: sprite_pack_start, reason: "sprite pack starts past the end of the file".to_string() })?;
+
+	let data_pack = get_data_pack(&data.chunk(data_pack_start, layout.data_pack_size)?.with_context(DataContext::DataPack), char_codes)?;
+	let sprite_pack = SpritePack::from_data(&data.chunk(sprite_pack_start, sprite_pack_size)?.with_context(DataContext::SpritePack))?;
+
+	let start_index = find_menu_strings(data, layout, shift)?;
+	let menu_strings = read_menu_strings(&data.chunk(start_index, data.len() - start_index)?.with_context(DataContext::MenuStrings), char_codes)?;
+
+	Ok(Firmware { data_pack, sprite_pack, menu_strings, use_patch_header, layout: layout.clone() })
+}
+
+fn find_menu_strings(data: &DataView, layout: &FirmwareLayout, shift: usize) -> Result<usize, FormatError> {
+	let start_index = match layout.menu_strings_start {
+		Some(start) => Some(start + shift).filter(|&start_index| data.data.get(start_index..).is_some_and(|bytes| bytes.starts_with(&MENU_STRINGS_START))),
+		None => data.find_bytes(&MENU_STRINGS_START)
+	};
+	start_index.ok_or_else(|| FormatError::MenuStrings { offset: layout.menu_strings_start.unwrap_or(0) + shift, string_index: None, reason: "can't find menu strings".to_string() })
+}
+
+// the regions written over the original firmware, in the order they're written
+struct FirmwareRegion {
+	name: &'static str,
+	start: usize,
+	capacity: usize,
+	data: Vec<u8>,
+	is_padded: bool
+}
+
+fn save_firmware_regions(firmware: &Firmware, original_data: &[u8]) -> Result<Vec<FirmwareRegion>, FormatError> {
+	let layout = &firmware.layout;
+	let shift = layout.get_shift(original_data);
+
+	let menu_strings_start = find_menu_strings(&DataView::new(original_data), layout, shift)?;
+	let menu_strings_capacity = layout.menu_strings_size.min(original_data.len() - menu_strings_start);
+
+	let data_pack_start = layout.data_pack_start + shift;
+	let mut data_pack_data = save_data_pack(&firmware.data_pack, data_pack_start)?;
+	if data_pack_data.get(77893) == Some(&0x09) {
+		data_pack_data[77893] = 0x89; // Fix a probably irrelevant discrepancy at the end of table 13 (graphic node offsets)
+	}
+
+	let sprite_pack_start = layout.sprite_pack_start + shift;
+	let sprite_pack_capacity = layout.get_sprite_pack_capacity(original_data.len(), shift)?;
+
+	Ok(vec![
+		FirmwareRegion { name: "Menu strings", start: menu_strings_start, capacity: menu_strings_capacity, data: save_menu_strings(&firmware.menu_strings)?, is_padded: true },
+		FirmwareRegion { name: "Data pack", start: data_pack_start, capacity: layout.data_pack_size, data: data_pack_data, is_padded: true },
+		FirmwareRegion { name: "Sprite pack", start: sprite_pack_start, capacity: sprite_pack_capacity, data: firmware.sprite_pack.as_bytes()?, is_padded: false }
+	])
+}
+
+pub fn get_firmware_budgets(firmware: &Firmware, original_data: &[u8]) -> Result<Vec<SizeBudget>, FormatError> {
+	let regions = save_firmware_regions(firmware, original_data)?;
+	Ok(regions.iter().map(|region| SizeBudget::new(region.name, region.data.len(), region.capacity)).collect())
+}
+
+pub fn save_firmware(firmware: &Firmware, original_data: &[u8], patch_header: Option<&[u8]>) -> Result<Vec<u8>, FormatError> {
+	let layout = &firmware.layout;
+	let already_has_header = layout.get_shift(original_data) > 0;
+
+	// every region is checked before any of them is written, so an overrun never spills into the next one
+	let regions = save_firmware_regions(firmware, original_data)?;
+	for region in &regions {
+		SizeBudget::new(region.name, region.data.len(), region.capacity).check()?;
+	}
+
+	let mut new_data = DataView::new(original_data);
+	for mut region in regions {
+		if region.is_padded {
+			region.data.resize(region.capacity, 0);
+		}
+		let end = region.start + region.data.len();
+		new_data.data.splice(region.start..end, region.data);
+	}
+
+	let use_patch_header = firmware.use_patch_header;
+	if use_patch_header && !already_has_header {
+		let header_file = patch_header
+			.ok_or_else(|| FormatError::File { offset: 0, reason: "unable to add patch header: no patch header provided".to_string() })?;
+		new_data.data.splice(0..0, header_file.to_vec());
+		new_data.data.splice((new_data.len() - layout.patch_header_size)..new_data.len(), Vec::new());
+	} else if !use_patch_header && already_has_header {
+		let padding = vec![0xFF; layout.patch_header_size];
+		new_data.data.splice(0..layout.patch_header_size, Vec::new());
+		new_data.data.extend_from_slice(&padding);
+	}
+
+	Ok(new_data.data)
+}
+
+pub fn read_menu_strings(data: &DataView, char_codes: &[CharEncoding]) -> Result<Vec<Text>, FormatError> {
+	let num_strings = data.get_u16(0)? as usize;
+
+	let mut offsets = Vec::new();
+	for i in 0..num_strings+1 {
+		let offset = data.get_u16((i+1)*2)? as usize;
+		offsets.push(offset);
+	}
+
+	let mut sizes = Vec::new();
+	for i in 0..num_strings {
+		let size = offsets[i+1].checked_sub(offsets[i])
+			.ok_or_else(|| data.error((i+1)*2, Some(i), "string ends before it starts"))?;
+		sizes.push(size)
+	}
+
+	let mut menu_strings: Vec<Text> = Vec::new();
+	for i in 0..num_strings {
+		let mut text_data: Vec<u16> = Vec::new();
+		for j in 0..sizes[i] {
+			let word = data.get_u16(offsets[i]*2 + j*2)?;
+			if word > 0 {
+				text_data.push(word);
+			}
+		}
+		menu_strings.push(Text::from_data(char_codes, &text_data));
+	}
+
+	Ok(menu_strings)
+}
+
+pub fn save_menu_strings(menu_strings: &[Text]) -> Result<Vec<u8>, FormatError> {
+	let mut offsets: Vec<usize> = vec![menu_strings.len() + 2];
+	for (i, menu_string) in menu_strings.iter().enumerate() {
+		let last_offset = offsets[i];
+		let string_size = menu_string.data.len();
+		offsets.push(last_offset + string_size + 1);
+	}
+	let last_offset = offsets.last().copied().unwrap_or(0);
+	if last_offset > u16::MAX as usize {
+		return Err(FormatError::SizeOverflow { region: "Menu strings".to_string(), size: last_offset * 2, limit: u16::MAX as usize * 2 });
+	}
+	let offsets = offsets.into_iter().map(|offset| offset as u16).collect();
+
+	let mut words: Vec<u16> = Vec::new();
+	for menu_string in menu_strings {
+		words = [words, menu_string.data.clone()].concat();
+		words.push(0);
+	}
+
+	let new_menu_strings_words = [
+		vec![menu_strings.len() as u16],
+		offsets,
+		words
+	].concat();
+	let new_menu_strings_data = words_to_bytes(&new_menu_strings_words);
+
+	Ok(new_menu_strings_data)
+}
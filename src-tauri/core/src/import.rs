@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use regex::Regex;
+
+use image::{ ImageReader, GenericImageView };
+
+use crate::data_pack::DataPack;
+use crate::sprite_pack::SpritePack;
+use crate::sprite_pack::palette::Color;
+use crate::text::{ Text, CharEncoding };
+use crate::error::FormatError;
+
+#[derive(Clone, Debug)]
+struct TamaStringTranslation {
+	id: u16,
+	value: String,
+	line_count: usize
+}
+
+impl TamaStringTranslation {
+	pub fn new(id: u16) -> TamaStringTranslation {
+		TamaStringTranslation {
+			id,
+			value: String::new(),
+			line_count: 0
+		}
+	}
+}
+
+enum StringType {
+	Unknown,
+	Menu,
+	Dialog,
+	Item,
+	Character,
+}
+
+pub fn import_strings_from(path: &Path, char_codes: &[CharEncoding], mut menu_strings: Option<&mut Vec<Text>>, mut data_pack: Option<&mut DataPack>) -> Result<(), FormatError> {
+	let mut current_string_type = StringType::Unknown;
+
+	let mut csv_reader = csv::Reader::from_path(path)?;
+	let mut temp_translation = TamaStringTranslation::new(0);
+	let mut last_line = String::new();
+
+	let mut add_string = |string_type: &StringType, id: u16, new_string: &str| {
+		match string_type {
+			StringType::Menu => {
+				if let Some(menu_strings) = menu_strings.as_deref_mut() {
+					if let Some(menu_string) = menu_strings.get_mut(id as usize) {
+						menu_string.set_string(char_codes, new_string);
+					}
+				}
+			},
+
+			StringType::Dialog => {
+				if let Some(data_pack) = data_pack.as_deref_mut() {
+					if let Some(tamastring) = data_pack.tamastrings.get_mut(id as usize) {
+						tamastring.value.set_string(char_codes, new_string);
+					}
+				}
+			},
+
+			StringType::Item => {
+				if let Some(data_pack) = data_pack.as_deref_mut() {
+					if let Some(item) = data_pack.items.get_mut(id as usize) {
+						item.name.set_string(char_codes, new_string);
+					}
+				}
+			},
+
+			StringType::Character => {
+				let substrings = new_string.split("<br>");
+
+				let mut new_name = String::new();
+				let mut new_pronoun = String::new();
+				let mut new_statement = String::new();
+				let mut new_question1 = String::new();
+				let mut new_question2 = String::new();
+
+				for (i, substring) in substrings.enumerate() {
+					match i {
+						0 => new_name = substring.to_string(),
+						1 => new_pronoun = substring.to_string(),
+						2 => new_statement = substring.to_string(),
+						3 => new_question1 = substring.to_string(),
+						4 => new_question2 = substring.to_string(),
+						_ => {}
+					}
+				}
+
+				if let Some(data_pack) = data_pack.as_deref_mut() {
+					if let Some(character) = data_pack.characters.get_mut(id as usize) {
+						character.name.set_string(char_codes, &new_name);
+						character.pronoun.set_string(char_codes, &new_pronoun);
+						character.statement.set_string(char_codes, &new_statement);
+						character.question1.set_string(char_codes, &new_question1);
+						character.question2.set_string(char_codes, &new_question2);
+					}
+				}
+			},
+			_ => {}
+		}
+	};
+
+	for result in csv_reader.records() {
+		let record = result?;
+
+		if let Some(id) = record.get(0) {
+
+			if let Ok(id) = id.parse::<u16>() {
+				if id > 0 {
+					add_string(&current_string_type, temp_translation.id, &temp_translation.value);
+				}
+
+				temp_translation = TamaStringTranslation::new(id);
+				if let Some(line) = record.get(2) {
+					temp_translation.value = line.to_string();
+					last_line = line.to_string();
+				}
+
+			} else if id.is_empty() && !temp_translation.value.is_empty() {
+				if let Some(line) = record.get(2) {
+					if !line.is_empty() {
+						temp_translation.line_count += 1;
+						if last_line.is_empty() {
+							temp_translation.value = format!("{}<hr>{}", temp_translation.value, line);
+						} else {
+							temp_translation.value = format!("{}<br>{}", temp_translation.value, line);
+						}
+					}
+					last_line = line.to_string();
+				}
+			} else {
+				add_string(&current_string_type, temp_translation.id, &temp_translation.value);
+				match id.to_uppercase().as_str() {
+					"MENUS" => current_string_type = StringType::Menu,
+					"DIALOG" | "STRINGS" => current_string_type = StringType::Dialog,
+					"ITEMS" => current_string_type = StringType::Item,
+					"CHARACTERS" => current_string_type = StringType::Character,
+					_ => {}
+				}
+			}
+		}
+	}
+
+	add_string(&current_string_type, temp_translation.id, &temp_translation.value);
+
+	Ok(())
+}
+
+// returns the indexes of the image sets that were replaced
+pub fn import_images_from(sprite_pack: &mut SpritePack, path: &Path) -> Result<Vec<usize>, FormatError> {
+	let re = Regex::new(r".+-(\d+).[Pp][Nn][Gg]$").map_err(|why| FormatError::Other { reason: why.to_string() })?;
+	let mut image_indexes = Vec::new();
+	for entry in path.read_dir()? {
+		let entry = entry?;
+		let entry_path = entry.path();
+		if let Some(filename) = entry_path.file_name() {
+			if let Some(caps) = re.captures(&filename.to_string_lossy()) {
+				if let Some(image_index_str) = caps.get(1) {
+					if let Ok(image_index) = image_index_str.as_str().parse::<usize>() {
+						import_image_spritesheet_from(sprite_pack, image_index, &entry_path)?;
+						image_indexes.push(image_index);
+					}
+				}
+			}
+		}
+	}
+	image_indexes.sort();
+	Ok(image_indexes)
+}
+
+pub fn import_image_spritesheet_from(sprite_pack: &mut SpritePack, image_index: usize, path: &Path) -> Result<(), FormatError> {
+	let spritesheet = ImageReader::open(path)?.decode()?;
+
+	let image_set = sprite_pack.image_sets.get_mut(image_index)
+		.ok_or_else(|| FormatError::ImageSet { image_index, reason: "image not found".to_string() })?;
+
+	let palette_count = image_set.palettes.len();
+	let subimage_count = image_set.subimages.len();
+
+	// make sure the spritesheet has the expected dimensions
+	let expected_width = image_set.width * subimage_count as u32;
+	let expected_height = image_set.height * palette_count as u32;
+	if spritesheet.width() != expected_width || spritesheet.height() != expected_height {
+		return Err(FormatError::ImageSet {
+			image_index,
+			reason: format!("spritesheet is {}x{} but should be {}x{}", spritesheet.width(), spritesheet.height(), expected_width, expected_height)
+		});
+	}
+
+	// get main palette
+	let img = spritesheet.view(0, 0, expected_width, image_set.height).to_image();
+	let mut main_palette = Vec::new();
+	let mut color_indexes = Vec::new();
+	for (i, color) in img.pixels().enumerate() {
+		let color = Color::from_rgba(color);
+		if !main_palette.contains(&color) {
+			main_palette.push(color);
+			color_indexes.push(i);
+		}
+	}
+
+	// get additional palettes
+	image_set.palettes = vec![main_palette];
+	if palette_count > 1 {
+		for i in 1..palette_count {
+			let y = i as u32 * image_set.height;
+			let mut palette = Vec::new();
+			let img = spritesheet.view(0, y, expected_width, image_set.height).to_image();
+			let pixels: Vec<Color> = img.pixels().map(Color::from_rgba).collect();
+			for color_index in &color_indexes {
+				palette.push(pixels[*color_index]);
+			}
+			image_set.palettes.push(palette);
+		}
+	}
+
+	// get pixel data
+	for (i, subimage) in image_set.subimages.iter_mut().enumerate() {
+		let x = i as u32 * image_set.width;
+		let img = spritesheet.view(x, 0, image_set.width, image_set.height).to_image();
+		subimage.pixel_data = Vec::new();
+		for color in img.pixels() {
+			let color = Color::from_rgba(color);
+			let pixel = image_set.palettes[0].iter().position(|c| *c == color)
+				.ok_or_else(|| FormatError::ImageSet { image_index, reason: format!("subimage {} uses a color that isn't in the first palette", i) })?;
+			subimage.pixel_data.push(pixel as u32);
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{ Serialize, Deserialize };
+
+use md5::{ Md5, Digest };
+
+use crate::BinType;
+use crate::error::FormatError;
+use crate::layout::{ FirmwareLayout, find_firmware_layout };
+use crate::smacard::get_hex_string;
+
+pub const KNOWN_DUMPS_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KnownDumps {
+	pub version: u32,
+	pub dumps: Vec<KnownDump>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KnownDump {
+	pub name: String,
+	pub bin_type: BinType,
+	#[serde(default)]
+	pub region: String,
+	#[serde(default)]
+	pub revision: String,
+	pub hashes: Vec<PartHash>
+}
+
+// either hash can be left out; every hash that is given has to match
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PartHash {
+	pub part: DumpPart,
+	#[serde(default)]
+	pub md5: Option<String>,
+	#[serde(default)]
+	pub crc32: Option<String>
+}
+
+// the code is everything in a firmware before the data pack, without the patch header
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpPart {
+	File,
+	Code,
+	DataPack,
+	SpritePack
+}
+
+impl DumpPart {
+	pub fn describe(&self) -> &'static str {
+		match self {
+			DumpPart::File => "whole file",
+			DumpPart::Code => "code",
+			DumpPart::DataPack => "data pack",
+			DumpPart::SpritePack => "sprite pack"
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Identification {
+	pub name: String,
+	pub region: String,
+	pub revision: String,
+	pub matched_parts: Vec<DumpPart>,
+	pub is_exact: bool
+}
+
+impl Identification {
+	pub fn describe(&self) -> String {
+		let mut title = self.name.clone();
+		let details: Vec<&str> = [self.region.as_str(), self.revision.as_str()].into_iter().filter(|detail| !detail.is_empty()).collect();
+		if !details.is_empty() {
+			title = format!("{} ({})", title, details.join(", "));
+		}
+		if self.is_exact {
+			title
+		} else {
+			let parts: Vec<&str> = self.matched_parts.iter().map(|part| part.describe()).collect();
+			format!("modified {} (matching {})", title, parts.join(", "))
+		}
+	}
+}
+
+pub fn read_known_dumps(path: &Path) -> Result<Vec<KnownDump>, FormatError> {
+	let known_dumps: KnownDumps = serde_json::from_str(&fs::read_to_string(path)?)?;
+	if known_dumps.version > KNOWN_DUMPS_VERSION {
+		return Err(FormatError::KnownDumps {
+			reason: format!("Known dumps file is version {}, but only version {} is supported", known_dumps.version, KNOWN_DUMPS_VERSION)
+		});
+	}
+	Ok(known_dumps.dumps)
+}
+
+pub fn get_part_hashes(data: &[u8], bin_type: &BinType, layouts: &[FirmwareLayout]) -> Vec<PartHash> {
+	get_parts(data, bin_type, layouts).into_iter()
+		.map(|(part, bytes)| PartHash {
+			part,
+			md5: Some(get_hex_string(&Md5::digest(bytes))),
+			crc32: Some(format!("{:08x}", crc32fast::hash(bytes)))
+		})
+		.collect()
+}
+
+// a whole-file match wins outright; otherwise the dump with the most matching parts is the likely base
+pub fn identify_dump(data: &[u8], bin_type: &BinType, known_dumps: &[KnownDump], layouts: &[FirmwareLayout]) -> Option<Identification> {
+	let hashes = get_part_hashes(data, bin_type, layouts);
+
+	known_dumps.iter()
+		.filter(|known_dump| known_dump.bin_type == *bin_type)
+		.filter_map(|known_dump| {
+			let matched_parts: Vec<DumpPart> = known_dump.hashes.iter()
+				.filter(|known_hash| hashes.iter().any(|hash| is_match(known_hash, hash)))
+				.map(|known_hash| known_hash.part)
+				.collect();
+			if matched_parts.is_empty() {
+				return None;
+			}
+			Some(Identification {
+				name: known_dump.name.clone(),
+				region: known_dump.region.clone(),
+				revision: known_dump.revision.clone(),
+				is_exact: matched_parts.contains(&DumpPart::File),
+				matched_parts
+			})
+		})
+		.max_by_key(|identification| (identification.is_exact, identification.matched_parts.len()))
+}
+
+fn is_match(known_hash: &PartHash, hash: &PartHash) -> bool {
+	let is_same = |known: &Option<String>, found: &Option<String>| match (known, found) {
+		(Some(known), Some(found)) => known.eq_ignore_ascii_case(found),
+		(None, _) => true,
+		(Some(_), None) => false
+	};
+	let has_any_hash = known_hash.md5.is_some() || known_hash.crc32.is_some();
+	known_hash.part == hash.part && has_any_hash && is_same(&known_hash.md5, &hash.md5) && is_same(&known_hash.crc32, &hash.crc32)
+}
+
+fn get_parts<'a>(data: &'a [u8], bin_type: &BinType, layouts: &[FirmwareLayout]) -> Vec<(DumpPart, &'a [u8])> {
+	let mut parts = vec![(DumpPart::File, data)];
+
+	match bin_type {
+		BinType::Firmware => {
+			if let Some(layout) = find_firmware_layout(data, layouts) {
+				let shift = layout.get_shift(data);
+				let data_pack_start = layout.data_pack_start + shift;
+				let sprite_pack_start = layout.sprite_pack_start + shift;
+				let sprite_pack_size = layout.get_sprite_pack_size(data.len(), shift).unwrap_or(0);
+				push_part(&mut parts, DumpPart::Code, data, shift, data_pack_start);
+				push_part(&mut parts, DumpPart::DataPack, data, data_pack_start, data_pack_start + layout.data_pack_size);
+				push_part(&mut parts, DumpPart::SpritePack, data, sprite_pack_start, sprite_pack_start + sprite_pack_size);
+			}
+		},
+		BinType::SmaCard => {
+			for (i, part) in [DumpPart::DataPack, DumpPart::SpritePack].into_iter().enumerate() {
+				let entry_start = 0x1004 + i * 16;
+				if let (Some(offset), Some(size)) = (read_u32(data, entry_start + 4), read_u32(data, entry_start + 12)) {
+					push_part(&mut parts, part, data, 0x1000 + offset, 0x1000 + offset + size);
+				}
+			}
+		}
+	}
+
+	parts
+}
+
+fn push_part<'a>(parts: &mut Vec<(DumpPart, &'a [u8])>, part: DumpPart, data: &'a [u8], start: usize, end: usize) {
+	if let Some(bytes) = data.get(start..end) {
+		parts.push((part, bytes));
+	}
+}
+
+fn read_u32(data: &[u8], i: usize) -> Option<usize> {
+	let bytes = data.get(i..i+4)?;
+	Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{ Serialize, Deserialize };
+
+use crate::error::FormatError;
+use crate::firmware::PATCH_HEADER_START;
+
+const SCAN_ALIGNMENT: usize = 0x1000;
+
+// offsets are for firmware without a patch header; a patch header pushes everything back by its size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareLayout {
+	pub name: String,
+	pub data_pack_start: usize,
+	pub data_pack_size: usize,
+	pub sprite_pack_start: usize,
+	#[serde(default)]
+	pub sprite_pack_size: Option<usize>,
+	#[serde(default = "default_patch_header_size")]
+	pub patch_header_size: usize,
+	#[serde(default)]
+	pub menu_strings_start: Option<usize>,
+	#[serde(default = "default_menu_strings_size")]
+	pub menu_strings_size: usize
+}
+
+fn default_patch_header_size() -> usize {
+	1024
+}
+
+fn default_menu_strings_size() -> usize {
+	29990
+}
+
+impl FirmwareLayout {
+	pub fn get_shift(&self, data: &[u8]) -> usize {
+		if data.starts_with(&PATCH_HEADER_START) { self.patch_header_size } else { 0 }
+	}
+
+	// for reading, the sprite pack runs to the end of the file unless the layout gives it a size
+	pub fn get_sprite_pack_size(&self, file_size: usize, shift: usize) -> Option<usize> {
+		let available_size = file_size.checked_sub(self.sprite_pack_start + shift)?;
+		Some(self.sprite_pack_size.map_or(available_size, |size| size.min(available_size)))
+	}
+
+	// for saving, the end of the file isn't a safe guess at how much room there is, so the layout has to say
+	pub fn get_sprite_pack_capacity(&self, file_size: usize, shift: usize) -> Result<usize, FormatError> {
+		let size = self.sprite_pack_size.ok_or_else(|| FormatError::Layout {
+			source: self.name.clone(),
+			reason: "no sprite_pack_size is given, so there's no telling how much room the sprite pack has".to_string()
+		})?;
+		Ok(size.min(file_size.saturating_sub(self.sprite_pack_start + shift)))
+	}
+
+	fn matches(&self, data: &[u8]) -> bool {
+		let shift = self.get_shift(data);
+		let data_pack_start = self.data_pack_start + shift;
+		let data_pack_ok = get_table_offsets_at(data, data_pack_start)
+			.is_some_and(|table_offsets| table_offsets[19] + 2 <= self.data_pack_size);
+		let sprite_pack_ok = self.get_sprite_pack_size(data.len(), shift)
+			.is_some_and(|size| has_sprite_pack_header_at(data, self.sprite_pack_start + shift, size));
+		data_pack_ok && sprite_pack_ok
+	}
+}
+
+pub fn get_builtin_layouts() -> Vec<FirmwareLayout> {
+	vec![
+		FirmwareLayout {
+			name: "Tamagotchi Smart".to_string(),
+			data_pack_start: 0x6CE000,
+			data_pack_size: 0x730000 - 0x6CE000,
+			sprite_pack_start: 0x730000,
+			sprite_pack_size: Some(0x1000000 - 0x730000),
+			patch_header_size: default_patch_header_size(),
+			menu_strings_start: None,
+			menu_strings_size: default_menu_strings_size()
+		}
+	]
+}
+
+pub fn read_layout_file(path: &Path) -> Result<FirmwareLayout, FormatError> {
+	let layout_error = |reason: String| FormatError::Layout { source: path.display().to_string(), reason };
+	let text = fs::read_to_string(path).map_err(|why| layout_error(why.to_string()))?;
+	let is_toml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml"));
+	let layout: FirmwareLayout = if is_toml {
+		toml::from_str(&text).map_err(|why| layout_error(why.to_string()))?
+	} else {
+		serde_json::from_str(&text).map_err(|why| layout_error(why.to_string()))?
+	};
+	if layout.data_pack_size < 80 {
+		return Err(layout_error(format!("\"{}\" has a data pack too small for its table offsets", layout.name)));
+	}
+	Ok(layout)
+}
+
+pub fn read_layout_folder(folder: &Path) -> Result<Vec<FirmwareLayout>, FormatError> {
+	let mut paths = Vec::new();
+	for entry in fs::read_dir(folder)? {
+		let path = entry?.path();
+		let is_layout_file = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml") || ext.eq_ignore_ascii_case("json"));
+		if is_layout_file {
+			paths.push(path);
+		}
+	}
+	paths.sort();
+	paths.iter()
+		.map(|path| read_layout_file(path))
+		.collect()
+}
+
+// user layouts come first so they can override a built-in layout for the same firmware
+pub fn find_firmware_layout(data: &[u8], user_layouts: &[FirmwareLayout]) -> Option<FirmwareLayout> {
+	user_layouts.iter().cloned()
+		.chain(get_builtin_layouts())
+		.find(|layout| layout.matches(data))
+		.or_else(|| scan_firmware_layout(data))
+}
+
+pub fn detect_firmware_layout(data: &[u8], user_layouts: &[FirmwareLayout]) -> Result<FirmwareLayout, FormatError> {
+	find_firmware_layout(data, user_layouts)
+		.ok_or_else(|| FormatError::Layout {
+			source: "detection".to_string(),
+			reason: "Unable to find the data pack and sprite pack in this firmware; it may need a layout file".to_string()
+		})
+}
+
+// look for a data pack's table offsets followed by a sprite pack's section offsets, both on sector boundaries
+fn scan_firmware_layout(data: &[u8]) -> Option<FirmwareLayout> {
+	let shift = if data.starts_with(&PATCH_HEADER_START) { default_patch_header_size() } else { 0 };
+	let file_size = data.len().checked_sub(shift)?;
+
+	for data_pack_start in (0..file_size).step_by(SCAN_ALIGNMENT) {
+		let Some(table_offsets) = get_table_offsets_at(data, data_pack_start + shift) else { continue };
+		let data_pack_end = data_pack_start + table_offsets[19] + 2;
+		let first_sprite_pack_start = (data_pack_end + SCAN_ALIGNMENT - 1) / SCAN_ALIGNMENT * SCAN_ALIGNMENT;
+		let sprite_pack_start = (first_sprite_pack_start..file_size).step_by(SCAN_ALIGNMENT)
+			.find(|&start| has_sprite_pack_header_at(data, start + shift, file_size - start));
+		// a scan only has this dump to go on, so the sprite pack gets the rest of it
+		if let Some(sprite_pack_start) = sprite_pack_start {
+			return Some(FirmwareLayout {
+				name: "Detected".to_string(),
+				data_pack_start,
+				data_pack_size: sprite_pack_start - data_pack_start,
+				sprite_pack_start,
+				sprite_pack_size: Some(file_size - sprite_pack_start),
+				patch_header_size: default_patch_header_size(),
+				menu_strings_start: None,
+				menu_strings_size: default_menu_strings_size()
+			});
+		}
+	}
+
+	None
+}
+
+fn read_u32(data: &[u8], i: usize) -> Option<usize> {
+	let bytes = data.get(i..i+4)?;
+	Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+// the first table always starts right after the 20 table offsets, and the rest follow in order
+fn get_table_offsets_at(data: &[u8], start: usize) -> Option<Vec<usize>> {
+	let table_offsets = (0..20)
+		.map(|i| read_u32(data, start + i*4).map(|offset| offset * 2))
+		.collect::<Option<Vec<usize>>>()?;
+	let is_in_order = table_offsets.windows(2).all(|pair| pair[0] <= pair[1]);
+	let is_in_file = start + table_offsets[19] + 2 <= data.len();
+	if table_offsets[0] == 80 && is_in_order && is_in_file {
+		Some(table_offsets)
+	} else {
+		None
+	}
+}
+
+// the image defs always start right after the 4 section offsets
+fn has_sprite_pack_header_at(data: &[u8], start: usize, size: usize) -> bool {
+	let section_offsets: Option<Vec<usize>> = (0..4).map(|i| read_u32(data, start + i*4)).collect();
+	section_offsets.is_some_and(|section_offsets| {
+		section_offsets[0] == 16
+			&& section_offsets.windows(2).all(|pair| pair[0] < pair[1])
+			&& section_offsets[3] < size
+			&& start + size <= data.len()
+	})
+}
@@ -0,0 +1,83 @@
+#![allow(clippy::type_complexity)]
+
+pub mod smacard;
+pub mod firmware;
+pub mod data_view;
+pub mod data_pack;
+pub mod sprite_pack;
+pub mod text;
+pub mod export;
+pub mod import;
+pub mod verify;
+pub mod error;
+pub mod project;
+pub mod diff;
+pub mod patch;
+pub mod safe_write;
+pub mod size_report;
+pub mod library;
+pub mod detect;
+pub mod layout;
+pub mod known_dumps;
+
+#[cfg(test)]
+mod test_util;
+
+pub use smacard::{ TamaSmaCard, read_card, save_card };
+pub use firmware::{ Firmware, read_firmware, save_firmware };
+pub use layout::FirmwareLayout;
+pub use text::CharEncoding;
+pub use error::FormatError;
+
+use data_view::DataView;
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum BinType {
+	Firmware,
+	SmaCard
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum BinSize {
+	Firmware,
+	Card128KB,
+	Card1MB,
+	Card2MB,
+	Auto,
+	TooBig
+}
+
+pub const CARD_SIZES: [BinSize; 3] = [BinSize::Card128KB, BinSize::Card1MB, BinSize::Card2MB];
+
+impl BinSize {
+	// the size of the whole card file, including the header and the signature at the end
+	pub fn card_capacity(&self) -> Option<usize> {
+		match self {
+			BinSize::Card128KB => Some(0x20000),
+			BinSize::Card1MB => Some(0x100000),
+			BinSize::Card2MB => Some(0x200000),
+			_ => None
+		}
+	}
+}
+
+pub fn get_smallest_card_size(used_size: usize) -> Option<BinSize> {
+	CARD_SIZES.iter().find(|bin_size| bin_size.card_capacity().is_some_and(|capacity| capacity >= used_size)).cloned()
+}
+
+// the best guess from the file's contents; use detect::detect_bin_type to see how sure it is
+pub fn get_bin_type(data: &DataView) -> BinType {
+	detect::detect_bin_type(data).bin_type
+}
+
+pub fn get_card_size(data: &DataView) -> BinSize {
+	if data.len() <= 0x20000 {
+		BinSize::Card128KB
+	} else if data.len() <= 0x100000 {
+		BinSize::Card1MB
+	} else if data.len() <= 0x200000 {
+		BinSize::Card2MB
+	} else {
+		BinSize::TooBig
+	}
+}
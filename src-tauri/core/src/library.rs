@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{ Path, PathBuf };
+use std::time::UNIX_EPOCH;
+
+use serde::{ Serialize, Deserialize };
+
+use crate::BinType;
+use crate::data_view::DataView;
+use crate::error::{ FormatError, DataContext };
+use crate::smacard::{ CardHeader, read_card_header, calc_md5 };
+use crate::detect::detect_bin_type;
+
+pub const LIBRARY_INDEX_VERSION: u32 = 2;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LibraryIndex {
+	pub version: u32,
+	pub entries: Vec<LibraryEntry>
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LibraryEntry {
+	pub path: PathBuf,
+	pub file_size: u64,
+	pub modified: u64,
+	pub bin_type: BinType,
+	pub card_header: Option<CardHeader>,
+	pub md5_ok: bool,
+	pub pack_count: Option<u16>,
+	pub error: Option<String>
+}
+
+#[derive(Clone, Serialize)]
+pub struct Library {
+	pub folder: PathBuf,
+	pub entries: Vec<LibraryEntry>,
+	pub duplicate_card_ids: Vec<u16>,
+	pub rescanned_count: usize
+}
+
+// files whose size and modified time match the index aren't read again; the index can hold
+// entries from several folders, and only the scanned folder's entries are replaced
+pub fn scan_library(folder: &Path, index_path: &Path) -> Result<Library, FormatError> {
+	let folder = &fs::canonicalize(folder)?;
+	let mut index = read_library_index(index_path);
+
+	let mut paths = Vec::new();
+	find_bin_files(folder, &mut paths)?;
+	paths.sort();
+
+	let cached_entries: HashMap<PathBuf, LibraryEntry> = index.entries.iter()
+		.filter(|entry| entry.path.starts_with(folder))
+		.map(|entry| (entry.path.clone(), entry.clone()))
+		.collect();
+
+	let mut entries = Vec::new();
+	let mut rescanned_count = 0;
+	for path in paths {
+		let metadata = fs::metadata(&path)?;
+		let modified = metadata.modified()
+			.map(|modified| modified.duration_since(UNIX_EPOCH).map(|duration| duration.as_millis() as u64).unwrap_or(0))
+			.unwrap_or(0);
+
+		match cached_entries.get(&path) {
+			Some(entry) if entry.file_size == metadata.len() && entry.modified == modified => {
+				entries.push(entry.clone());
+			},
+			_ => {
+				let entry = read_library_entry(&path, metadata.len(), modified);
+				if entry.bin_type == BinType::SmaCard {
+					rescanned_count += 1;
+				}
+				entries.push(entry);
+			}
+		}
+	}
+
+	index.version = LIBRARY_INDEX_VERSION;
+	index.entries.retain(|entry| !entry.path.starts_with(folder));
+	index.entries.extend(entries.iter().cloned());
+	write_library_index(index_path, &index)?;
+
+	// firmware stays in the index so it isn't read again, but it isn't part of the card library
+	entries.retain(|entry| entry.bin_type == BinType::SmaCard);
+
+	let duplicate_card_ids = find_duplicate_card_ids(&entries);
+
+	Ok(Library { folder: folder.to_path_buf(), entries, duplicate_card_ids, rescanned_count })
+}
+
+fn find_bin_files(folder: &Path, paths: &mut Vec<PathBuf>) -> Result<(), FormatError> {
+	for dir_entry in fs::read_dir(folder)? {
+		let path = dir_entry?.path();
+		if path.is_dir() {
+			find_bin_files(&path, paths)?;
+		} else if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("bin")) {
+			paths.push(path);
+		}
+	}
+	Ok(())
+}
+
+// firmware is told apart by its contents rather than its size, so a patched or trimmed dump is still skipped
+fn read_library_entry(path: &Path, file_size: u64, modified: u64) -> LibraryEntry {
+	let mut entry = LibraryEntry { path: path.to_path_buf(), file_size, modified, bin_type: BinType::SmaCard, card_header: None, md5_ok: false, pack_count: None, error: None };
+
+	let data = match fs::read(path) {
+		Ok(data) => data,
+		Err(why) => {
+			entry.error = Some(why.to_string());
+			return entry;
+		}
+	};
+
+	entry.bin_type = detect_bin_type(&DataView::new(&data)).bin_type;
+	if entry.bin_type == BinType::Firmware {
+		return entry;
+	}
+
+	let header_data = DataView::new(&data[..data.len().min(0x1000)]).with_context(DataContext::CardHeader);
+	match read_card_header(&header_data) {
+		Ok(card_header) => {
+			entry.md5_ok = calc_md5(&data) == card_header.md5;
+			entry.card_header = Some(card_header);
+		},
+		Err(why) => entry.error = Some(why.to_string())
+	}
+
+	if data.len() >= 0x1000 + 4 {
+		entry.pack_count = Some(u16::from_le_bytes([data[0x1002], data[0x1003]]));
+	}
+
+	entry
+}
+
+// every card ID used by more than one file is reported, even when the files are copies of the same card
+fn find_duplicate_card_ids(entries: &[LibraryEntry]) -> Vec<u16> {
+	let mut counts_by_card_id: HashMap<u16, usize> = HashMap::new();
+	for card_header in entries.iter().filter_map(|entry| entry.card_header.as_ref()) {
+		*counts_by_card_id.entry(card_header.card_id).or_default() += 1;
+	}
+	let mut duplicate_card_ids: Vec<u16> = counts_by_card_id.into_iter()
+		.filter(|(_, count)| *count > 1)
+		.map(|(card_id, _)| card_id)
+		.collect();
+	duplicate_card_ids.sort();
+	duplicate_card_ids
+}
+
+// a missing or unreadable index just means every file gets read again
+fn read_library_index(index_path: &Path) -> LibraryIndex {
+	fs::read_to_string(index_path).ok()
+		.and_then(|json| serde_json::from_str::<LibraryIndex>(&json).ok())
+		.filter(|index| index.version == LIBRARY_INDEX_VERSION)
+		.unwrap_or(LibraryIndex { version: LIBRARY_INDEX_VERSION, entries: Vec::new() })
+}
+
+fn write_library_index(index_path: &Path, index: &LibraryIndex) -> Result<(), FormatError> {
+	if let Some(parent) = index_path.parent() {
+		fs::create_dir_all(parent)?;
+	}
+	fs::write(index_path, serde_json::to_string(index)?)?;
+	Ok(())
+}
@@ -0,0 +1,361 @@
+use serde::Serialize;
+
+use crate::error::FormatError;
+
+const IPS_HEADER: &[u8] = b"PATCH";
+const IPS_FOOTER: &[u8] = b"EOF";
+const IPS_MAX_SIZE: usize = 0x1000000;
+const IPS_MAX_RECORD_SIZE: usize = 0xFFFF;
+
+const BPS_HEADER: &[u8] = b"BPS1";
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum PatchFormat {
+	Ips,
+	Bps
+}
+
+impl PatchFormat {
+	pub fn extension(&self) -> &'static str {
+		match self {
+			PatchFormat::Ips => "ips",
+			PatchFormat::Bps => "bps"
+		}
+	}
+}
+
+// IPS offsets are 24-bit, so files of 16 MB or more (like a full firmware dump) need BPS
+pub fn get_patch_format(file_size: usize) -> PatchFormat {
+	if file_size < IPS_MAX_SIZE {
+		PatchFormat::Ips
+	} else {
+		PatchFormat::Bps
+	}
+}
+
+pub fn create_patch(original_data: &[u8], new_data: &[u8]) -> Result<(PatchFormat, Vec<u8>), FormatError> {
+	let patch_format = get_patch_format(original_data.len().max(new_data.len()));
+	let patch = match patch_format {
+		PatchFormat::Ips => create_ips(original_data, new_data)?,
+		PatchFormat::Bps => create_bps(original_data, new_data)
+	};
+	Ok((patch_format, patch))
+}
+
+pub fn apply_patch(original_data: &[u8], patch: &[u8]) -> Result<Vec<u8>, FormatError> {
+	if patch.starts_with(BPS_HEADER) {
+		apply_bps(original_data, patch)
+	} else if patch.starts_with(IPS_HEADER) {
+		apply_ips(original_data, patch)
+	} else {
+		Err(patch_error("Unrecognized patch format: expected an IPS or BPS file"))
+	}
+}
+
+fn patch_error(reason: &str) -> FormatError {
+	FormatError::Patch { reason: reason.to_string() }
+}
+
+fn get_ips_offset(offset: usize) -> [u8; 3] {
+	let bytes = (offset as u32).to_be_bytes();
+	[bytes[1], bytes[2], bytes[3]]
+}
+
+pub fn create_ips(original_data: &[u8], new_data: &[u8]) -> Result<Vec<u8>, FormatError> {
+	if original_data.len() >= IPS_MAX_SIZE || new_data.len() >= IPS_MAX_SIZE {
+		return Err(patch_error("IPS patches only support files under 16 MB; use BPS instead"));
+	}
+
+	let mut patch = IPS_HEADER.to_vec();
+
+	let mut i = 0;
+	while i < new_data.len() {
+		if original_data.get(i) == Some(&new_data[i]) {
+			i += 1;
+			continue;
+		}
+
+		// an offset that spells out "EOF" would end the patch early, so start one byte sooner
+		let start = if get_ips_offset(i) == IPS_FOOTER { i - 1 } else { i };
+		let mut end = i;
+		while end < new_data.len() && end - start < IPS_MAX_RECORD_SIZE && original_data.get(end) != Some(&new_data[end]) {
+			end += 1;
+		}
+
+		patch.extend_from_slice(&get_ips_offset(start));
+		patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+		patch.extend_from_slice(&new_data[start..end]);
+		i = end;
+	}
+
+	patch.extend_from_slice(IPS_FOOTER);
+
+	if new_data.len() < original_data.len() {
+		patch.extend_from_slice(&get_ips_offset(new_data.len()));
+	}
+
+	Ok(patch)
+}
+
+pub fn apply_ips(original_data: &[u8], patch: &[u8]) -> Result<Vec<u8>, FormatError> {
+	let mut new_data = original_data.to_vec();
+	let mut i = IPS_HEADER.len();
+
+	let read = |i: usize, size: usize| -> Result<&[u8], FormatError> {
+		patch.get(i..i+size).ok_or_else(|| patch_error(&format!("IPS patch ends unexpectedly at offset 0x{:X}", i)))
+	};
+
+	loop {
+		let offset_bytes = read(i, 3)?;
+		if offset_bytes == IPS_FOOTER {
+			i += 3;
+			break;
+		}
+		let offset = u32::from_be_bytes([0, offset_bytes[0], offset_bytes[1], offset_bytes[2]]) as usize;
+		let size_bytes = read(i + 3, 2)?;
+		let size = u16::from_be_bytes([size_bytes[0], size_bytes[1]]) as usize;
+		i += 5;
+
+		// a record with size 0 is run-length encoded: a 2 byte count then the byte to repeat
+		let (record, record_size) = if size == 0 {
+			let rle_bytes = read(i, 3)?;
+			let count = u16::from_be_bytes([rle_bytes[0], rle_bytes[1]]) as usize;
+			i += 3;
+			(vec![rle_bytes[2]; count], count)
+		} else {
+			let record = read(i, size)?.to_vec();
+			i += size;
+			(record, size)
+		};
+
+		if new_data.len() < offset + record_size {
+			new_data.resize(offset + record_size, 0);
+		}
+		new_data[offset..offset+record_size].copy_from_slice(&record);
+	}
+
+	if let Ok(truncate_bytes) = read(i, 3) {
+		let truncate_size = u32::from_be_bytes([0, truncate_bytes[0], truncate_bytes[1], truncate_bytes[2]]) as usize;
+		new_data.truncate(truncate_size);
+	}
+
+	Ok(new_data)
+}
+
+fn write_number(data: &mut Vec<u8>, number: usize) {
+	let mut number = number as u64;
+	loop {
+		let x = (number & 0x7f) as u8;
+		number >>= 7;
+		if number == 0 {
+			data.push(0x80 | x);
+			break;
+		}
+		data.push(x);
+		number -= 1;
+	}
+}
+
+fn read_number(patch: &[u8], i: &mut usize) -> Result<usize, FormatError> {
+	let mut number: u64 = 0;
+	let mut shift: u64 = 1;
+	loop {
+		let x = *patch.get(*i).ok_or_else(|| patch_error("BPS patch ends unexpectedly"))? as u64;
+		*i += 1;
+		number = number.checked_add((x & 0x7f) * shift).ok_or_else(|| patch_error("BPS patch has an invalid number"))?;
+		if x & 0x80 != 0 {
+			break;
+		}
+		shift = shift.checked_shl(7).filter(|shift| *shift < (1 << 56)).ok_or_else(|| patch_error("BPS patch has an invalid number"))?;
+		number += shift;
+	}
+	Ok(number as usize)
+}
+
+fn read_crc32(patch: &[u8], i: usize) -> u32 {
+	u32::from_le_bytes([patch[i], patch[i+1], patch[i+2], patch[i+3]])
+}
+
+// writes "source read" actions wherever the bytes are unchanged and "target read" actions everywhere else
+pub fn create_bps(original_data: &[u8], new_data: &[u8]) -> Vec<u8> {
+	let mut patch = BPS_HEADER.to_vec();
+	write_number(&mut patch, original_data.len());
+	write_number(&mut patch, new_data.len());
+	write_number(&mut patch, 0);
+
+	let mut i = 0;
+	while i < new_data.len() {
+		let is_same = |j: usize| original_data.get(j) == Some(&new_data[j]);
+		let start = i;
+		if is_same(i) {
+			while i < new_data.len() && is_same(i) {
+				i += 1;
+			}
+			write_number(&mut patch, (i - start - 1) << 2);
+		} else {
+			while i < new_data.len() && !is_same(i) {
+				i += 1;
+			}
+			write_number(&mut patch, ((i - start - 1) << 2) | 1);
+			patch.extend_from_slice(&new_data[start..i]);
+		}
+	}
+
+	patch.extend_from_slice(&crc32fast::hash(original_data).to_le_bytes());
+	patch.extend_from_slice(&crc32fast::hash(new_data).to_le_bytes());
+	let patch_crc32 = crc32fast::hash(&patch);
+	patch.extend_from_slice(&patch_crc32.to_le_bytes());
+
+	patch
+}
+
+pub fn apply_bps(original_data: &[u8], patch: &[u8]) -> Result<Vec<u8>, FormatError> {
+	if patch.len() < BPS_HEADER.len() + 12 {
+		return Err(patch_error("BPS patch is too small"));
+	}
+	let footer_start = patch.len() - 12;
+
+	let patch_crc32 = read_crc32(patch, footer_start + 8);
+	if crc32fast::hash(&patch[..footer_start + 8]) != patch_crc32 {
+		return Err(patch_error("BPS patch is damaged: its checksum doesn't match"));
+	}
+
+	let source_crc32 = read_crc32(patch, footer_start);
+	let actual_source_crc32 = crc32fast::hash(original_data);
+	if actual_source_crc32 != source_crc32 {
+		return Err(patch_error(&format!("This patch was made for a different base file (CRC32 {:08X}, but the open file is {:08X})", source_crc32, actual_source_crc32)));
+	}
+
+	let mut i = BPS_HEADER.len();
+	let source_size = read_number(patch, &mut i)?;
+	let target_size = read_number(patch, &mut i)?;
+	let metadata_size = read_number(patch, &mut i)?;
+	i += metadata_size;
+
+	if source_size != original_data.len() {
+		return Err(patch_error(&format!("This patch was made for a {} byte file, but the open file is {} bytes", source_size, original_data.len())));
+	}
+
+	let mut new_data: Vec<u8> = Vec::new();
+	let mut source_offset: isize = 0;
+	let mut target_offset: isize = 0;
+
+	let read_offset = |i: &mut usize| -> Result<isize, FormatError> {
+		let number = read_number(patch, i)?;
+		let offset = (number >> 1) as isize;
+		Ok(if number & 1 != 0 { -offset } else { offset })
+	};
+
+	while i < footer_start {
+		let action = read_number(patch, &mut i)?;
+		let length = (action >> 2) + 1;
+		if new_data.len() + length > target_size {
+			return Err(patch_error("BPS patch writes past the end of the target file"));
+		}
+		match action & 3 {
+			0 => {
+				let start = new_data.len();
+				let source = original_data.get(start..start+length).ok_or_else(|| patch_error("BPS patch reads past the end of the base file"))?;
+				new_data.extend_from_slice(source);
+			},
+			1 => {
+				let target = patch.get(i..i+length).filter(|_| i + length <= footer_start).ok_or_else(|| patch_error("BPS patch ends unexpectedly"))?;
+				new_data.extend_from_slice(target);
+				i += length;
+			},
+			2 => {
+				source_offset += read_offset(&mut i)?;
+				let start = usize::try_from(source_offset).map_err(|_| patch_error("BPS patch has an invalid source offset"))?;
+				let source = original_data.get(start..start+length).ok_or_else(|| patch_error("BPS patch reads past the end of the base file"))?;
+				new_data.extend_from_slice(source);
+				source_offset += length as isize;
+			},
+			_ => {
+				target_offset += read_offset(&mut i)?;
+				// target copies can overlap the bytes they are writing, so copy one at a time
+				for _ in 0..length {
+					let start = usize::try_from(target_offset).map_err(|_| patch_error("BPS patch has an invalid target offset"))?;
+					let byte = *new_data.get(start).ok_or_else(|| patch_error("BPS patch has an invalid target offset"))?;
+					new_data.push(byte);
+					target_offset += 1;
+				}
+			}
+		}
+	}
+
+	if new_data.len() != target_size {
+		return Err(patch_error(&format!("BPS patch produced {} bytes, but should produce {} bytes", new_data.len(), target_size)));
+	}
+
+	let target_crc32 = read_crc32(patch, footer_start + 4);
+	if crc32fast::hash(&new_data) != target_crc32 {
+		return Err(patch_error("Patched file doesn't match the patch's checksum"));
+	}
+
+	Ok(new_data)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn get_test_data(size: usize) -> Vec<u8> {
+		(0..size).map(|i| (i * 7 % 251) as u8).collect()
+	}
+
+	fn get_edited_data(original_data: &[u8], new_size: usize) -> Vec<u8> {
+		let mut new_data = original_data.to_vec();
+		new_data.resize(new_size, 0xAA);
+		for i in (3..new_data.len().min(original_data.len())).step_by(97) {
+			new_data[i] ^= 0xFF;
+		}
+		new_data
+	}
+
+	#[test]
+	fn ips_round_trip() {
+		let original_data = get_test_data(0x20000);
+		for new_size in [0x20000, 0x21000, 0x1F000] {
+			let new_data = get_edited_data(&original_data, new_size);
+			let patch = create_ips(&original_data, &new_data).unwrap();
+			assert!(apply_patch(&original_data, &patch).unwrap() == new_data);
+		}
+	}
+
+	// a change at 0x454F46 would otherwise be written with an offset that reads as "EOF"
+	#[test]
+	fn ips_round_trip_at_eof_offset() {
+		let original_data = get_test_data(0x454F50);
+		let mut new_data = original_data.clone();
+		new_data[0x454F46] ^= 0xFF;
+		let patch = create_ips(&original_data, &new_data).unwrap();
+		assert!(apply_ips(&original_data, &patch).unwrap() == new_data);
+	}
+
+	#[test]
+	fn bps_round_trip() {
+		let original_data = get_test_data(0x20000);
+		for new_size in [0x20000, 0x21000, 0x1F000] {
+			let new_data = get_edited_data(&original_data, new_size);
+			let patch = create_bps(&original_data, &new_data);
+			assert!(apply_patch(&original_data, &patch).unwrap() == new_data);
+		}
+	}
+
+	#[test]
+	fn bps_rejects_another_base() {
+		let original_data = get_test_data(0x1000);
+		let new_data = get_edited_data(&original_data, 0x1000);
+		let patch = create_bps(&original_data, &new_data);
+		let mut other_data = original_data.clone();
+		other_data[0] ^= 0xFF;
+		assert!(matches!(apply_bps(&other_data, &patch), Err(FormatError::Patch { .. })));
+	}
+
+	#[test]
+	fn patch_format_depends_on_size() {
+		assert_eq!(get_patch_format(IPS_MAX_SIZE - 1), PatchFormat::Ips);
+		assert_eq!(get_patch_format(IPS_MAX_SIZE), PatchFormat::Bps);
+		assert!(create_ips(&[], &vec![0; IPS_MAX_SIZE]).is_err());
+	}
+}
@@ -0,0 +1,336 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{ Serialize, Deserialize };
+use serde::de::DeserializeOwned;
+use image::{ Rgba, RgbaImage, ImageReader };
+
+use crate::{ BinType, BinSize };
+use crate::error::FormatError;
+use crate::smacard::{ CardHeader, PackTable };
+use crate::data_pack::DataPack;
+use crate::sprite_pack::SpritePack;
+use crate::sprite_pack::image_def::{ ImageSet, SubImage, save_image_sets };
+use crate::sprite_pack::palette::Color;
+use crate::text::{ Text, CharEncoding, encode_string };
+
+pub const PROJECT_VERSION: u32 = 1;
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ProjectInfo {
+	pub version: u32,
+	pub bin_type: BinType,
+	pub bin_size: BinSize,
+	pub use_patch_header: Option<bool>
+}
+
+// the image count is only needed to read the project back in, so it's worked out here rather than by the caller
+#[derive(Serialize, Deserialize)]
+struct ProjectFile {
+	#[serde(flatten)]
+	info: ProjectInfo,
+	image_count: usize
+}
+
+pub struct Project {
+	pub info: ProjectInfo,
+	pub card_header: Option<CardHeader>,
+	pub pack_table: Option<PackTable>,
+	pub data_pack: DataPack,
+	pub sprite_pack: SpritePack,
+	pub menu_strings: Option<Vec<Text>>
+}
+
+#[derive(Serialize, Deserialize)]
+struct ImageSetInfo {
+	original_index: usize,
+	width: u32,
+	height: u32,
+	width_in_sprites: u32,
+	height_in_sprites: u32,
+	is_quadrupled: bool,
+	first_palette_index: usize,
+	palettes: Vec<Vec<Color>>,
+	subimages: Vec<SubImageInfo>
+}
+
+// pixel data is only stored when the spritesheet alone can't tell two palette entries apart
+#[derive(Serialize, Deserialize)]
+struct SubImageInfo {
+	offset_x: i32,
+	offset_y: i32,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pixel_data: Option<Vec<u32>>
+}
+
+pub fn export_project_to(path: &Path, info: &ProjectInfo, card_header: Option<&CardHeader>, pack_table: Option<&PackTable>, data_pack: &DataPack, sprite_pack: &SpritePack, menu_strings: Option<&[Text]>) -> Result<(), FormatError> {
+	fs::create_dir_all(path)?;
+
+	let project_file = ProjectFile { info: info.clone(), image_count: sprite_pack.image_sets.len() };
+	write_json(&path.join("project.json"), &project_file)?;
+
+	if let Some(card_header) = card_header {
+		write_json(&path.join("card_header.json"), card_header)?;
+	}
+
+	if let Some(pack_table) = pack_table {
+		write_json(&path.join("pack_table.json"), pack_table)?;
+	}
+
+	if let Some(menu_strings) = menu_strings {
+		write_json(&path.join("menu_strings.json"), &menu_strings)?;
+	}
+
+	let data_pack_path = path.join("data_pack");
+	fs::create_dir_all(&data_pack_path)?;
+	if let serde_json::Value::Object(parts) = serde_json::to_value(data_pack)? {
+		for (name, value) in parts {
+			write_json(&data_pack_path.join(format!("{}.json", name)), &value)?;
+		}
+	}
+
+	let images_path = path.join("images");
+	fs::create_dir_all(&images_path)?;
+	for (i, image_set) in sprite_pack.image_sets.iter().enumerate() {
+		let spritesheet = image_set.to_spritesheet()
+			.map_err(|why| FormatError::ImageSet { image_index: i, reason: why.to_string() })?;
+		spritesheet.save(images_path.join(format!("{}.png", i)))?;
+
+		let subimages = image_set.subimages.iter().map(|subimage| {
+			let is_ambiguous = subimage.pixel_data.iter().any(|pixel| {
+				find_color_index(&image_set.palettes, &get_pixel_colors(&image_set.palettes, *pixel)) != Some(*pixel as usize)
+			});
+			SubImageInfo {
+				offset_x: subimage.offset_x,
+				offset_y: subimage.offset_y,
+				pixel_data: if is_ambiguous { Some(subimage.pixel_data.clone()) } else { None }
+			}
+		}).collect();
+
+		let image_set_info = ImageSetInfo {
+			original_index: image_set.original_index,
+			width: image_set.width,
+			height: image_set.height,
+			width_in_sprites: image_set.width_in_sprites,
+			height_in_sprites: image_set.height_in_sprites,
+			is_quadrupled: image_set.is_quadrupled,
+			first_palette_index: image_set.first_palette_index,
+			palettes: image_set.palettes.clone(),
+			subimages
+		};
+		write_json(&images_path.join(format!("{}.json", i)), &image_set_info)?;
+	}
+
+	Ok(())
+}
+
+pub fn import_project_from(path: &Path, char_codes: &[CharEncoding]) -> Result<Project, FormatError> {
+	let info_path = path.join("project.json");
+	let ProjectFile { info, image_count } = read_json(&info_path)?;
+	if info.version > PROJECT_VERSION {
+		return Err(FormatError::Project {
+			path: info_path.display().to_string(),
+			reason: format!("project version {} is newer than this version of Smarty Pants supports ({})", info.version, PROJECT_VERSION)
+		});
+	}
+
+	let card_header = match info.bin_type {
+		BinType::SmaCard => Some(read_json(&path.join("card_header.json"))?),
+		BinType::Firmware => None
+	};
+
+	// projects exported before the pack table was kept don't have one, so the card gets the default
+	let pack_table_path = path.join("pack_table.json");
+	let pack_table = if pack_table_path.exists() { Some(read_json(&pack_table_path)?) } else { None };
+
+	let mut menu_strings: Option<Vec<Text>> = match info.bin_type {
+		BinType::SmaCard => None,
+		BinType::Firmware => Some(read_json(&path.join("menu_strings.json"))?)
+	};
+
+	let data_pack_path = path.join("data_pack");
+	let mut parts = serde_json::Map::new();
+	for entry in fs::read_dir(&data_pack_path)? {
+		let entry_path = entry?.path();
+		if entry_path.extension().is_some_and(|ext| ext == "json") {
+			if let Some(name) = entry_path.file_stem() {
+				let value: serde_json::Value = read_json(&entry_path)?;
+				parts.insert(name.to_string_lossy().to_string(), value);
+			}
+		}
+	}
+	let mut data_pack: DataPack = serde_json::from_value(serde_json::Value::Object(parts))
+		.map_err(|why| FormatError::Project { path: data_pack_path.display().to_string(), reason: why.to_string() })?;
+
+	update_changed_strings(&mut data_pack, menu_strings.as_deref_mut(), char_codes);
+
+	let images_path = path.join("images");
+	let mut image_sets = Vec::new();
+	for i in 0..image_count {
+		let image_set_info: ImageSetInfo = read_json(&images_path.join(format!("{}.json", i)))?;
+		let spritesheet = ImageReader::open(images_path.join(format!("{}.png", i)))?.decode()?.to_rgba8();
+		image_sets.push(read_image_set(i, image_set_info, &spritesheet)?);
+	}
+	let (_, _, colors) = save_image_sets(&image_sets)?;
+	let sprite_pack = SpritePack { image_sets, colors };
+
+	Ok(Project { info, card_header, pack_table, data_pack, sprite_pack, menu_strings })
+}
+
+fn write_json<T: Serialize + ?Sized>(path: &Path, value: &T) -> Result<(), FormatError> {
+	let mut json = serde_json::to_string_pretty(value)?;
+	json.push('\n');
+	fs::write(path, json)?;
+	Ok(())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, FormatError> {
+	let json = fs::read_to_string(path)
+		.map_err(|why| FormatError::Project { path: path.display().to_string(), reason: why.to_string() })?;
+	let value = serde_json::from_str(&json)
+		.map_err(|why| FormatError::Project { path: path.display().to_string(), reason: why.to_string() })?;
+	Ok(value)
+}
+
+// the string is what people edit, so only re-encode it when it no longer matches the data
+fn update_text(text: &mut Text, char_codes: &[CharEncoding]) {
+	if encode_string(char_codes, &text.data) != text.string {
+		let string = text.string.clone();
+		text.set_string(char_codes, &string);
+	}
+}
+
+fn update_changed_strings(data_pack: &mut DataPack, menu_strings: Option<&mut [Text]>, char_codes: &[CharEncoding]) {
+	if let Some(menu_strings) = menu_strings {
+		for menu_string in menu_strings.iter_mut() {
+			update_text(menu_string, char_codes);
+		}
+	}
+	for tamastring in data_pack.tamastrings.iter_mut() {
+		update_text(&mut tamastring.value, char_codes);
+	}
+	for item in data_pack.items.iter_mut() {
+		update_text(&mut item.name, char_codes);
+	}
+	for character in data_pack.characters.iter_mut() {
+		update_text(&mut character.name, char_codes);
+		update_text(&mut character.pronoun, char_codes);
+		update_text(&mut character.statement, char_codes);
+		update_text(&mut character.question1, char_codes);
+		update_text(&mut character.question2, char_codes);
+	}
+}
+
+fn get_pixel_colors(palettes: &[Vec<Color>], pixel: u32) -> Vec<Rgba<u8>> {
+	palettes.iter()
+		.map(|palette| palette.get(pixel as usize).map(|color| color.as_rgba()).unwrap_or(Rgba([0, 0, 0, 0])))
+		.collect()
+}
+
+// find the palette entry whose colors match the pixel in every palette row,
+// falling back to a looser match for colors that were changed by an image editor
+fn find_color_index(palettes: &[Vec<Color>], pixel_colors: &[Rgba<u8>]) -> Option<usize> {
+	let color_count = palettes.first()?.len();
+	let exact_match = (0..color_count).find(|&i| {
+		palettes.iter().zip(pixel_colors).all(|(palette, pixel_color)| {
+			palette.get(i).is_some_and(|color| color.as_rgba() == *pixel_color)
+		})
+	});
+	exact_match.or_else(|| (0..color_count).find(|&i| {
+		palettes.iter().zip(pixel_colors).all(|(palette, pixel_color)| {
+			palette.get(i).is_some_and(|color| {
+				(pixel_color[3] < 255 && color.as_rgba()[3] == 0) || *color == Color::from_rgba(pixel_color)
+			})
+		})
+	}))
+}
+
+fn read_image_set(image_index: usize, info: ImageSetInfo, spritesheet: &RgbaImage) -> Result<ImageSet, FormatError> {
+	let expected_width = info.width * info.subimages.len() as u32;
+	let expected_height = info.height * info.palettes.len() as u32;
+	if spritesheet.width() != expected_width || spritesheet.height() != expected_height {
+		return Err(FormatError::ImageSet {
+			image_index,
+			reason: format!("spritesheet is {}x{} but should be {}x{}", spritesheet.width(), spritesheet.height(), expected_width, expected_height)
+		});
+	}
+
+	let mut subimages = Vec::new();
+	for (i, subimage_info) in info.subimages.iter().enumerate() {
+		let mut pixel_data = Vec::new();
+		for y in 0..info.height {
+			for x in 0..info.width {
+				let pixel_colors: Vec<Rgba<u8>> = (0..info.palettes.len())
+					.map(|row| *spritesheet.get_pixel(x + (i as u32 * info.width), y + (row as u32 * info.height)))
+					.collect();
+				let stored_pixel = subimage_info.pixel_data.as_ref()
+					.and_then(|stored_pixels| stored_pixels.get(pixel_data.len()))
+					.filter(|stored_pixel| get_pixel_colors(&info.palettes, **stored_pixel) == pixel_colors);
+				let pixel = match stored_pixel {
+					Some(stored_pixel) => *stored_pixel,
+					None => find_color_index(&info.palettes, &pixel_colors)
+						.ok_or_else(|| FormatError::ImageSet {
+							image_index,
+							reason: format!("pixel {},{} of subimage {} uses a color that isn't in the palettes in {}.json", x, y, i, image_index)
+						})? as u32
+				};
+				pixel_data.push(pixel);
+			}
+		}
+		subimages.push(SubImage { offset_x: subimage_info.offset_x, offset_y: subimage_info.offset_y, pixel_data });
+	}
+
+	Ok(ImageSet {
+		original_index: info.original_index,
+		width: info.width,
+		height: info.height,
+		width_in_sprites: info.width_in_sprites,
+		height_in_sprites: info.height_in_sprites,
+		is_quadrupled: info.is_quadrupled,
+		first_palette_index: info.first_palette_index,
+		palettes: info.palettes,
+		subimages
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::data_view::DataView;
+	use crate::smacard::{ TamaSmaCard, read_card, save_card };
+	use crate::test_util::{ TestDir, get_test_card, get_test_image_set };
+	use crate::text::get_default_char_codes;
+
+	#[test]
+	fn card_project_round_trip() {
+		let char_codes = get_default_char_codes();
+		let mut card = get_test_card();
+		let image_sets = vec![get_test_image_set()];
+		let (_, _, colors) = save_image_sets(&image_sets).unwrap();
+		card.sprite_pack = SpritePack { image_sets, colors };
+		let data = save_card(&card, &BinSize::Auto).unwrap();
+		let card = read_card(&DataView::new(&data), &char_codes).unwrap();
+
+		let dir = TestDir::new("project");
+		let info = ProjectInfo { version: PROJECT_VERSION, bin_type: BinType::SmaCard, bin_size: BinSize::Auto, use_patch_header: None };
+		export_project_to(&dir, &info, Some(&card.header), Some(&card.pack_table), &card.data_pack, &card.sprite_pack, None).unwrap();
+		let project = import_project_from(&dir, &char_codes).unwrap();
+
+		let new_card = TamaSmaCard {
+			header: project.card_header.unwrap(),
+			pack_table: project.pack_table.unwrap(),
+			data_pack: project.data_pack,
+			sprite_pack: project.sprite_pack
+		};
+		assert!(save_card(&new_card, &project.info.bin_size).unwrap() == data);
+	}
+
+	#[test]
+	fn newer_project_version_is_rejected() {
+		let dir = TestDir::new("project-version");
+		let info = ProjectInfo { version: PROJECT_VERSION + 1, bin_type: BinType::SmaCard, bin_size: BinSize::Auto, use_patch_header: None };
+		write_json(&dir.join("project.json"), &ProjectFile { info, image_count: 0 }).unwrap();
+		let result = import_project_from(&dir, &get_default_char_codes());
+		assert!(matches!(result, Err(FormatError::Project { .. })));
+	}
+}
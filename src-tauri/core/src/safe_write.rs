@@ -0,0 +1,103 @@
+use std::fs::{ self, File };
+use std::io::Write;
+use std::path::{ Path, PathBuf };
+
+use crate::error::FormatError;
+
+pub fn get_backup_path(path: &Path, index: usize) -> PathBuf {
+	let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+	file_name.push(format!(".bak{}", index));
+	path.with_file_name(file_name)
+}
+
+// keeps the previous versions of a file as name.bak1 (newest) to name.bakN (oldest)
+pub fn rotate_backups(path: &Path, backup_count: usize) -> Result<(), FormatError> {
+	if backup_count == 0 || !path.is_file() {
+		return Ok(());
+	}
+
+	let oldest_backup_path = get_backup_path(path, backup_count);
+	if oldest_backup_path.exists() {
+		fs::remove_file(&oldest_backup_path)?;
+	}
+	for i in (1..backup_count).rev() {
+		let backup_path = get_backup_path(path, i);
+		if backup_path.exists() {
+			fs::rename(&backup_path, get_backup_path(path, i + 1))?;
+		}
+	}
+
+	// copy rather than rename, so there is never a moment where the file is missing
+	fs::copy(path, get_backup_path(path, 1))?;
+
+	Ok(())
+}
+
+// writes to a temp file next to the target, flushes it to disk, then renames it over the target,
+// so a crash or a full disk leaves either the old file or the new one but never half of each
+pub fn write_file(path: &Path, data: &[u8], backup_count: usize) -> Result<(), FormatError> {
+	let mut temp_file_name = path.file_name()
+		.ok_or_else(|| FormatError::Io { reason: format!("{} isn't a valid file name", path.display()) })?.to_os_string();
+	temp_file_name.push(format!(".tmp{}", std::process::id()));
+	let temp_path = path.with_file_name(temp_file_name);
+
+	let result = write_temp_file(&temp_path, data)
+		.and_then(|_| rotate_backups(path, backup_count))
+		.and_then(|_| fs::rename(&temp_path, path).map_err(FormatError::from));
+
+	if result.is_err() {
+		let _ = fs::remove_file(&temp_path);
+	} else {
+		sync_parent_dir(path);
+	}
+
+	result
+}
+
+fn write_temp_file(temp_path: &Path, data: &[u8]) -> Result<(), FormatError> {
+	let mut file = File::create(temp_path)?;
+	file.write_all(data)?;
+	file.sync_all()?;
+	Ok(())
+}
+
+// makes the rename itself durable; not every platform can open a directory, so this is best effort
+fn sync_parent_dir(path: &Path) {
+	if let Some(parent) = path.parent() {
+		let parent = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+		if let Ok(dir) = File::open(parent) {
+			let _ = dir.sync_all();
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_util::TestDir;
+
+	#[test]
+	fn backups_rotate_newest_first() {
+		let dir = TestDir::new("backup");
+		let path = dir.join("card.bin");
+		for version in 1..=4_u8 {
+			write_file(&path, &[version], 2).unwrap();
+		}
+
+		let contents = [path.clone(), get_backup_path(&path, 1), get_backup_path(&path, 2), get_backup_path(&path, 3)]
+			.map(|path| fs::read(path).ok());
+		assert_eq!(contents, [Some(vec![4]), Some(vec![3]), Some(vec![2]), None]);
+		assert_eq!(fs::read_dir(&*dir).unwrap().count(), 3);
+	}
+
+	#[test]
+	fn no_backups_when_count_is_zero() {
+		let dir = TestDir::new("no-backup");
+		let path = dir.join("card.bin");
+		write_file(&path, &[1], 0).unwrap();
+		write_file(&path, &[2], 0).unwrap();
+
+		assert_eq!(fs::read(&path).unwrap(), [2]);
+		assert!(!get_backup_path(&path, 1).exists());
+	}
+}
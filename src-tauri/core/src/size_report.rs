@@ -0,0 +1,126 @@
+use serde::Serialize;
+
+use crate::{ BinSize, CARD_SIZES, FormatError, get_smallest_card_size };
+use crate::data_view::DataView;
+use crate::data_pack::{ TABLE_NAMES, get_table_offsets };
+use crate::sprite_pack::SpritePack;
+use crate::smacard::{ TamaSmaCard, save_card_packs };
+
+#[derive(Clone, Serialize)]
+pub struct SizeReport {
+	pub sections: Vec<SizeSection>,
+	pub used_size: usize,
+	pub headroom: Vec<SizeHeadroom>,
+	pub smallest_size: Option<BinSize>
+}
+
+#[derive(Clone, Serialize)]
+pub struct SizeSection {
+	pub group: String,
+	pub name: String,
+	pub size: usize
+}
+
+// headroom is negative when the card doesn't fit
+#[derive(Clone, Serialize)]
+pub struct SizeHeadroom {
+	pub bin_size: BinSize,
+	pub capacity: usize,
+	pub headroom: i64
+}
+
+// how much of a fixed-size region is used, for catching overruns while editing rather than on save
+#[derive(Clone, Serialize)]
+pub struct SizeBudget {
+	pub region: String,
+	pub size: usize,
+	pub capacity: usize
+}
+
+impl SizeBudget {
+	pub fn new(region: &str, size: usize, capacity: usize) -> SizeBudget {
+		SizeBudget { region: region.to_string(), size, capacity }
+	}
+
+	pub fn check(&self) -> Result<(), FormatError> {
+		if self.size > self.capacity {
+			Err(FormatError::SizeOverflow { region: self.region.clone(), size: self.size, limit: self.capacity })
+		} else {
+			Ok(())
+		}
+	}
+}
+
+// an Auto card is measured against the smallest size it fits in, or the largest if it fits in none
+pub fn get_card_budgets(card: &TamaSmaCard, bin_size: &BinSize) -> Result<Vec<SizeBudget>, FormatError> {
+	let used_size = 0x1000 + save_card_packs(card)?.len() + 16;
+	let bin_size = match bin_size {
+		BinSize::Auto => get_smallest_card_size(used_size).unwrap_or_else(|| CARD_SIZES[CARD_SIZES.len() - 1].clone()),
+		_ => bin_size.clone()
+	};
+	let capacity = bin_size.card_capacity()
+		.ok_or_else(|| FormatError::Other { reason: format!("{:?} is not a valid TamaSma card size", bin_size) })?;
+	Ok(vec![SizeBudget::new("Card data", used_size, capacity)])
+}
+
+pub fn get_card_size_report(card: &TamaSmaCard) -> Result<SizeReport, FormatError> {
+	let mut sections = Vec::new();
+	let mut add_section = |group: &str, name: &str, size: usize| {
+		sections.push(SizeSection { group: group.to_string(), name: name.to_string(), size });
+	};
+
+	add_section("Card", "header", 0x1000);
+
+	// every size is read back from a single save, so the sections add up to exactly what gets written
+	let packs_data = save_card_packs(card)?;
+	let packs = DataView::new(&packs_data);
+	let pack_count = packs.get_u16(2)? as usize;
+	add_section("Card", "pack table", 4 + pack_count * 16);
+
+	let (_, table_sizes) = get_table_offsets(&get_pack(&packs, 0)?)?;
+	add_section("Data pack", "table offsets", 80);
+	for (name, size) in TABLE_NAMES.iter().zip(table_sizes) {
+		add_section("Data pack", name, size);
+	}
+
+	add_section("Sprite pack", "section offsets", 16);
+	for (section, size) in SpritePack::get_section_sizes(&get_pack(&packs, 1)?)? {
+		add_section("Sprite pack", &section.to_string(), size);
+	}
+
+	for (i, entry) in card.pack_table.entries.iter().enumerate().skip(2) {
+		if !entry.data.is_empty() {
+			add_section("Other packs", &format!("pack {}", i), entry.data.len().max(entry.padded_size as usize));
+		}
+	}
+
+	let packs_size = packs_data.len();
+	let accounted_size: usize = sections.iter().map(|section| section.size).sum();
+	sections.push(SizeSection {
+		group: "Card".to_string(),
+		name: "alignment padding".to_string(),
+		size: (0x1000 + packs_size).saturating_sub(accounted_size)
+	});
+	sections.push(SizeSection { group: "Card".to_string(), name: "signature".to_string(), size: 16 });
+
+	let used_size = 0x1000 + packs_size + 16;
+	let headroom = CARD_SIZES.iter()
+		.filter_map(|bin_size| bin_size.card_capacity().map(|capacity| SizeHeadroom {
+			bin_size: bin_size.clone(),
+			capacity,
+			headroom: capacity as i64 - used_size as i64
+		}))
+		.collect();
+
+	Ok(SizeReport {
+		sections,
+		used_size,
+		headroom,
+		smallest_size: get_smallest_card_size(used_size)
+	})
+}
+
+fn get_pack(packs: &DataView, pack_index: usize) -> Result<DataView, FormatError> {
+	let entry_start = 4 + pack_index * 16;
+	packs.chunk(packs.get_u32(entry_start + 4)? as usize, packs.get_u32(entry_start + 12)? as usize)
+}
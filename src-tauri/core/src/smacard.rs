@@ -0,0 +1,573 @@
+use std::num::Wrapping;
+
+use serde::{ Serialize, Deserialize };
+
+use md5::{ Md5, Digest };
+
+use crate::{ BinSize, get_smallest_card_size, CARD_SIZES };
+use crate::data_view::{ DataView, bytes_to_words };
+use crate::data_pack::{ DataPack, get_data_pack, save_data_pack };
+use crate::sprite_pack::SpritePack;
+use crate::text::CharEncoding;
+use crate::error::{ FormatError, DataContext };
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct CardHeader {
+	pub sector_count: u16,
+	pub checksum: u16,
+	pub device_ids: [u32; 3],
+	pub vendor_id: String,
+	pub product_id: String,
+	pub card_type: CardType,
+	pub card_id: u16,
+	pub year: u16,
+	pub month: u16,
+	pub day: u16,
+	pub revision: u16,
+	pub md5: [u8; 16]
+}
+
+// the last 16 bytes of every card
+pub const CARD_SIGNATURE: [u8; 16] = [50, 132, 171, 86, 34, 17, 220, 254, 142, 107, 85, 255, 181, 16, 127, 51];
+
+#[derive(Clone, Serialize)]
+pub struct HeaderIssue {
+	pub field: String,
+	pub offset: usize,
+	pub expected: String,
+	pub found: String
+}
+
+impl HeaderIssue {
+	pub fn describe(&self) -> String {
+		format!("{} at 0x{:X}: expected {}, found {}", self.field, self.offset, self.expected, self.found)
+	}
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CardType {
+	TamaSmaCard,
+	PromoTreasure,
+	PromoItem,
+	Unknown
+}
+
+impl CardHeader {
+	// the checksum, MD5 and sector count are filled in when the card is saved
+	pub fn new(card_id: u8, card_type: CardType, vendor_id: &str, product_id: &str) -> Result<CardHeader, FormatError> {
+		if card_id > 0x7f {
+			return Err(FormatError::CardHeader { offset: 50, reason: format!("card ID {} is too large (must be 0 to 127)", card_id) });
+		}
+		Ok(CardHeader {
+			sector_count: 31,
+			checksum: 0,
+			device_ids: [0, 0, 0],
+			vendor_id: check_header_id("vendor ID", vendor_id)?,
+			product_id: check_header_id("product ID", product_id)?,
+			card_type,
+			card_id: card_id as u16,
+			year: 0,
+			month: 0,
+			day: 0,
+			revision: 0,
+			md5: [0; 16]
+		})
+	}
+
+	pub fn set_ids_and_type(&mut self, vendor_id: &str, product_id: &str, card_type: CardType) -> Result<(), FormatError> {
+		if matches!(card_type, CardType::Unknown) {
+			return Err(FormatError::CardHeader { offset: 48, reason: "card type must be TamaSmaCard, PromoTreasure or PromoItem".to_string() });
+		}
+		let vendor_id = check_header_id("vendor ID", vendor_id)?;
+		let product_id = check_header_id("product ID", product_id)?;
+		self.vendor_id = vendor_id;
+		self.product_id = product_id;
+		self.card_type = card_type;
+		Ok(())
+	}
+}
+
+// vendor and product IDs are stored as exactly 16 ASCII bytes; real cards pad them with spaces,
+// so padding is left to the caller rather than guessed here
+pub fn check_header_id(field: &str, id: &str) -> Result<String, FormatError> {
+	if let Some(i) = id.chars().position(|c| !c.is_ascii()) {
+		return Err(FormatError::HeaderField { field: field.to_string(), reason: format!("character {} isn't ASCII", i) });
+	}
+	if id.len() != 16 {
+		return Err(FormatError::HeaderField { field: field.to_string(), reason: format!("must be exactly 16 bytes, but is {}", id.len()) });
+	}
+	Ok(id.to_string())
+}
+
+// the table at 0x1000 that lists each pack's offset and size; only the data and sprite packs (0 and 1)
+// are parsed, so the rest are kept as raw bytes along with every field we don't understand
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PackTable {
+	pub magic: u16,
+	pub entries: Vec<PackEntry>
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PackEntry {
+	pub unknown: u32,
+	pub offset: u32,
+	pub padded_size: u32,
+	pub size: u32,
+	#[serde(default)]
+	pub data: Vec<u8>
+}
+
+impl Default for PackTable {
+	fn default() -> PackTable {
+		let empty_entry = PackEntry { unknown: 0, offset: 0, padded_size: 0, size: 0, data: Vec::new() };
+		PackTable { magic: 0x3232, entries: vec![empty_entry; 4] }
+	}
+}
+
+#[derive(Clone)]
+pub struct TamaSmaCard {
+	pub header: CardHeader,
+	pub pack_table: PackTable,
+	pub data_pack: DataPack,
+	pub sprite_pack: SpritePack
+}
+
+pub fn new_card(header: CardHeader) -> TamaSmaCard {
+	let data_pack = DataPack::new(header.card_id);
+	TamaSmaCard { header, pack_table: PackTable::default(), data_pack, sprite_pack: SpritePack::default() }
+}
+
+pub fn read_card(data: &DataView, char_codes: &[CharEncoding]) -> Result<TamaSmaCard, FormatError> {
+	let header = read_card_header(&data.chunk(0, data.len().min(0x1000))?.with_context(DataContext::CardHeader))?;
+	if data.len() < 0x1000 {
+		return Err(FormatError::PackTable { offset: 0x1000, pack_index: None, reason: "file ends before the pack table".to_string() });
+	}
+	let pack_table = data.chunk(0x1000, data.len() - 0x1000)?.with_context(DataContext::PackTable);
+	let (pack_table, data_pack, sprite_pack) = read_card_packs(&pack_table, char_codes)?;
+	Ok(TamaSmaCard { header, pack_table, data_pack, sprite_pack })
+}
+
+pub fn read_card_header(data: &DataView) -> Result<CardHeader, FormatError> {
+	if data.len() < 80 {
+		return Err(data.error(0, None, "file is too short for a card header"));
+	}
+
+	let sector_count = data.get_u16(0)?;
+
+	let checksum = data.get_u16(2)?;
+
+	let device_ids = [
+		data.get_u32(4)?,
+		data.get_u32(8)?,
+		data.get_u32(12)?
+	];
+
+	let mut vendor_id = String::new();
+	for i in 0..16 {
+		vendor_id.push(data.get_u8(i+16)?.into());
+	}
+
+	let mut product_id = String::new();
+	for i in 0..16 {
+		product_id.push(data.get_u8(i+32)?.into());
+	}
+
+	let card_type = match data.get_u16(48)? {
+		0 => CardType::TamaSmaCard,
+		1 => CardType::PromoTreasure,
+		2 => CardType::PromoItem,
+		_ => CardType::Unknown
+	};
+
+	let card_id = data.get_u16(50)?;
+
+	let year = data.get_u16(54)?;
+	let month = data.get_u16(56)?;
+	let day = data.get_u16(58)?;
+	let revision = data.get_u16(60)?;
+
+	let mut md5 = [0; 16];
+	for (i, byte) in md5.iter_mut().enumerate() {
+		*byte = data.get_u8(i+64)?;
+	}
+
+	Ok(CardHeader {
+		sector_count,
+		checksum,
+		device_ids,
+		vendor_id,
+		product_id,
+		card_type,
+		card_id,
+		year,
+		month,
+		day,
+		revision,
+		md5
+	})
+}
+
+pub fn read_card_packs(data: &DataView, char_codes: &[CharEncoding]) -> Result<(PackTable, DataPack, SpritePack), FormatError> {
+	if data.len() < 68 {
+		return Err(data.error(0, None, "too short for pack info"));
+	}
+
+	let magic = data.get_u16(0)?;
+	let pack_count = data.get_u16(2)? as usize;
+	if pack_count < 2 {
+		return Err(data.error(2, None, &format!("expected at least 2 packs, found {}", pack_count)));
+	}
+
+	let mut data_pack_opt: Option<DataPack> = None;
+	let mut sprite_pack_opt: Option<SpritePack> = None;
+	let mut entries = Vec::new();
+
+	for i in 0..pack_count {
+		let mut entry = PackEntry {
+			unknown: data.get_u32(i*16+4)?,
+			offset: data.get_u32(i*16+8)?,
+			padded_size: data.get_u32(i*16+12)?,
+			size: data.get_u32(i*16+16)?,
+			data: Vec::new()
+		};
+		let pack_offset = entry.offset as usize;
+		let pack_size = entry.size as usize;
+
+		if pack_offset > 0 && pack_size > 0 {
+			let pack_data = data.chunk(pack_offset, pack_size)
+				.map_err(|_| data.error(i*16+8, Some(i), &format!("{} byte(s) at 0x{:X} run past the end of the file", pack_size, data.offset + pack_offset)))?;
+			match i {
+				0 => data_pack_opt = Some(get_data_pack(&pack_data.with_context(DataContext::DataPack), char_codes)?),
+				1 => sprite_pack_opt = Some(SpritePack::from_data(&pack_data.with_context(DataContext::SpritePack))?),
+				_ => entry.data = pack_data.get_bytes(0, pack_size)?.to_vec()
+			}
+		}
+
+		entries.push(entry);
+	}
+
+	if let Some(data_pack) = data_pack_opt {
+		if let Some(sprite_pack) = sprite_pack_opt {
+			Ok((PackTable { magic, entries }, data_pack, sprite_pack))
+		} else {
+			Err(data.error(24, Some(1), "sprite pack not found"))
+		}
+	} else {
+		Err(data.error(8, Some(0), "data pack not found"))
+	}
+}
+
+pub fn save_card(card: &TamaSmaCard, bin_size: &BinSize) -> Result<Vec<u8>, FormatError> {
+	let pack_data = save_card_packs(card)?;
+
+	// the header, the packs and the signature
+	let used_size = 0x1000 + pack_data.len() + 16;
+	let largest_capacity = CARD_SIZES.iter().filter_map(|size| size.card_capacity()).max().unwrap_or(0);
+	let bin_size = match bin_size {
+		BinSize::Auto => get_smallest_card_size(used_size)
+			.ok_or(FormatError::SizeOverflow { region: "Card data".to_string(), size: used_size, limit: largest_capacity })?,
+		_ => bin_size.clone()
+	};
+	let card_size = bin_size.card_capacity()
+		.ok_or_else(|| FormatError::CardHeader { offset: 0, reason: format!("{:?} is not a valid TamaSma card size", bin_size) })?;
+	if card_size < used_size {
+		return Err(FormatError::SizeOverflow { region: "Card data".to_string(), size: used_size, limit: card_size });
+	}
+
+	let mut header = card.header.clone();
+	header.sector_count = (card_size / 0x1000 - 1) as u16;
+	let header_data = save_card_header(&header)?;
+
+	let mut data = [header_data, pack_data].concat();
+	data.resize(card_size - 16, 0);
+	data.extend_from_slice(&CARD_SIGNATURE);
+
+	write_header_checksums(&mut data);
+
+	Ok(data)
+}
+
+// the pack table and every pack, as they appear from 0x1000 on
+pub fn save_card_packs(card: &TamaSmaCard) -> Result<Vec<u8>, FormatError> {
+	let pack_count = card.pack_table.entries.len().max(2);
+	let data_pack_offset = 4 + pack_count * 16;
+	let mut data_pack_data = save_data_pack(&card.data_pack, data_pack_offset)?;
+	data_pack_data.extend_from_slice(&[0, 0]);
+
+	let sprite_pack_data = card.sprite_pack.as_bytes()?;
+
+	let mut sprite_pack_offset = data_pack_offset + data_pack_data.len();
+	while sprite_pack_offset % 32 != 0 {
+		sprite_pack_offset += 1;
+	}
+	let padded_data_pack_size = sprite_pack_offset - data_pack_offset;
+	data_pack_data.resize(padded_data_pack_size, 0);
+
+	let mut pack_summary = card.pack_table.magic.to_le_bytes().to_vec();
+	pack_summary.extend_from_slice(&(pack_count as u16).to_le_bytes());
+
+	// extra packs go after the sprite pack with their sizes unchanged, at their old offsets if there's still room
+	let mut extra_pack_data: Vec<u8> = Vec::new();
+	let extra_pack_offset = sprite_pack_offset + sprite_pack_data.len();
+
+	for i in 0..pack_count {
+		let entry = card.pack_table.entries.get(i);
+		let unknown = entry.map(|entry| entry.unknown).unwrap_or(0);
+		let (offset, padded_size, size) = match (i, entry) {
+			(0, _) => (data_pack_offset as u32, data_pack_data.len() as u32, data_pack_data.len() as u32 - 2),
+			(1, _) => (sprite_pack_offset as u32, sprite_pack_data.len() as u32, sprite_pack_data.len() as u32),
+			(_, Some(entry)) if !entry.data.is_empty() => {
+				while (extra_pack_offset + extra_pack_data.len()) % 32 != 0 {
+					extra_pack_data.push(0);
+				}
+				if entry.offset as usize > extra_pack_offset + extra_pack_data.len() {
+					extra_pack_data.resize(entry.offset as usize - extra_pack_offset, 0);
+				}
+				let offset = (extra_pack_offset + extra_pack_data.len()) as u32;
+				extra_pack_data.extend_from_slice(&entry.data);
+				extra_pack_data.resize(extra_pack_data.len() + (entry.padded_size as usize).saturating_sub(entry.data.len()), 0);
+				(offset, entry.padded_size, entry.size)
+			},
+			(_, Some(entry)) => (entry.offset, entry.padded_size, entry.size),
+			(_, None) => (0, 0, 0)
+		};
+		pack_summary.extend_from_slice(&unknown.to_le_bytes());
+		pack_summary.extend_from_slice(&offset.to_le_bytes());
+		pack_summary.extend_from_slice(&padded_size.to_le_bytes());
+		pack_summary.extend_from_slice(&size.to_le_bytes());
+	}
+
+	Ok([pack_summary, data_pack_data, sprite_pack_data, extra_pack_data].concat())
+}
+
+// the checksum covers everything from byte 1000 on, and the MD5 covers the first 64 bytes (including the checksum)
+fn write_header_checksums(data: &mut [u8]) {
+	let checksum = calc_checksum(&data[1000..]);
+	data[2..4].copy_from_slice(&checksum.to_le_bytes());
+	let md5 = calc_md5(data);
+	data[64..80].copy_from_slice(&md5);
+}
+
+pub fn calc_md5(data: &[u8]) -> [u8; 16] {
+	let mut hasher = Md5::new();
+	hasher.update(&data[0..64]);
+	hasher.finalize().into()
+}
+
+fn get_sector_count(file_size: usize) -> usize {
+	(file_size + 0xfff) / 0x1000 - 1
+}
+
+// compares the stored sector count, checksum, MD5 and signature with the values the rest of the file implies;
+// dumps saved by tools that don't update these fields will show up here as well as corrupt ones
+pub fn check_card_header(data: &[u8]) -> Vec<HeaderIssue> {
+	let mut issues = Vec::new();
+	if data.len() < 0x1000 {
+		issues.push(HeaderIssue { field: "File size".to_string(), offset: 0, expected: "at least 4096 bytes".to_string(), found: format!("{} bytes", data.len()) });
+		return issues;
+	}
+
+	if data.len() % 0x1000 != 0 {
+		issues.push(HeaderIssue {
+			field: "File size".to_string(),
+			offset: 0,
+			expected: "a whole number of 4 KB sectors".to_string(),
+			found: format!("{} bytes", data.len())
+		});
+	}
+
+	let sector_count = u16::from_le_bytes([data[0], data[1]]) as usize;
+	let expected_sector_count = get_sector_count(data.len());
+	if sector_count != expected_sector_count {
+		issues.push(HeaderIssue {
+			field: "Sector count".to_string(),
+			offset: 0,
+			expected: format!("{} for a {} byte file", expected_sector_count, data.len()),
+			found: sector_count.to_string()
+		});
+	}
+
+	let checksum = u16::from_le_bytes([data[2], data[3]]);
+	let expected_checksum = calc_checksum(&data[1000..]);
+	if checksum != expected_checksum {
+		issues.push(HeaderIssue {
+			field: "Checksum".to_string(),
+			offset: 2,
+			expected: format!("0x{:04X}", expected_checksum),
+			found: format!("0x{:04X}", checksum)
+		});
+	}
+
+	let md5 = &data[64..80];
+	let expected_md5 = calc_md5(data);
+	if md5 != expected_md5 {
+		issues.push(HeaderIssue {
+			field: "MD5".to_string(),
+			offset: 64,
+			expected: get_hex_string(&expected_md5),
+			found: get_hex_string(md5)
+		});
+	}
+
+	let signature_offset = data.len() - 16;
+	let signature = &data[signature_offset..];
+	if signature != CARD_SIGNATURE {
+		issues.push(HeaderIssue {
+			field: "Signature".to_string(),
+			offset: signature_offset,
+			expected: get_hex_string(&CARD_SIGNATURE),
+			found: get_hex_string(signature)
+		});
+	}
+
+	issues
+}
+
+// rewrites the sector count, signature, checksum and MD5 without touching anything else in the file
+pub fn repair_card_header(data: &[u8]) -> Result<Vec<u8>, FormatError> {
+	if data.len() < 0x1000 || data.len() % 0x1000 != 0 {
+		return Err(FormatError::CardHeader { offset: 0, reason: format!("file is {} bytes, which isn't a whole number of 4 KB sectors; repack the card instead", data.len()) });
+	}
+	let sector_count = u16::try_from(get_sector_count(data.len()))
+		.map_err(|_| FormatError::CardHeader { offset: 0, reason: format!("file is too large for a card ({} bytes)", data.len()) })?;
+
+	let mut new_data = data.to_vec();
+	new_data[0..2].copy_from_slice(&sector_count.to_le_bytes());
+	let signature_offset = new_data.len() - 16;
+	new_data[signature_offset..].copy_from_slice(&CARD_SIGNATURE);
+	write_header_checksums(&mut new_data);
+
+	Ok(new_data)
+}
+
+pub(crate) fn get_hex_string(bytes: &[u8]) -> String {
+	bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn save_card_header(header: &CardHeader) -> Result<Vec<u8>, FormatError> {
+	let mut data: Vec<u8> = Vec::new();
+
+	data.extend_from_slice(&header.sector_count.to_le_bytes());
+	data.extend_from_slice(&header.checksum.to_le_bytes());
+	data.extend_from_slice(&header.device_ids[0].to_le_bytes());
+	data.extend_from_slice(&header.device_ids[1].to_le_bytes());
+	data.extend_from_slice(&header.device_ids[2].to_le_bytes());
+	data.extend_from_slice(&get_header_id_bytes("vendor ID", 16, &header.vendor_id)?);
+	data.extend_from_slice(&get_header_id_bytes("product ID", 32, &header.product_id)?);
+
+	let card_type = match header.card_type {
+		CardType::TamaSmaCard => 0_u16,
+		CardType::PromoTreasure => 1_u16,
+		CardType::PromoItem => 2_u16,
+		_ => { return Err(FormatError::CardHeader { offset: 48, reason: "unknown card type can't be saved".to_string() }); }
+	};
+	data.extend_from_slice(&card_type.to_le_bytes());
+
+	data.extend_from_slice(&header.card_id.to_le_bytes());
+
+	data.extend_from_slice(&[0, 0]);
+
+	data.extend_from_slice(&header.year.to_le_bytes());
+	data.extend_from_slice(&header.month.to_le_bytes());
+	data.extend_from_slice(&header.day.to_le_bytes());
+	data.extend_from_slice(&header.revision.to_le_bytes());
+
+	data.extend_from_slice(&[0, 0]);
+
+	data.extend_from_slice(&header.md5);
+
+	let padding_len = 0x1000 - data.len();
+	let padding = vec![0_u8; padding_len];
+	data.extend_from_slice(&padding);
+
+	Ok(data)
+}
+
+// read_card_header turns each byte into one char, so write them back the same way rather than as UTF-8
+fn get_header_id_bytes(name: &str, offset: usize, id: &str) -> Result<Vec<u8>, FormatError> {
+	let bytes = id.chars()
+		.enumerate()
+		.map(|(i, c)| u8::try_from(c).map_err(|_| FormatError::CardHeader { offset: offset + i, reason: format!("{} has a character that doesn't fit in one byte", name) }))
+		.collect::<Result<Vec<u8>, FormatError>>()?;
+	if bytes.len() != 16 {
+		return Err(FormatError::CardHeader { offset, reason: format!("{} must be exactly 16 bytes, but is {}", name, bytes.len()) });
+	}
+	Ok(bytes)
+}
+
+fn calc_checksum(data: &[u8]) -> u16 {
+	let words = bytes_to_words(data);
+	let mut checksum = Wrapping(0_u16);
+	for word in words {
+		checksum += Wrapping(word);
+	}
+	checksum.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_util::{ get_test_card, get_saved_test_card };
+	use crate::text::get_default_char_codes;
+
+	fn resave(data: &[u8]) -> Vec<u8> {
+		let card = read_card(&DataView::new(data), &get_default_char_codes()).unwrap();
+		save_card(&card, &BinSize::Auto).unwrap()
+	}
+
+	#[test]
+	fn card_round_trip() {
+		let data = get_saved_test_card();
+		assert!(resave(&data) == data);
+	}
+
+	// packs after the sprite pack aren't parsed, but they have to come back byte for byte
+	#[test]
+	fn card_round_trip_with_extra_packs() {
+		let mut card = get_test_card();
+		card.pack_table.entries[2] = PackEntry { unknown: 7, offset: 0x4000, padded_size: 0x40, size: 0x30, data: (0..0x30).collect() };
+		card.pack_table.entries[3] = PackEntry { unknown: 9, offset: 0, padded_size: 0x20, size: 0x11, data: vec![0xAB; 0x11] };
+		let data = save_card(&card, &BinSize::Auto).unwrap();
+
+		let loaded_card = read_card(&DataView::new(&data), &get_default_char_codes()).unwrap();
+		assert_eq!(loaded_card.pack_table.entries[2].data, card.pack_table.entries[2].data);
+		assert_eq!(loaded_card.pack_table.entries[2].offset, 0x4000);
+		assert_eq!(loaded_card.pack_table.entries[3].data, card.pack_table.entries[3].data);
+		assert!(resave(&data) == data);
+	}
+
+	#[test]
+	fn saved_card_header_is_valid() {
+		assert!(check_card_header(&get_saved_test_card()).is_empty());
+	}
+
+	#[test]
+	fn header_repair() {
+		let mut data = get_saved_test_card();
+		data[0] = 0;
+		data[0x2000] ^= 0xFF;
+		let last = data.len() - 1;
+		data[last] = 0;
+
+		let fields: Vec<String> = check_card_header(&data).into_iter().map(|issue| issue.field).collect();
+		assert_eq!(fields, ["Sector count", "Checksum", "MD5", "Signature"]);
+
+		let repaired_data = repair_card_header(&data).unwrap();
+		assert!(check_card_header(&repaired_data).is_empty());
+		assert!(repaired_data[0x1000..last] == data[0x1000..last]);
+	}
+
+	#[test]
+	fn header_repair_needs_whole_sectors() {
+		let mut data = get_saved_test_card();
+		data.push(0);
+		assert!(repair_card_header(&data).is_err());
+	}
+
+	#[test]
+	fn header_ids_must_be_16_ascii_bytes() {
+		assert!(check_header_id("vendor ID", "BANDAI          ").is_ok());
+		assert!(check_header_id("vendor ID", "BANDAI").is_err());
+		assert!(check_header_id("vendor ID", "BANDAÏ          ").is_err());
+	}
+}
@@ -0,0 +1,117 @@
+use image::RgbaImage;
+
+use crate::data_view::DataView;
+use crate::error::{ FormatError, DataContext, SpritePackSection };
+
+pub mod image_def;
+pub mod palette;
+pub mod sprite;
+
+use image_def::{ ImageSet, get_image_sets, save_image_sets };
+use palette::{ Color, get_palettes, save_palettes };
+use sprite::{ get_sprites, save_sprites, save_pixel_data };
+
+#[derive(Clone, Default)]
+pub struct SpritePack {
+	pub image_sets: Vec<ImageSet>,
+	pub colors: Vec<Color>
+}
+
+impl SpritePack {
+	pub fn from_data(data: &DataView) -> Result<Self, FormatError> {
+		let image_defs_offset = data.get_u32(0)? as usize;
+		let sprite_defs_offset = data.get_u32(4)? as usize;
+		let palettes_offset = data.get_u32(8)? as usize;
+		let pixel_data_offset = data.get_u32(12)? as usize;
+
+		let get_section = |section: SpritePackSection, start: usize, end: usize| -> Result<DataView, FormatError> {
+			let context = DataContext::SpritePackSection(section);
+			if end < start {
+				return Err(FormatError::at(context, data.offset + start, None, &format!("section ends at 0x{:X} before it starts", data.offset + end)));
+			}
+			Ok(data.chunk(start, end - start)?.with_context(context))
+		};
+
+		let colors = get_palettes(
+			&get_section(SpritePackSection::Palettes, palettes_offset, pixel_data_offset)?
+		)?;
+
+		let sprites = get_sprites(
+			&get_section(SpritePackSection::SpriteDefs, sprite_defs_offset, palettes_offset)?,
+			&get_section(SpritePackSection::PixelData, pixel_data_offset, data.len())?
+		)?;
+
+		let image_sets = get_image_sets(
+			&get_section(SpritePackSection::ImageDefs, image_defs_offset, sprite_defs_offset)?,
+			&sprites,
+			&colors
+		)?;
+
+		let sprite_pack = Self { image_sets, colors };
+
+		Ok(sprite_pack)
+	}
+
+	pub fn as_bytes(&self) -> Result<Vec<u8>, FormatError> {
+		let (image_def_data, sprite_data, mut palette_data, pixel_data) = self.save_sections()?;
+
+		let image_defs_offset = 16;
+		let sprites_offset = image_defs_offset + image_def_data.len();
+		let palettes_offset = sprites_offset + sprite_data.len();
+
+		let mut pixel_data_offset = palettes_offset + palette_data.len();
+		while pixel_data_offset % 16 != 0 {
+			pixel_data_offset += 1;
+		}
+		let padded_palette_size = pixel_data_offset - palettes_offset;
+		palette_data.resize(padded_palette_size, 0);
+
+		let mut data: Vec<u8> = Vec::new();
+
+		data.extend_from_slice(&u32::to_le_bytes(image_defs_offset as u32));
+		data.extend_from_slice(&u32::to_le_bytes(sprites_offset as u32));
+		data.extend_from_slice(&u32::to_le_bytes(palettes_offset as u32));
+		data.extend_from_slice(&u32::to_le_bytes(pixel_data_offset as u32));
+
+		data.extend_from_slice(&image_def_data);
+		data.extend_from_slice(&sprite_data);
+		data.extend_from_slice(&palette_data);
+		data.extend_from_slice(&pixel_data);
+
+		Ok(data)
+	}
+
+	// sizes of the image defs, sprite defs, palettes (with their padding) and pixel data in a saved sprite pack
+	pub fn get_section_sizes(data: &DataView) -> Result<Vec<(SpritePackSection, usize)>, FormatError> {
+		let offsets = [data.get_u32(0)? as usize, data.get_u32(4)? as usize, data.get_u32(8)? as usize, data.get_u32(12)? as usize, data.len()];
+		if offsets.windows(2).any(|pair| pair[1] < pair[0]) {
+			return Err(data.error(0, None, "section offsets are out of order"));
+		}
+		Ok(vec![
+			(SpritePackSection::ImageDefs, offsets[1] - offsets[0]),
+			(SpritePackSection::SpriteDefs, offsets[2] - offsets[1]),
+			(SpritePackSection::Palettes, offsets[3] - offsets[2]),
+			(SpritePackSection::PixelData, offsets[4] - offsets[3])
+		])
+	}
+
+	fn save_sections(&self) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>, Vec<u8>), FormatError> {
+		let (image_def_data, sprites, colors) = save_image_sets(&self.image_sets)?;
+		let palette_data = save_palettes(&colors)?;
+		let (pixel_data, sprite_defs) = save_pixel_data(&sprites);
+		let sprite_data = save_sprites(&sprite_defs)?;
+		Ok((image_def_data, sprite_data, palette_data, pixel_data))
+	}
+
+	pub fn get_image_data(&self) -> Result<Vec<Vec<RgbaImage>>, FormatError> {
+		let mut images = Vec::new();
+		for image_set in &self.image_sets {
+			let mut subimages = Vec::new();
+			for i in 0..image_set.palettes.len() {
+				subimages = [subimages, image_set.to_images(i)?].concat();
+			}
+			images.push(subimages);
+		}
+		Ok(images)
+	}
+}
@@ -1,8 +1,10 @@
-use std::error::Error;
 use std::cmp::Ordering;
 use image::Rgba;
 use serde::{ Serialize, Deserialize };
 
+use crate::data_view::DataView;
+use crate::error::FormatError;
+
 #[derive(Debug, Default, Clone, Copy, Eq, Serialize, Deserialize)]
 pub struct Color(u8, u8, u8, u8);
 
@@ -86,16 +88,16 @@ impl PartialOrd for Color {
 	}
 }
 
-pub fn get_palettes(data: &[u8]) -> Result<Vec<Color>, Box<dyn Error>> {
+pub fn get_palettes(data: &DataView) -> Result<Vec<Color>, FormatError> {
 	let mut colors = Vec::new();
 	for i in 0..data.len()/2 {
-		let color = Color::from_bytes([data[i*2], data[i*2+1]]);
+		let color = Color::from_word(data.get_u16(i*2)?);
 		colors.push(color);
 	}
 	Ok(colors)
 }
 
-pub fn save_palettes(colors: &[Color]) -> Result<Vec<u8>, Box<dyn Error>> {
+pub fn save_palettes(colors: &[Color]) -> Result<Vec<u8>, FormatError> {
 	let mut data = Vec::new();
 	for color in colors {
 		data.extend_from_slice(&color.as_bytes());
@@ -1,7 +1,7 @@
-use std::error::Error;
 use std::cmp::Ordering;
 
 use crate::data_view::{ DataView, BitWriter };
+use crate::error::FormatError;
 
 #[derive(Eq)]
 pub struct SpriteDef {
@@ -65,15 +65,15 @@ impl PartialOrd for Sprite {
 	}
 }
 
-pub fn get_sprites(data: &DataView, all_pixel_data: &DataView) -> Result<Vec<Sprite>, Box<dyn Error>> {
+pub fn get_sprites(data: &DataView, all_pixel_data: &DataView) -> Result<Vec<Sprite>, FormatError> {
 	let mut sprites = Vec::new();
 
 	let mut i = 0;
 	while i + 8 <= data.len() {
-		let pixel_data_index = data.get_u16(i) as usize;
-		let offset_x = data.get_i16(i + 2) as i32;
-		let offset_y = data.get_i16(i + 4) as i32;
-		let props = data.get_u16(i + 6);
+		let pixel_data_index = data.get_u16(i)? as usize;
+		let offset_x = data.get_i16(i + 2)? as i32;
+		let offset_y = data.get_i16(i + 4)? as i32;
+		let props = data.get_u16(i + 6)?;
 
 		let bpp = [2, 4, 6, 8][(props & 0x0003) as usize];
 		let _is_flipped = (props & 0x000c) >> 2;			// unused on Smart
@@ -90,13 +90,12 @@ pub fn get_sprites(data: &DataView, all_pixel_data: &DataView) -> Result<Vec<Spr
 
 		let byte_count = width * height * bpp / 8;
 		let pixel_data_offset = pixel_data_index * byte_count;
-		let bits = all_pixel_data.get_bits(pixel_data_offset, byte_count);
+		let bits = all_pixel_data.get_bits(pixel_data_offset, byte_count)?;
 		let mut pixels = Vec::new();
 		for i in 0..(width * height) {
 			let pixel_offset = i * bpp;
 			let pixel_bits = bits[pixel_offset..(pixel_offset + bpp)].to_vec();
-			let bit_string: String = pixel_bits.iter().map(|x| x.to_string()).collect();
-			let color_index = u32::from_str_radix(&bit_string, 2)?;
+			let color_index = pixel_bits.iter().fold(0, |color_index, bit| (color_index << 1) | *bit as u32);
 			pixels.push(color_index);
 		}
 
@@ -175,7 +174,7 @@ pub fn save_pixel_data(sprites: &[Sprite]) -> (Vec<u8>, Vec<SpriteDef>) {
 	(data, sprite_defs)
 }
 
-pub fn save_sprites(sprite_defs: &[SpriteDef]) -> Result<Vec<u8>, Box<dyn Error>> {
+pub fn save_sprites(sprite_defs: &[SpriteDef]) -> Result<Vec<u8>, FormatError> {
 	let mut data = Vec::new();
 
 	for sprite_def in sprite_defs {
@@ -196,7 +195,7 @@ pub fn save_sprites(sprite_defs: &[SpriteDef]) -> Result<Vec<u8>, Box<dyn Error>
 			4 => 1,
 			6 => 2,
 			8 => 3,
-			_ => return Err("Invalid bits per pixel".into())
+			_ => return Err(FormatError::Sprite { sprite_index: sprite_def.index, image_index: None, reason: format!("invalid bits per pixel {}", sprite_def.bpp) })
 		} as u16;
 
 		let width = match sprite_def.width {
@@ -204,7 +203,7 @@ pub fn save_sprites(sprite_defs: &[SpriteDef]) -> Result<Vec<u8>, Box<dyn Error>
 			16 => 1,
 			32 => 2,
 			64 => 3,
-			_ => return Err("Invalid width".into())
+			_ => return Err(FormatError::Sprite { sprite_index: sprite_def.index, image_index: None, reason: format!("invalid width {}", sprite_def.width) })
 		} << 4;
 
 		let height = match sprite_def.height {
@@ -212,7 +211,7 @@ pub fn save_sprites(sprite_defs: &[SpriteDef]) -> Result<Vec<u8>, Box<dyn Error>
 			16 => 1,
 			32 => 2,
 			64 => 3,
-			_ => return Err("Invalid height".into())
+			_ => return Err(FormatError::Sprite { sprite_index: sprite_def.index, image_index: None, reason: format!("invalid height {}", sprite_def.height) })
 		} << 6;
 
 		let is_quadrupled = if sprite_def.is_quadrupled {
@@ -0,0 +1,61 @@
+// fixtures shared by the unit tests
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{ Path, PathBuf };
+
+use crate::BinSize;
+use crate::smacard::{ CardHeader, CardType, TamaSmaCard, new_card, save_card };
+use crate::sprite_pack::image_def::{ ImageSet, SubImage };
+use crate::sprite_pack::palette::Color;
+
+pub fn get_test_card() -> TamaSmaCard {
+	new_card(CardHeader::new(5, CardType::TamaSmaCard, "BANDAI          ", "TAMASMA         ").unwrap())
+}
+
+pub fn get_saved_test_card() -> Vec<u8> {
+	save_card(&get_test_card(), &BinSize::Auto).unwrap()
+}
+
+// an 8x8 image with two subimages and a four color palette
+pub fn get_test_image_set() -> ImageSet {
+	let palette = vec![Color::new(0, 0, 0, 255), Color::new(248, 0, 0, 255), Color::new(0, 248, 0, 255), Color::new(0, 0, 248, 255)];
+	let subimages = (0..2).map(|i| SubImage { offset_x: i, offset_y: -i, pixel_data: (0..64).map(|j| (i as u32 + j) % 4).collect() }).collect();
+	ImageSet {
+		original_index: 0,
+		width: 8,
+		height: 8,
+		width_in_sprites: 1,
+		height_in_sprites: 1,
+		is_quadrupled: false,
+		first_palette_index: 0,
+		palettes: vec![palette],
+		subimages
+	}
+}
+
+// an empty folder under the temp dir, removed again when the test is done with it
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+	pub fn new(name: &str) -> TestDir {
+		let path = std::env::temp_dir().join(format!("smartypants-{}-test-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&path);
+		fs::create_dir_all(&path).unwrap();
+		TestDir(path)
+	}
+}
+
+impl Deref for TestDir {
+	type Target = Path;
+
+	fn deref(&self) -> &Path {
+		&self.0
+	}
+}
+
+impl Drop for TestDir {
+	fn drop(&mut self) {
+		let _ = fs::remove_dir_all(&self.0);
+	}
+}
@@ -0,0 +1,237 @@
+use serde::Serialize;
+
+use crate::{ BinType, get_card_size };
+use crate::data_view::DataView;
+use crate::error::FormatError;
+use crate::text::CharEncoding;
+use crate::smacard::{ read_card, save_card };
+use crate::firmware::{ read_firmware_with_layout, save_firmware, PATCH_HEADER_START, MENU_STRINGS_START };
+use crate::layout::{ FirmwareLayout, detect_firmware_layout };
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ByteRange {
+	pub start: usize,
+	pub end: usize,
+	pub region: String
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RoundTripReport {
+	pub bin_type: BinType,
+	pub original_size: usize,
+	pub saved_size: usize,
+	pub differences: Vec<ByteRange>
+}
+
+impl RoundTripReport {
+	pub fn is_identical(&self) -> bool {
+		self.original_size == self.saved_size && self.differences.is_empty()
+	}
+}
+
+struct Region {
+	start: usize,
+	end: usize,
+	name: String
+}
+
+// parses the original bin as the given type, saves it again without any edits, and reports where the two differ
+pub fn verify_round_trip(original_data: &[u8], bin_type: &BinType, char_codes: &[CharEncoding], layouts: &[FirmwareLayout]) -> Result<RoundTripReport, FormatError> {
+	let data = DataView::new(original_data);
+
+	let (saved_data, regions) = match bin_type {
+		BinType::SmaCard => {
+			let card = read_card(&data, char_codes)?;
+			let saved_data = save_card(&card, &get_card_size(&data))?;
+			(saved_data, get_card_regions(original_data))
+		},
+		BinType::Firmware => {
+			let layout = detect_firmware_layout(original_data, layouts)?;
+			let firmware = read_firmware_with_layout(&data, char_codes, &layout)?;
+			let saved_data = save_firmware(&firmware, original_data, None)?;
+			(saved_data, get_firmware_regions(original_data, &layout))
+		}
+	};
+
+	let mut differences: Vec<ByteRange> = Vec::new();
+	let max_len = original_data.len().max(saved_data.len());
+	for i in 0..max_len {
+		if original_data.get(i) != saved_data.get(i) {
+			let region = get_region_name(&regions, i, original_data.len());
+			match differences.last_mut() {
+				Some(last) if last.end == i && last.region == region => {
+					last.end = i + 1;
+				},
+				_ => differences.push(ByteRange { start: i, end: i + 1, region })
+			}
+		}
+	}
+
+	Ok(RoundTripReport {
+		bin_type: bin_type.clone(),
+		original_size: original_data.len(),
+		saved_size: saved_data.len(),
+		differences
+	})
+}
+
+fn get_region_name(regions: &[Region], offset: usize, original_size: usize) -> String {
+	if offset >= original_size {
+		return "past end of original file".to_string();
+	}
+	match regions.iter().find(|region| region.start <= offset && offset < region.end) {
+		Some(region) => region.name.clone(),
+		None => "unmapped".to_string()
+	}
+}
+
+fn read_u32(data: &[u8], i: usize) -> Option<usize> {
+	let bytes = data.get(i..i+4)?;
+	Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+fn push_region(regions: &mut Vec<Region>, start: usize, end: usize, name: &str) {
+	if start < end {
+		regions.push(Region { start, end, name: name.to_string() });
+	}
+}
+
+fn get_card_regions(data: &[u8]) -> Vec<Region> {
+	let mut regions = Vec::new();
+
+	let header_fields = [
+		(0, 2, "card header: sector count"),
+		(2, 4, "card header: checksum"),
+		(4, 16, "card header: device IDs"),
+		(16, 32, "card header: vendor ID"),
+		(32, 48, "card header: product ID"),
+		(48, 50, "card header: card type"),
+		(50, 52, "card header: card ID"),
+		(52, 54, "card header: reserved"),
+		(54, 56, "card header: year"),
+		(56, 58, "card header: month"),
+		(58, 60, "card header: day"),
+		(60, 62, "card header: revision"),
+		(62, 64, "card header: reserved"),
+		(64, 80, "card header: MD5"),
+		(80, 0x1000, "card header: padding")
+	];
+	for (start, end, name) in header_fields {
+		push_region(&mut regions, start, end, name);
+	}
+
+	// every entry is kept on save, so the table runs as long as its pack count says
+	let pack_count = data.get(0x1002..0x1004).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize).unwrap_or(4);
+	push_region(&mut regions, 0x1000, 0x1000 + 4 + pack_count * 16, "pack table");
+
+	if let (Some(data_pack_offset), Some(data_pack_size)) = (read_u32(data, 0x1000 + 8), read_u32(data, 0x1000 + 16)) {
+		let start = 0x1000 + data_pack_offset;
+		add_data_pack_regions(&mut regions, data, start, start + data_pack_size);
+	}
+
+	if let (Some(sprite_pack_offset), Some(sprite_pack_size)) = (read_u32(data, 0x1000 + 24), read_u32(data, 0x1000 + 32)) {
+		let start = 0x1000 + sprite_pack_offset;
+		add_sprite_pack_regions(&mut regions, data, start, start + sprite_pack_size);
+	}
+
+	for i in 2..pack_count {
+		let entry_start = 0x1000 + 4 + i * 16;
+		if let (Some(offset), Some(size)) = (read_u32(data, entry_start + 4), read_u32(data, entry_start + 12)) {
+			push_region(&mut regions, 0x1000 + offset, 0x1000 + offset + size, &format!("pack {}", i));
+		}
+	}
+
+	if data.len() >= 16 {
+		push_region(&mut regions, data.len() - 16, data.len(), "card signature");
+	}
+
+	push_region(&mut regions, 0x1000, data.len(), "card padding");
+
+	regions
+}
+
+fn get_firmware_regions(data: &[u8], layout: &FirmwareLayout) -> Vec<Region> {
+	let mut regions = Vec::new();
+
+	let has_patch_header = data.starts_with(&PATCH_HEADER_START);
+	let shift = layout.get_shift(data);
+	if has_patch_header {
+		push_region(&mut regions, 0, layout.patch_header_size, "patch header");
+	}
+
+	let menu_strings_start = match layout.menu_strings_start {
+		Some(start) => Some(start + shift),
+		None => (0..data.len()).find(|&i| data[i..].starts_with(&MENU_STRINGS_START))
+	};
+	if let Some(start) = menu_strings_start {
+		push_region(&mut regions, start, start + layout.menu_strings_size, "menu strings");
+	}
+
+	let data_pack_start = layout.data_pack_start + shift;
+	add_data_pack_regions(&mut regions, data, data_pack_start, data_pack_start + layout.data_pack_size);
+
+	let sprite_pack_start = layout.sprite_pack_start + shift;
+	let sprite_pack_size = layout.get_sprite_pack_size(data.len(), shift).unwrap_or(0);
+	add_sprite_pack_regions(&mut regions, data, sprite_pack_start, sprite_pack_start + sprite_pack_size);
+
+	push_region(&mut regions, 0, data.len(), "firmware code");
+
+	regions
+}
+
+fn add_data_pack_regions(regions: &mut Vec<Region>, data: &[u8], start: usize, end: usize) {
+	push_region(regions, start, start + 80, "data pack: table offsets");
+
+	let table_offsets_opt: Option<Vec<usize>> = (0..20)
+		.map(|i| read_u32(data, start + i*4).map(|offset| start + offset*2))
+		.collect();
+	if let Some(mut table_offsets) = table_offsets_opt {
+		table_offsets.push(end);
+		for i in 0..20 {
+			push_region(regions, table_offsets[i], table_offsets[i+1], &format!("data pack: table {}", i));
+		}
+	}
+
+	push_region(regions, start, end, "data pack");
+}
+
+fn add_sprite_pack_regions(regions: &mut Vec<Region>, data: &[u8], start: usize, end: usize) {
+	push_region(regions, start, start + 16, "sprite pack: section offsets");
+
+	let section_offsets_opt: Option<Vec<usize>> = (0..4)
+		.map(|i| read_u32(data, start + i*4).map(|offset| start + offset))
+		.collect();
+	if let Some(mut section_offsets) = section_offsets_opt {
+		section_offsets.push(end);
+		let section_names = ["image defs", "sprite defs", "palettes", "pixel data"];
+		for (i, section_name) in section_names.iter().enumerate() {
+			push_region(regions, section_offsets[i], section_offsets[i+1], &format!("sprite pack: {}", section_name));
+		}
+	}
+
+	push_region(regions, start, end, "sprite pack");
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::BinSize;
+	use crate::smacard::PackEntry;
+	use crate::test_util::get_test_card;
+
+	#[test]
+	fn pack_table_region_covers_every_entry() {
+		let mut card = get_test_card();
+		for i in 4..6 {
+			card.pack_table.entries.push(PackEntry { unknown: 0, offset: 0, padded_size: 0x20, size: 0x10, data: vec![i; 0x10] });
+		}
+		let data = save_card(&card, &BinSize::Auto).unwrap();
+		let regions = get_card_regions(&data);
+
+		let last_entry = 0x1000 + 4 + 5 * 16;
+		assert_eq!(get_region_name(&regions, last_entry, data.len()), "pack table");
+		assert_eq!(get_region_name(&regions, last_entry + 15, data.len()), "pack table");
+		let pack_offset = read_u32(&data, last_entry + 4).unwrap();
+		assert_eq!(get_region_name(&regions, 0x1000 + pack_offset, data.len()), "pack 5");
+	}
+}
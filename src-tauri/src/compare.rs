@@ -0,0 +1,172 @@
+use std::fs;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use tauri::{ AppHandle, Manager, State, Emitter };
+use tauri::async_runtime::spawn;
+
+use rfd::FileDialog;
+
+use smartypants_core::diff::{ self, BinDiff, ChangeKind, get_section_title };
+
+use crate::{ show_spinner, hide_spinner, show_error_message };
+use crate::file::{ FileState, get_user_layouts };
+use crate::text::FontState;
+
+#[derive(Default)]
+pub struct CompareState {
+	pub diff: Mutex<Option<BinDiff>>,
+	pub old_name: Mutex<String>,
+	pub new_name: Mutex<String>
+}
+
+#[derive(Clone, Serialize)]
+struct ComparisonView {
+	old_name: String,
+	new_name: String,
+	summary: String,
+	sections: Vec<ComparisonSection>
+}
+
+#[derive(Clone, Serialize)]
+struct ComparisonSection {
+	title: String,
+	entities: Vec<ComparisonEntity>
+}
+
+#[derive(Clone, Serialize)]
+struct ComparisonEntity {
+	title: String,
+	kind: String,
+	fields: Vec<String>
+}
+
+#[tauri::command]
+pub fn compare_bins(handle: AppHandle) {
+	let file_state: State<FileState> = handle.state();
+	let base_path = file_state.base_path.lock().unwrap().clone();
+
+	let mut old_dialog = FileDialog::new()
+		.set_title("Choose the original bin")
+		.add_filter("firmware dump", &["bin"]);
+	if let Some(base_path) = &base_path {
+		old_dialog = old_dialog.set_directory(base_path);
+	}
+	let Some(old_path) = old_dialog.pick_file() else { return };
+
+	let mut new_dialog = FileDialog::new()
+		.set_title("Choose the modified bin")
+		.add_filter("firmware dump", &["bin"]);
+	if let Some(parent) = old_path.parent() {
+		new_dialog = new_dialog.set_directory(parent);
+	}
+	let Some(new_path) = new_dialog.pick_file() else { return };
+
+	show_spinner(&handle);
+	spawn(async move {
+		if let Err(why) = compare_files(&handle, &old_path, &new_path) {
+			show_error_message(why);
+		}
+		hide_spinner(&handle);
+	});
+}
+
+fn compare_files(handle: &AppHandle, old_path: &Path, new_path: &Path) -> Result<(), Box<dyn Error>> {
+	let compare_state: State<CompareState> = handle.state();
+	let font_state: State<FontState> = handle.state();
+	let char_codes = font_state.char_codes.lock().unwrap().clone();
+
+	let diff = diff::compare_bins(&fs::read(old_path)?, &fs::read(new_path)?, None, &char_codes, &get_user_layouts(handle)?)?;
+	let old_name = get_file_name(old_path);
+	let new_name = get_file_name(new_path);
+
+	handle.emit("show_compare_dialog", get_comparison_view(&diff, &old_name, &new_name))?;
+
+	*compare_state.diff.lock().unwrap() = Some(diff);
+	*compare_state.old_name.lock().unwrap() = old_name;
+	*compare_state.new_name.lock().unwrap() = new_name;
+
+	Ok(())
+}
+
+fn get_file_name(path: &Path) -> String {
+	path.file_name()
+		.map(|file_name| file_name.to_string_lossy().to_string())
+		.unwrap_or_else(|| path.display().to_string())
+}
+
+fn get_comparison_view(diff: &BinDiff, old_name: &str, new_name: &str) -> ComparisonView {
+	let mut sections: Vec<ComparisonSection> = Vec::new();
+	for change in &diff.changes {
+		let title = get_section_title(&change.section).to_string();
+		if !sections.last().is_some_and(|section| section.title == title) {
+			sections.push(ComparisonSection { title, entities: Vec::new() });
+		}
+		if let Some(section) = sections.last_mut() {
+			section.entities.push(ComparisonEntity {
+				title: change.title(),
+				kind: match change.kind {
+					ChangeKind::Added => "added",
+					ChangeKind::Removed => "removed",
+					ChangeKind::Changed => "changed"
+				}.to_string(),
+				fields: change.fields.iter().map(|field| field.describe()).collect()
+			});
+		}
+	}
+
+	let summary = if diff.changes.is_empty() {
+		"No differences found.".to_string()
+	} else {
+		format!("{} changed entities.", diff.changes.len())
+	};
+
+	ComparisonView {
+		old_name: old_name.to_string(),
+		new_name: new_name.to_string(),
+		summary,
+		sections
+	}
+}
+
+#[tauri::command]
+pub fn export_comparison(handle: AppHandle, format: String) {
+	let compare_state: State<CompareState> = handle.state();
+	let file_state: State<FileState> = handle.state();
+
+	let diff_opt = compare_state.diff.lock().unwrap().clone();
+	match diff_opt {
+		Some(diff) => {
+			let old_name = compare_state.old_name.lock().unwrap().clone();
+			let new_name = compare_state.new_name.lock().unwrap().clone();
+			let (filter_name, extension) = if format == "json" { ("JSON", "json") } else { ("Markdown", "md") };
+
+			let mut file_dialog = FileDialog::new()
+				.add_filter(filter_name, &[extension])
+				.set_file_name(format!("comparison.{}", extension));
+			if let Some(base_path) = file_state.base_path.lock().unwrap().as_ref() {
+				file_dialog = file_dialog.set_directory(base_path);
+			}
+
+			if let Some(path) = file_dialog.save_file() {
+				if let Err(why) = write_comparison(&path, &diff, &format, &old_name, &new_name) {
+					show_error_message(why);
+				}
+			}
+		},
+		None => show_error_message("Compare two bins first".into())
+	}
+}
+
+fn write_comparison(path: &Path, diff: &BinDiff, format: &str, old_name: &str, new_name: &str) -> Result<(), Box<dyn Error>> {
+	let contents = if format == "json" {
+		serde_json::to_string_pretty(diff)?
+	} else {
+		diff.to_markdown(old_name, new_name)
+	};
+	fs::write(path, contents)?;
+	Ok(())
+}
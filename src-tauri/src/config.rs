@@ -9,14 +9,16 @@ use tauri::path::BaseDirectory;
 #[derive(serde::Serialize)]
 pub struct ConfigState {
 	pub theme: Mutex<String>,
-	pub show_toolbar: Mutex<bool>
+	pub show_toolbar: Mutex<bool>,
+	pub backup_count: Mutex<usize>
 }
 
 impl Default for ConfigState {
 	fn default() -> Self {
 		ConfigState {
 			theme: Mutex::new(String::new()),
-			show_toolbar: Mutex::new(true)
+			show_toolbar: Mutex::new(true),
+			backup_count: Mutex::new(3)
 		}
 	}
 }
@@ -40,6 +42,11 @@ pub fn load_config(handle: AppHandle) {
 					"show_toolbar" => {
 						set_toolbar_visibility(&handle, Some(prop.value == "true"));
 					},
+					"backup_count" => {
+						if let Ok(backup_count) = prop.value.parse() {
+							set_backup_count(&handle, backup_count);
+						}
+					},
 					_ => ()
 				}
 			}
@@ -51,9 +58,10 @@ pub fn save_config(handle: &AppHandle) -> Result<(), Box<dyn Error>> {
 	let config_state: State<ConfigState> = handle.state();
 	let config_path = handle.path().resolve("resources/config.ini", BaseDirectory::Resource)?;
 	fs::write(config_path, format!(
-		"theme = {}\nshow_toolbar = {}",
+		"theme = {}\nshow_toolbar = {}\nbackup_count = {}",
 		config_state.theme.lock().unwrap(),
-		if *config_state.show_toolbar.lock().unwrap() { "true" } else { "false" }
+		if *config_state.show_toolbar.lock().unwrap() { "true" } else { "false" },
+		config_state.backup_count.lock().unwrap()
 	))?;
 	Ok(())
 }
@@ -137,6 +145,25 @@ pub fn set_toolbar_visibility(handle: &AppHandle, new_value: Option<bool>) {
 	save_config(handle).unwrap();
 }
 
+pub fn set_backup_count(handle: &AppHandle, new_value: usize) {
+	let config_state: State<ConfigState> = handle.state();
+	*config_state.backup_count.lock().unwrap() = new_value;
+
+	if let Some(menu) = handle.menu() {
+		if let Some(MenuItemKind::Submenu(config_menu)) = menu.get("config") {
+			if let Some(MenuItemKind::Submenu(backups_menu)) = config_menu.get("backups") {
+				for menu_item in backups_menu.items().unwrap() {
+					if let Some(check_menu_item) = menu_item.as_check_menuitem() {
+						check_menu_item.set_checked(check_menu_item.id().0 == format!("backups_{}", new_value)).unwrap();
+					}
+				}
+			}
+		}
+	}
+
+	save_config(handle).unwrap();
+}
+
 fn read_ini_file(contents: &str) -> Vec<IniProp> {
 	let mut props = Vec::new();
 	let lines = contents.split('\n').collect::<Vec<&str>>();
@@ -1,168 +1,17 @@
 use std::error::Error;
 
-use serde::{ Serialize, Deserialize };
+use tauri::{ AppHandle, Manager, State, Emitter };
 
-use tauri::{ AppHandle, Manager, State };
+use rfd::{ MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
 
-use super::EntityId;
-use crate::{ DataState, update_window_title };
-use crate::data_view::{ DataView, words_to_bytes, resize_words };
-use crate::text::{ Text, FontState };
-use crate::file::set_file_modified;
-
-#[derive(Clone, Serialize, Deserialize)]
-pub enum CharacterType {
-	Unknown,
-	Egg,
-	Baby,
-	Child,
-	Teen,
-	Adult,
-	Npc
-}
-
-#[derive(Clone, Serialize, Deserialize)]
-pub enum Gender {
-	Female,
-	Male
-}
+use smartypants_core::data_pack::character::{ Character, add_new_character, copy_character, remove_character, get_character_references };
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Character {
-	pub id: EntityId,
-	pub character_type: CharacterType,
-	pub name: Text,
-	pub profile_image_id: EntityId,
-	pub icon_image_id: EntityId,
-	pub composition_id: EntityId,
-	pub unknown1: EntityId,
-	pub pronoun: Text,
-	pub statement: Text,
-	pub question1: Text,
-	pub question2: Text,
-	pub unknown2: u16,
-	pub unknown3: u16,
-	pub global_id: EntityId,
-	pub unknown4: u16,
-	pub unknown5: u16,
-	pub unknown6: u16,
-	pub unknown7: u16,
-	pub gender: Gender
-}
-
-impl Character {
-	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
-		self.id.set_card_id(old_card_id, new_card_id);
-		self.profile_image_id.set_card_id(old_card_id, new_card_id);
-		self.icon_image_id.set_card_id(old_card_id, new_card_id);
-		self.composition_id.set_card_id(old_card_id, new_card_id);
-		self.unknown1.set_card_id(old_card_id, new_card_id);
-		self.global_id.set_card_id(old_card_id, new_card_id);
-	}
-}
-
-pub fn get_characters(handle: &AppHandle, data: &DataView) -> Vec<Character> {
-	let mut characters = Vec::new();
-
-	let mut i = 0;
-	while i + 96 <= data.len() {
-		let id = EntityId::new(data.get_u16(i));
-		let character_type = match data.get_u16(i + 2) {
-			1 => CharacterType::Egg,
-			2 => CharacterType::Baby,
-			3 => CharacterType::Child,
-			4 => CharacterType::Teen,
-			5 => CharacterType::Adult,
-			6 => CharacterType::Npc,
-			_ => CharacterType::Unknown,
-		};
-		let name = data.get_text(handle, i + 4, 10);
-		let profile_image_id = EntityId::new(data.get_u16(i + 24));
-		let icon_image_id = EntityId::new(data.get_u16(i + 26));
-		let composition_id = EntityId::new(data.get_u16(i + 28));
-		let unknown1 = EntityId::new(data.get_u16(i + 30));
-		let pronoun = data.get_text(handle, i + 32, 6);
-		let statement = data.get_text(handle, i + 44, 6);
-		let question1 = data.get_text(handle, i + 56, 6);
-		let question2 = data.get_text(handle, i + 68, 6);
-		let unknown2 = data.get_u16(i + 80);
-		let unknown3 = data.get_u16(i + 82);
-		let global_id = EntityId::new(data.get_u16(i + 84));
-		let unknown4 = data.get_u16(i + 86);
-		let unknown5 = data.get_u16(i + 88);
-		let unknown6 = data.get_u16(i + 90);
-		let unknown7 = data.get_u16(i + 92);
-		let gender = match data.get_u16(i + 94) {
-			0 => Gender::Female,
-			_ => Gender::Male
-		};
-
-		characters.push(Character {
-			id,
-			character_type,
-			name,
-			profile_image_id,
-			icon_image_id,
-			composition_id,
-			unknown1,
-			pronoun,
-			statement,
-			question1,
-			question2,
-			unknown2,
-			unknown3,
-			global_id,
-			unknown4,
-			unknown5,
-			unknown6,
-			unknown7,
-			gender
-		});
-
-		i += 96;
-	}
-
-	characters
-}
-
-pub fn save_characters(characters: &[Character]) -> Result<Vec<u8>, Box<dyn Error>> {
-	let mut words: Vec<u16> = Vec::new();
-
-	for character in characters {
-		words.push(character.id.to_word());
-		words.push(match character.character_type {
-			CharacterType::Unknown => 0,
-			CharacterType::Egg => 1,
-			CharacterType::Baby => 2,
-			CharacterType::Child => 3,
-			CharacterType::Teen => 4,
-			CharacterType::Adult => 5,
-			CharacterType::Npc => 6
-		});
-		words = [words, resize_words(&character.name.data, 10)].concat();
-		words.push(character.profile_image_id.to_word());
-		words.push(character.icon_image_id.to_word());
-		words.push(character.composition_id.to_word());
-		words.push(character.unknown1.to_word());
-		words = [words, resize_words(&character.pronoun.data, 6)].concat();
-		words = [words, resize_words(&character.statement.data, 6)].concat();
-		words = [words, resize_words(&character.question1.data, 6)].concat();
-		words = [words, resize_words(&character.question2.data, 6)].concat();
-		words.push(character.unknown2);
-		words.push(character.unknown3);
-		words.push(character.global_id.to_word());
-		words.push(character.unknown4);
-		words.push(character.unknown5);
-		words.push(character.unknown6);
-		words.push(character.unknown7);
-		words.push(match character.gender {
-			Gender::Female => 0,
-			Gender::Male => 1
-		});
-	}
-
-	Ok(words_to_bytes(&words))
-}
+use crate::{ DataState, update_window_title, show_error_message };
+use crate::text::FontState;
+use crate::file::set_file_modified;
+use crate::history::{ Edit, record_edit };
+use crate::import::update_image_sets;
+use crate::data_pack::get_new_entity_card_id;
 
 #[tauri::command]
 pub fn update_character(handle: AppHandle, index: usize, new_character: Character) -> Option<Character> {
@@ -173,6 +22,8 @@ pub fn update_character(handle: AppHandle, index: usize, new_character: Characte
 	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
 	if let Some(data_pack) = data_pack_opt.as_mut() {
 		if let Some(character) = data_pack.characters.get_mut(index) {
+			let old_character = character.clone();
+
 			character.name.set_string(char_codes, &new_character.name.string);
 			character.profile_image_id = new_character.profile_image_id;
 			character.icon_image_id = new_character.icon_image_id;
@@ -189,11 +40,162 @@ pub fn update_character(handle: AppHandle, index: usize, new_character: Characte
 			character.unknown7 = new_character.unknown7;
 			character.gender = new_character.gender;
 
+			let character = character.clone();
+			drop(data_pack_opt);
+			record_edit(&handle, Edit::Character(index, old_character));
 			set_file_modified(&handle, true);
 			update_window_title(&handle);
-			return Some(character.clone());
+			return Some(character);
 		}
 	}
 
 	None
 }
+
+#[tauri::command]
+pub fn add_character(handle: AppHandle) {
+	if let Err(why) = insert_character(&handle) {
+		show_error_message(why);
+	}
+}
+
+#[tauri::command]
+pub fn duplicate_character(handle: AppHandle, index: usize) {
+	if let Err(why) = copy_character_at(&handle, index) {
+		show_error_message(why);
+	}
+}
+
+#[tauri::command]
+pub fn delete_character(handle: AppHandle, index: usize) {
+	if let Err(why) = delete_character_at(&handle, index) {
+		show_error_message(why);
+	}
+}
+
+fn insert_character(handle: &AppHandle) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
+
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+
+	let old_characters = data_pack.characters.clone();
+	let card_id = get_new_entity_card_id(&bin_type, data_pack);
+	add_new_character(data_pack, card_id)?;
+
+	let characters = data_pack.characters.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::Characters(old_characters));
+	handle.emit("update_characters", (&characters, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
+}
+
+// the copy always goes at the end, since items refer to characters by index
+fn copy_character_at(handle: &AppHandle, index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
+
+	let dialog_result = MessageDialog::new()
+		.set_level(MessageLevel::Info)
+		.set_title("Duplicate Character")
+		.set_description("Should the copy get its own frame group and profile/icon images? Copies can be edited without changing the original character, but take up more space.")
+		.set_buttons(MessageButtons::YesNoCancelCustom("Copy Images".to_string(), "Share Images".to_string(), "Cancel".to_string()))
+		.show();
+	let copy_assets = match dialog_result {
+		MessageDialogResult::Yes => true,
+		MessageDialogResult::No => false,
+		MessageDialogResult::Custom(label) if label == "Copy Images" => true,
+		MessageDialogResult::Custom(label) if label == "Share Images" => false,
+		_ => return Ok(())
+	};
+
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+	let sprite_pack = sprite_pack_opt.as_mut().ok_or("No sprite pack found")?;
+
+	let old_characters = data_pack.characters.clone();
+	let old_frame_groups = data_pack.frame_groups.clone();
+	let old_image_sets = sprite_pack.image_sets.clone();
+
+	let card_id = get_new_entity_card_id(&bin_type, data_pack);
+	copy_character(data_pack, sprite_pack, index, card_id, copy_assets)?;
+
+	let frame_groups_added = data_pack.frame_groups.len() != old_frame_groups.len();
+	let image_sets_added = sprite_pack.image_sets.len() != old_image_sets.len();
+
+	let characters = data_pack.characters.clone();
+	let frame_groups = data_pack.frame_groups.clone();
+	drop(sprite_pack_opt);
+	drop(data_pack_opt);
+
+	let mut edits = vec![Edit::Characters(old_characters)];
+	if frame_groups_added {
+		edits.push(Edit::FrameGroups(old_frame_groups));
+	}
+	if image_sets_added {
+		edits.push(Edit::ImageSets(old_image_sets));
+	}
+	record_edit(handle, Edit::Batch(edits));
+	if frame_groups_added {
+		handle.emit("update_frame_groups", (&frame_groups, false))?;
+	}
+	handle.emit("update_characters", (&characters, true))?;
+	if image_sets_added {
+		update_image_sets(handle)?;
+	}
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
+}
+
+fn delete_character_at(handle: &AppHandle, index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+
+	let (character_name, references) = {
+		let data_pack_opt = data_state.data_pack.lock().unwrap();
+		let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+		let character = data_pack.characters.get(index).ok_or(format!("Character {} not found", index))?;
+		(character.name.string.clone(), get_character_references(data_pack, index))
+	};
+
+	if !references.is_empty() {
+		let mut lines = vec![format!("Character {} ({}) is still in use:", index, character_name)];
+		lines.extend(references);
+		lines.push("Items that unlock it will unlock nothing. Delete it anyway?".to_string());
+		let dialog_result = MessageDialog::new()
+			.set_level(MessageLevel::Warning)
+			.set_title("Delete Character")
+			.set_description(lines.join("\n"))
+			.set_buttons(MessageButtons::OkCancel)
+			.show();
+		if dialog_result != MessageDialogResult::Ok {
+			return Ok(());
+		}
+	}
+
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+
+	let old_characters = data_pack.characters.clone();
+	let old_items = data_pack.items.clone();
+	remove_character(data_pack, index)?;
+
+	let items = data_pack.items.clone();
+	let characters = data_pack.characters.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::Batch(vec![Edit::Characters(old_characters), Edit::Items(old_items)]));
+	handle.emit("update_items", (&items, false))?;
+	handle.emit("update_characters", (&characters, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
+}
@@ -1,281 +1,227 @@
 use std::error::Error;
 
-use serde::{ Serialize, Deserialize };
+use tauri::{ AppHandle, Manager, State, Emitter };
 
-use tauri::{ AppHandle, Manager, State };
+use rfd::{ MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
 
-use super::EntityId;
-use crate::{ DataState, update_window_title };
-use crate::data_view::DataView;
-use crate::file::set_file_modified;
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum FrameLayerType {
-	Unknown,
-	Face,
-	Npc,
-	Body,
-	HeadAccessory,
-	FaceAccessory,
-	BodyAccessory,
-	DirtClouds,
-	HandAccessory
-}
+use smartypants_core::data_pack::DataPack;
+use smartypants_core::data_pack::frame::{ Frame, FrameGroup, FrameLayer, check_frame };
 
-#[derive(Default, Clone, Debug, Serialize, Deserialize)]
-pub struct FrameLayer {
-	pub x: Option<i16>,
-	pub y: Option<i16>,
-	pub subimage_index: u16,
-	pub layer_type: Option<FrameLayerType>,
-	pub image_id: Option<EntityId>,
-	pub unknown1: u16,
-	pub unknown2: u16,
-	pub unknown3: u16
-}
+use crate::{ DataState, update_window_title, show_error_message };
+use crate::file::set_file_modified;
+use crate::history::{ Edit, record_edit };
+use crate::data_pack::get_new_entity_card_id;
 
-#[derive(Clone, Serialize, Deserialize)]
-pub enum Frame {
-	Implicit,
-	Explicit(Vec<FrameLayer>)
-}
+#[tauri::command]
+pub fn update_frame(handle: AppHandle, group_index: usize, frame_index: usize, new_frame: Frame) -> Option<Frame> {
+	if let Err(why) = check_new_frame(&handle, &new_frame) {
+		show_error_message(format!("Frame {}-{} can't be saved: {}", group_index, frame_index, why).into());
+		return None;
+	}
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct FrameGroup {
-	pub frames: Vec<Frame>
-}
+	let data_state: State<DataState> = handle.state();
 
-impl FrameGroup {
-	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
-		for frame in self.frames.iter_mut() {
-			if let Frame::Explicit(layers) = frame {
-				for layer in layers.iter_mut() {
-					if let Some(image_id) = &mut layer.image_id {
-						image_id.set_card_id(old_card_id, new_card_id);
-					}
-				}
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	if let Some(data_pack) = data_pack_opt.as_mut() {
+		if let Some(frame_group) = data_pack.frame_groups.get_mut(group_index) {
+			if let Some(frame) = frame_group.frames.get_mut(frame_index) {
+				let old_frame = std::mem::replace(frame, new_frame);
+				let frame = frame.clone();
+				drop(data_pack_opt);
+				record_edit(&handle, Edit::Frame(group_index, frame_index, old_frame));
+				set_file_modified(&handle, true);
+				update_window_title(&handle);
+				return Some(frame);
 			}
 		}
 	}
-}
 
-pub fn get_frame_layers(data: &DataView) -> Vec<FrameLayer> {
-	let mut frame_layers = Vec::new();
-
-	let mut i = 0;
-	while i + 2 <= data.len() {
-		let mut layer = FrameLayer::default();
-
-		let bitmask = data.get_u16(i);
-		let mut flags = [false; 16];
-		for (i, flag) in flags.iter_mut().enumerate() {
-			*flag = bitmask & (1 << i) != 0;
-		}
+	None
+}
 
-		if flags[0] {
-			i += 2;
-			if i < data.len() {
-				layer.x = Some(data.get_i16(i));
-			}
-		}
+// a new group starts with every frame implicit, or as a copy of another group such as a character's composition
+#[tauri::command]
+pub fn add_frame_group(handle: AppHandle, source_index: Option<usize>) {
+	if let Err(why) = append_frame_group(&handle, source_index) {
+		show_error_message(why);
+	}
+}
 
-		if flags[1] {
-			i += 2;
-			if i < data.len() {
-				layer.y = Some(data.get_i16(i));
+// a frame made implicit loses its layers, so that's confirmed first; one made explicit starts with a single blank layer
+#[tauri::command]
+pub fn set_frame_explicit(handle: AppHandle, group_index: usize, frame_index: usize, is_explicit: bool) -> Option<Frame> {
+	if !is_explicit {
+		let layer_count = match get_layer_count(&handle, group_index, frame_index) {
+			Ok(layer_count) => layer_count,
+			Err(why) => {
+				show_error_message(why);
+				return None;
 			}
-		}
-
-		if flags[2] {
-			i += 2;
-			if i < data.len() {
-				layer.subimage_index = data.get_u16(i);
+		};
+		if layer_count > 0 {
+			let dialog_result = MessageDialog::new()
+				.set_level(MessageLevel::Warning)
+				.set_title("Make Implicit")
+				.set_description(format!("Frame {}-{} has {} layer(s), which will be removed. Make it implicit anyway?", group_index, frame_index, layer_count))
+				.set_buttons(MessageButtons::OkCancel)
+				.show();
+			if dialog_result != MessageDialogResult::Ok {
+				return None;
 			}
 		}
+	}
 
-		if flags[4] {
-			i += 2;
-			if i < data.len() {
-				layer.unknown1 = data.get_u16(i);
-			}
-		}
+	edit_frame(&handle, group_index, frame_index, |frame| {
+		*frame = match (&*frame, is_explicit) {
+			(Frame::Implicit, true) => Frame::Explicit(vec![FrameLayer::default()]),
+			(Frame::Explicit(_), false) => Frame::Implicit,
+			_ => return Ok(())
+		};
+		Ok(())
+	})
+}
 
-		if flags[5] {
-			i += 2;
-			if i < data.len() {
-				layer.unknown2 = data.get_u16(i);
-			}
+#[tauri::command]
+pub fn insert_frame_layer(handle: AppHandle, group_index: usize, frame_index: usize, layer_index: usize) -> Option<Frame> {
+	edit_frame(&handle, group_index, frame_index, |frame| {
+		let layers = get_layers(frame)?;
+		if layer_index > layers.len() {
+			return Err(format!("Can't insert a layer at {}: the frame has only {}", layer_index, layers.len()).into());
 		}
+		layers.insert(layer_index, FrameLayer::default());
+		Ok(())
+	})
+}
 
-		if flags[8] {
-			i += 2;
-			if i < data.len() {
-				layer.unknown3 = data.get_u16(i);
-			}
+// deleting the last layer makes the frame implicit, rather than leaving an explicit frame with no layers
+#[tauri::command]
+pub fn delete_frame_layer(handle: AppHandle, group_index: usize, frame_index: usize, layer_index: usize) -> Option<Frame> {
+	edit_frame(&handle, group_index, frame_index, |frame| {
+		let layers = get_layers(frame)?;
+		if layer_index >= layers.len() {
+			return Err(format!("Layer {} not found", layer_index).into());
 		}
-
-		if flags[9] {
-			i += 2;
-			if i < data.len() {
-				layer.layer_type = Some(match data.get_u16(i) {
-					1 => FrameLayerType::Face,
-					2 => FrameLayerType::Npc,
-					3 => FrameLayerType::Body,
-					4 => FrameLayerType::HeadAccessory,
-					6 => FrameLayerType::FaceAccessory,
-					8 => FrameLayerType::BodyAccessory,
-					9 => FrameLayerType::DirtClouds,
-					10 => FrameLayerType::HandAccessory,
-					_ => FrameLayerType::Unknown,
-				});
-			}
+		layers.remove(layer_index);
+		if layers.is_empty() {
+			*frame = Frame::Implicit;
 		}
+		Ok(())
+	})
+}
 
-		if flags[10] {
-			i += 2;
-			if i < data.len() {
-				layer.image_id = Some(EntityId::new(data.get_u16(i)));
-			}
+#[tauri::command]
+pub fn move_frame_layer(handle: AppHandle, group_index: usize, frame_index: usize, layer_index: usize, new_index: usize) -> Option<Frame> {
+	edit_frame(&handle, group_index, frame_index, |frame| {
+		let layers = get_layers(frame)?;
+		if layer_index >= layers.len() || new_index >= layers.len() {
+			return Err(format!("Can't move layer {} to {}: the frame has only {}", layer_index, new_index, layers.len()).into());
 		}
+		let layer = layers.remove(layer_index);
+		layers.insert(new_index, layer);
+		Ok(())
+	})
+}
 
-		frame_layers.push(layer);
+fn get_layer_count(handle: &AppHandle, group_index: usize, frame_index: usize) -> Result<usize, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+	let frame = data_pack.frame_groups.get(group_index)
+		.and_then(|frame_group| frame_group.frames.get(frame_index))
+		.ok_or(format!("Frame {}-{} not found", group_index, frame_index))?;
+	Ok(match frame {
+		Frame::Explicit(layers) => layers.len(),
+		Frame::Implicit => 0
+	})
+}
 
-		i += 2;
+fn get_layers(frame: &mut Frame) -> Result<&mut Vec<FrameLayer>, Box<dyn Error>> {
+	match frame {
+		Frame::Explicit(layers) => Ok(layers),
+		Frame::Implicit => Err("Implicit frames have no layers; make the frame explicit first".into())
 	}
+}
 
-	frame_layers
+fn check_new_frame(handle: &AppHandle, frame: &Frame) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+	check_new_frame_in(handle, data_pack, frame)
 }
 
-pub fn get_frame_groups(data: &DataView, layers: Vec<FrameLayer>) -> Vec<FrameGroup> {
-	let mut all_frames = Vec::new();
-	let mut i = 0;
-	while i + 4 <= data.len() {
-		let layer_index = data.get_u16(i) as usize;
-		let frame = if layer_index == 0xffff {
-			Frame::Implicit
-		} else {
-			let num_layers = data.get_u16(i + 2) as usize;
-			Frame::Explicit(layers[layer_index..(layer_index + num_layers)].to_vec())
-		};
-		all_frames.push(frame);
-		i += 4;
-	}
+// takes the data pack the caller has already locked; the sprite pack is always locked after it
+fn check_new_frame_in(handle: &AppHandle, data_pack: &DataPack, frame: &Frame) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
 
-	let mut frame_groups = Vec::new();
-	for i in 0..(all_frames.len() / 53) {
-		let frames = all_frames[(i*53)..(i*53+53)].to_vec();
-		frame_groups.push(FrameGroup { frames })
-	}
+	let sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+	let sprite_pack = sprite_pack_opt.as_ref().ok_or("No sprite pack found")?;
 
-	frame_groups
+	check_frame(frame, sprite_pack, get_new_entity_card_id(&bin_type, data_pack))?;
+	Ok(())
 }
 
-pub fn save_frame_groups(frame_groups: &[FrameGroup]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), Box<dyn Error>> {
-	let mut frame_layer_offsets = Vec::new();
-	let mut frame_layer_data = Vec::new();
-	let mut frame_group_data = Vec::new();
-	let mut frame_layer_index = 0_u16;
-
-	for frame_group in frame_groups {
-		for frame in &frame_group.frames {
-			match frame {
-				Frame::Implicit => {
-					frame_group_data.extend_from_slice(&[0xFF, 0xFF, 0, 0]);
-				},
-				Frame::Explicit(frame_layers) => {
-					frame_group_data.extend_from_slice(&frame_layer_index.to_le_bytes());
-					frame_group_data.extend_from_slice(&(frame_layers.len() as u16).to_le_bytes());
-					for frame_layer in frame_layers {
-						frame_layer_offsets.extend_from_slice(&(frame_layer_data.len() as u32 / 2).to_le_bytes());
-						let this_layer_data = save_frame_layer(frame_layer)?;
-						frame_layer_data.extend_from_slice(&this_layer_data);
-						frame_layer_index += 1;
-					}
-				}
-			}
+fn edit_frame(handle: &AppHandle, group_index: usize, frame_index: usize, edit: impl FnOnce(&mut Frame) -> Result<(), Box<dyn Error>>) -> Option<Frame> {
+	match apply_frame_edit(handle, group_index, frame_index, edit) {
+		Ok(frame) => Some(frame),
+		Err(why) => {
+			show_error_message(why);
+			None
 		}
 	}
-
-	frame_layer_offsets.extend_from_slice(&(frame_layer_data.len() as u32 / 2).to_le_bytes());
-
-	Ok((frame_layer_offsets, frame_layer_data, frame_group_data))
 }
 
-fn save_frame_layer(frame_layer: &FrameLayer) -> Result<Vec<u8>, Box<dyn Error>> {
-	let mut bitmask: u16 = 0;
-	let mut data = vec![0, 0];
-
-	if let Some(x) = frame_layer.x {
-		bitmask |= 1 << 0;
-		data.extend_from_slice(&x.to_le_bytes());
-	}
-
-	if let Some(y) = frame_layer.y {
-		bitmask |= 1 << 1;
-		data.extend_from_slice(&y.to_le_bytes());
-	}
-
-	if frame_layer.subimage_index > 0 {
-		bitmask |= 1 << 2;
-		data.extend_from_slice(&frame_layer.subimage_index.to_le_bytes());
-	}
-
-	if frame_layer.unknown1 > 0 {
-		bitmask |= 1 << 4;
-		data.extend_from_slice(&frame_layer.unknown1.to_le_bytes());
-	}
+fn apply_frame_edit(handle: &AppHandle, group_index: usize, frame_index: usize, edit: impl FnOnce(&mut Frame) -> Result<(), Box<dyn Error>>) -> Result<Frame, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
 
-	if frame_layer.unknown2 > 0 {
-		bitmask |= 1 << 5;
-		data.extend_from_slice(&frame_layer.unknown2.to_le_bytes());
-	}
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	let frame = data_pack.frame_groups.get(group_index)
+		.and_then(|frame_group| frame_group.frames.get(frame_index))
+		.ok_or(format!("Frame {}-{} not found", group_index, frame_index))?;
+
+	let old_frame = frame.clone();
+	let mut frame = frame.clone();
+	edit(&mut frame)?;
+	check_new_frame_in(handle, data_pack, &frame)
+		.map_err(|why| format!("Frame {}-{} can't be saved: {}", group_index, frame_index, why))?;
+
+	data_pack.frame_groups[group_index].frames[frame_index] = frame.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::Frame(group_index, frame_index, old_frame));
+	set_file_modified(handle, true);
+	update_window_title(handle);
+	Ok(frame)
+}
 
-	if frame_layer.unknown3 > 0 {
-		bitmask |= 1 << 8;
-		data.extend_from_slice(&frame_layer.unknown3.to_le_bytes());
-	}
+// groups are numbered by position, so new ones go at the end
+fn append_frame_group(handle: &AppHandle, source_index: Option<usize>) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
 
-	if let Some(layer_type) = &frame_layer.layer_type {
-		bitmask |= 1 << 9;
-		let layer_id: u16 = match layer_type {
-			FrameLayerType::Face => 1,
-			FrameLayerType::Npc => 2,
-			FrameLayerType::Body => 3,
-			FrameLayerType::HeadAccessory => 4,
-			FrameLayerType::FaceAccessory => 6,
-			FrameLayerType::BodyAccessory => 8,
-			FrameLayerType::DirtClouds => 9,
-			FrameLayerType::HandAccessory => 10,
-			FrameLayerType::Unknown => 0,
-		};
-		data.extend_from_slice(&layer_id.to_le_bytes());
-	}
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
 
-	if let Some(image_id) = &frame_layer.image_id {
-		bitmask |= 1 << 10;
-		data.extend_from_slice(&image_id.to_word().to_le_bytes());
+	let max_group_count = if get_new_entity_card_id(&bin_type, data_pack).is_some() { 0x100 } else { 0x8000 };
+	if data_pack.frame_groups.len() >= max_group_count {
+		return Err("There are no free frame group IDs left".into());
 	}
 
-	data.splice(0..2, bitmask.to_le_bytes());
-
-	Ok(data)
-}
+	let new_frame_group = match source_index {
+		Some(index) => data_pack.frame_groups.get(index).ok_or(format!("Frame group {} not found", index))?.clone(),
+		None => FrameGroup::new()
+	};
 
-#[tauri::command]
-pub fn update_frame(handle: AppHandle, group_index: usize, frame_index: usize, new_frame: Frame) -> Option<Frame> {
-	let data_state: State<DataState> = handle.state();
+	let old_frame_groups = data_pack.frame_groups.clone();
+	data_pack.frame_groups.push(new_frame_group);
+	let frame_groups = data_pack.frame_groups.clone();
+	drop(data_pack_opt);
 
-	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-	if let Some(data_pack) = data_pack_opt.as_mut() {
-		if let Some(frame_group) = data_pack.frame_groups.get_mut(group_index) {
-			if let Some(frame) = frame_group.frames.get_mut(frame_index) {
-				*frame = new_frame;
-				set_file_modified(&handle, true);
-				update_window_title(&handle);
-				return Some(frame.clone());
-			}
-		}
-	}
+	record_edit(handle, Edit::FrameGroups(old_frame_groups));
+	handle.emit("update_frame_groups", (&frame_groups, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
 
-	None
+	Ok(())
 }
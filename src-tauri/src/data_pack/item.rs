@@ -1,222 +1,17 @@
 use std::error::Error;
 
-use serde::{ Serialize, Deserialize };
+use tauri::{ AppHandle, Manager, State, Emitter };
 
-use tauri::{ AppHandle, Manager, State };
+use rfd::{ MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
 
-use super::EntityId;
-use crate::{ DataState, update_window_title };
-use crate::data_view::{ DataView, words_to_bytes, resize_words };
-use crate::text::{ Text, FontState };
-use crate::file::set_file_modified;
-
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Item {
-	pub id: EntityId,
-	pub item_type: ItemType,
-	pub name: Text,
-	pub image_id: Option<EntityId>,
-	pub worn_image_id: Option<EntityId>,
-	pub close_image_id: Option<EntityId>,
-	pub animation_id: Option<EntityId>,
-	pub price: u16,
-	pub unknown1: u16,
-	pub unknown2: u16,
-	pub unknown3: u16,
-	pub unlocked_character: Option<u16>,
-	pub game_type: Option<GameType>
-}
-
-impl Item {
-	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
-		self.id.set_card_id(old_card_id, new_card_id);
-		if let Some(image_id) = &mut self.image_id {
-			image_id.set_card_id(old_card_id, new_card_id);
-		}
-		if let Some(worn_image_id) = &mut self.worn_image_id {
-			worn_image_id.set_card_id(old_card_id, new_card_id);
-		}
-		if let Some(close_image_id) = &mut self.close_image_id {
-			close_image_id.set_card_id(old_card_id, new_card_id);
-		}
-		if let Some(animation_id) = &mut self.animation_id {
-			animation_id.set_card_id(old_card_id, new_card_id);
-		}
-	}
-}
-
-#[derive(Clone, PartialEq, Serialize, Deserialize)]
-pub enum ItemType {
-	Unknown,
-	Meal,
-	Snack,
-	Toy,
-	AccessoryHead,
-	AccessoryFace,
-	AccessoryBody,
-	AccessoryHand,
-	Room,
-	Game
-}
+use smartypants_core::data_pack::get_next_entity_id;
+use smartypants_core::data_pack::item::{ Item, get_item_references };
 
-impl ItemType {
-	pub fn is_accessory(&self) -> bool {
-		*self == ItemType::AccessoryHead || *self == ItemType::AccessoryFace || *self == ItemType::AccessoryBody || *self == ItemType::AccessoryHand
-	}
-}
-
-#[derive(Clone, Serialize, Deserialize)]
-pub enum GameType {
-	Unknown,
-	GuessingGame,
-	TimingGame,
-	MemoryGame,
-	DodgingGame,
-	ShakingGame,
-	SwipingGame
-}
-
-pub fn get_items(handle: &AppHandle, data: &DataView) -> Vec<Item> {
-	let mut items = Vec::new();
-
-	let mut i = 0;
-	while i + 42 <= data.len() {
-		let id = EntityId::new(data.get_u16(i));
-		let item_type = match data.get_u16(i + 2) {
-			0 => ItemType::Meal,
-			1 => ItemType::Snack,
-			2 => ItemType::Toy,
-			3 => ItemType::AccessoryHead,
-			4 => ItemType::AccessoryFace,
-			5 => ItemType::AccessoryBody,
-			6 => ItemType::AccessoryHand,
-			7 => ItemType::Room,
-			8 => ItemType::Game,
-			_ => ItemType::Unknown
-		};
-		let name = data.get_text(handle, i + 4, 10);
-		let image_id = if data.get_u16(i + 24) > 0 {
-			Some(EntityId::new(data.get_u16(i + 24)))
-		} else {
-			None
-		};
-		let worn_image_id = if data.get_u16(i + 26) > 0 {
-			Some(EntityId::new(data.get_u16(i + 26)))
-		} else {
-			None
-		};
-		let close_image_id = if data.get_u16(i + 28) > 0 {
-			Some(EntityId::new(data.get_u16(i + 28)))
-		} else {
-			None
-		};
-		let animation_id = if data.get_u16(i + 30) > 0 {
-			Some(EntityId::new(data.get_u16(i + 30)))
-		} else {
-			None
-		};
-		let price = data.get_u16(i + 32);
-		let unknown1 = data.get_u16(i + 34);
-		let unknown2 = data.get_u16(i + 36);
-		let unknown3 = data.get_u16(i + 38);
-		let unlocked_character = if item_type == ItemType::Game || data.get_u16(i + 40) == 0 {
-			None
-		} else {
-			Some(data.get_u16(i + 40))
-		};
-		let game_type = if item_type == ItemType::Game {
-			Some(match data.get_u16(i + 40) {
-				10 => GameType::GuessingGame,
-				11 => GameType::TimingGame,
-				12 => GameType::MemoryGame,
-				13 => GameType::DodgingGame,
-				14 => GameType::ShakingGame,
-				15 => GameType::SwipingGame,
-				_ => GameType::Unknown
-			})
-		} else {
-			None
-		};
-
-		items.push(Item {
-			id,
-			item_type,
-			name,
-			image_id,
-			worn_image_id,
-			close_image_id,
-			animation_id,
-			price,
-			unknown1,
-			unknown2,
-			unknown3,
-			unlocked_character,
-			game_type
-		});
-
-		i += 42;
-	}
-
-	items
-}
-
-pub fn save_items(items: &[Item]) -> Result<Vec<u8>, Box<dyn Error>> {
-	let mut words: Vec<u16> = Vec::new();
-
-	for item in items {
-		words.push(item.id.to_word());
-		words.push(match item.item_type {
-			ItemType::Meal => 0,
-			ItemType::Snack => 1,
-			ItemType::Toy => 2,
-			ItemType::AccessoryHead => 3,
-			ItemType::AccessoryFace => 4,
-			ItemType::AccessoryBody => 5,
-			ItemType::AccessoryHand => 6,
-			ItemType::Room => 7,
-			ItemType::Game => 8,
-			ItemType::Unknown => 9,
-		});
-		words = [words, resize_words(&item.name.data, 10)].concat();
-		words.push(match &item.image_id {
-			Some(id) => id.to_word(),
-			None => 0
-		});
-		words.push(match &item.worn_image_id {
-			Some(id) => id.to_word(),
-			None => 0
-		});
-		words.push(match &item.close_image_id {
-			Some(id) => id.to_word(),
-			None => 0
-		});
-		words.push(match &item.animation_id {
-			Some(id) => id.to_word(),
-			None => 0
-		});
-		words.push(item.price);
-		words.push(item.unknown1);
-		words.push(item.unknown2);
-		words.push(item.unknown3);
-		words.push(match item.item_type {
-			ItemType::Game => match &item.game_type {
-				Some(game_type) => match game_type {
-					GameType::GuessingGame => 10,
-					GameType::TimingGame => 11,
-					GameType::MemoryGame => 12,
-					GameType::DodgingGame => 13,
-					GameType::ShakingGame => 14,
-					GameType::SwipingGame => 15,
-					GameType::Unknown => 0
-				},
-				None => 0
-			},
-			_ => item.unlocked_character.unwrap_or(0)
-		});
-	}
-
-	Ok(words_to_bytes(&words))
-}
+use crate::{ DataState, update_window_title, show_error_message };
+use crate::text::FontState;
+use crate::file::set_file_modified;
+use crate::history::{ Edit, record_edit };
+use crate::data_pack::get_new_entity_card_id;
 
 #[tauri::command]
 pub fn update_item(handle: AppHandle, index: usize, new_item: Item) -> Option<Item> {
@@ -227,6 +22,8 @@ pub fn update_item(handle: AppHandle, index: usize, new_item: Item) -> Option<It
 	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
 	if let Some(data_pack) = data_pack_opt.as_mut() {
 		if let Some(item) = data_pack.items.get_mut(index) {
+			let old_item = item.clone();
+
 			item.item_type = new_item.item_type;
 			item.name.set_string(char_codes, &new_item.name.string);
 			item.image_id = new_item.image_id;
@@ -240,11 +37,115 @@ pub fn update_item(handle: AppHandle, index: usize, new_item: Item) -> Option<It
 			item.game_type = new_item.game_type;
 			item.unlocked_character = new_item.unlocked_character;
 
+			let item = item.clone();
+			drop(data_pack_opt);
+			record_edit(&handle, Edit::Item(index, old_item));
 			set_file_modified(&handle, true);
 			update_window_title(&handle);
-			return Some(item.clone());
+			return Some(item);
 		}
 	}
 
 	None
 }
+
+#[tauri::command]
+pub fn add_item(handle: AppHandle) {
+	if let Err(why) = insert_item(&handle, None) {
+		show_error_message(why);
+	}
+}
+
+#[tauri::command]
+pub fn duplicate_item(handle: AppHandle, index: usize) {
+	if let Err(why) = insert_item(&handle, Some(index)) {
+		show_error_message(why);
+	}
+}
+
+#[tauri::command]
+pub fn delete_item(handle: AppHandle, index: usize) {
+	if let Err(why) = remove_item(&handle, index) {
+		show_error_message(why);
+	}
+}
+
+// a new item goes at the end; a duplicate goes right after the item it was copied from
+fn insert_item(handle: &AppHandle, source_index: Option<usize>) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
+
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+
+	let card_id = get_new_entity_card_id(&bin_type, data_pack);
+	let new_id = get_next_entity_id(data_pack.items.iter().map(|item| &item.id), card_id)
+		.ok_or("There are no free item IDs left")?;
+
+	let old_items = data_pack.items.clone();
+	match source_index {
+		Some(index) => {
+			let mut new_item = data_pack.items.get(index).ok_or(format!("Item {} not found", index))?.clone();
+			new_item.id = new_id;
+			data_pack.items.insert(index + 1, new_item);
+		},
+		None => {
+			data_pack.items.push(Item::new(new_id));
+		}
+	}
+
+	let items = data_pack.items.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::Items(old_items));
+	handle.emit("update_items", (&items, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
+}
+
+fn remove_item(handle: &AppHandle, index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+
+	let (item_name, references) = {
+		let data_pack_opt = data_state.data_pack.lock().unwrap();
+		let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+		let item = data_pack.items.get(index).ok_or(format!("Item {} not found", index))?;
+		(item.name.string.clone(), get_item_references(data_pack, index))
+	};
+
+	if !references.is_empty() {
+		let mut lines = vec![format!("Item {} ({}) is still in use:", index, item_name)];
+		lines.extend(references);
+		lines.push("Delete it anyway?".to_string());
+		let dialog_result = MessageDialog::new()
+			.set_level(MessageLevel::Warning)
+			.set_title("Delete Item")
+			.set_description(lines.join("\n"))
+			.set_buttons(MessageButtons::OkCancel)
+			.show();
+		if dialog_result != MessageDialogResult::Ok {
+			return Ok(());
+		}
+	}
+
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	if index >= data_pack.items.len() {
+		return Err(format!("Item {} not found", index).into());
+	}
+
+	let old_items = data_pack.items.clone();
+	data_pack.items.remove(index);
+
+	let items = data_pack.items.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::Items(old_items));
+	handle.emit("update_items", (&items, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
+}
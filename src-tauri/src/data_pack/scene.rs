@@ -1,333 +1,201 @@
 use std::error::Error;
 
-use serde::{ Serialize, Deserialize };
+use tauri::{ AppHandle, Manager, State, Emitter };
 
-use tauri::{ AppHandle, Manager, State };
+use rfd::{ MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
 
-use super::EntityId;
-use crate::{ DataState, update_window_title };
-use crate::data_view::DataView;
+use smartypants_core::data_pack::scene::{ Scene, SceneLayer, get_scene_references, remove_scene };
+
+use crate::{ DataState, update_window_title, show_error_message };
 use crate::file::set_file_modified;
+use crate::history::{ Edit, record_edit };
+use crate::data_pack::get_new_entity_card_id;
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct Scene {
-	pub layers: Vec<SceneLayer>
-}
+#[tauri::command]
+pub fn update_scene_layer(handle: AppHandle, scene_index: usize, layer_index: usize, new_layer: SceneLayer) -> Option<SceneLayer> {
+	let data_state: State<DataState> = handle.state();
 
-impl Scene {
-	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
-		for layer in self.layers.iter_mut() {
-			if let Some(image_id) = &mut layer.image_id {
-				image_id.set_card_id(old_card_id, new_card_id);
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	if let Some(data_pack) = data_pack_opt.as_mut() {
+		if let Some(scene) = data_pack.scenes.get_mut(scene_index) {
+			if let Some(layer) = scene.layers.get_mut(layer_index) {
+				let old_layer = std::mem::replace(layer, new_layer);
+				let layer = layer.clone();
+				drop(data_pack_opt);
+				record_edit(&handle, Edit::SceneLayer(scene_index, layer_index, old_layer));
+				set_file_modified(&handle, true);
+				update_window_title(&handle);
+				return Some(layer);
 			}
 		}
 	}
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SceneLayer {
-	pub x: i16,
-	pub y: i16,
-	pub image_id: Option<EntityId>,
-	pub subimage_index: u16,
-	pub unknown1: i16,
-	pub unknown2: u16,
-	pub unknown3: u16,
-	pub unknown4: u16,
-	pub unknown5: u16,
-	pub unknown6: u16,
-	pub unknown7: u16,
-	pub unknown8: u16,
-	pub flag1: bool,
-	pub flag2: bool,
-	pub flag3: bool,
-	pub flag4: bool
+	None
 }
 
-pub fn get_scene_offsets(data: &DataView) -> (Vec<usize>, Vec<usize>) {
-	let mut offsets = Vec::new();
-	for i in 0..(data.len()/2) {
-		let offset = data.get_u16(i*2) as usize * 2;
-		offsets.push(offset);
+#[tauri::command]
+pub fn add_scene(handle: AppHandle) {
+	if let Err(why) = append_scene(&handle, None) {
+		show_error_message(why);
 	}
+}
 
-	let mut sizes = Vec::new();
-	for i in 0..(offsets.len() - 1) {
-		sizes.push(offsets[i+1] - offsets[i]);
+#[tauri::command]
+pub fn duplicate_scene(handle: AppHandle, index: usize) {
+	if let Err(why) = append_scene(&handle, Some(index)) {
+		show_error_message(why);
 	}
-
-	let _ = offsets.pop();
-
-	(offsets, sizes)
 }
 
-pub fn get_scene_layer_offsets(data: &DataView, offsets: Vec<usize>, sizes: Vec<usize>) -> Vec<Vec<usize>> {
-	let mut scene_layer_offsets = Vec::new();
+#[tauri::command]
+pub fn delete_scene(handle: AppHandle, index: usize) {
+	if let Err(why) = delete_scene_at(&handle, index) {
+		show_error_message(why);
+	}
+}
 
-	for i in 0..offsets.len() {
-		let mut layer_offsets = Vec::new();
-		let layer_offset_data = data.chunk(offsets[i], sizes[i]);
-		for j in 0..(layer_offset_data.len()/2) {
-			let layer_offset = layer_offset_data.get_u16(j*2) * 2;
-			layer_offsets.push(layer_offset as usize);
+#[tauri::command]
+pub fn insert_scene_layer(handle: AppHandle, scene_index: usize, layer_index: usize) {
+	let result = edit_scene(&handle, scene_index, |scene| {
+		if layer_index > scene.layers.len() {
+			return Err(format!("Can't insert a layer at {}: scene {} has only {}", layer_index, scene_index, scene.layers.len()).into());
 		}
-		scene_layer_offsets.push(layer_offsets);
+		scene.layers.insert(layer_index, SceneLayer::default());
+		Ok(())
+	});
+	if let Err(why) = result {
+		show_error_message(why);
 	}
-
-	scene_layer_offsets
 }
 
-pub fn get_scenes(data: &DataView, offsets: Vec<Vec<usize>>) -> Vec<Scene> {
-	let mut scenes = Vec::new();
-
-	for layer_offsets in offsets {
-		let mut layers = Vec::new();
-
-		for i in layer_offsets {
-			if i + 2 < data.len() {
-				let mut local_i = i;
-
-				let bitmask = data.get_u16(i);
-				let mut flags = [false; 16];
-				for (i, flag) in flags.iter_mut().enumerate() {
-					*flag = bitmask & (1 << i) != 0;
-				}
-
-				let x = if flags[0] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_i16(local_i)
-				} else {
-					0
-				};
-
-				let y = if flags[1] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_i16(local_i)
-				} else {
-					0
-				};
-
-				let image_id = if flags[2] && local_i + 2 < data.len() {
-					local_i += 2;
-					Some(EntityId::new(data.get_u16(local_i)))
-				} else {
-					None
-				};
-
-				let unknown1 = if flags[3] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_i16(local_i)
-				} else {
-					0
-				};
-
-				let unknown2 = if flags[4] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let unknown3 = if flags[5] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let subimage_index = if flags[6] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let unknown4 = if flags[7] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let unknown5 = if flags[8] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let unknown6 = if flags[9] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let unknown7 = if flags[10] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let unknown8 = if flags[11] && local_i + 2 < data.len() {
-					local_i += 2;
-					data.get_u16(local_i)
-				} else {
-					0
-				};
-
-				let flag1 = flags[12];
-
-				let flag2 = flags[13];
-
-				let flag3 = flags[14];
-
-				let flag4 = flags[15];
-
-				layers.push(SceneLayer {
-					x,
-					y,
-					image_id,
-					subimage_index,
-					unknown1,
-					unknown2,
-					unknown3,
-					unknown4,
-					unknown5,
-					unknown6,
-					unknown7,
-					unknown8,
-					flag1,
-					flag2,
-					flag3,
-					flag4
-				});
-			}
+#[tauri::command]
+pub fn delete_scene_layer(handle: AppHandle, scene_index: usize, layer_index: usize) {
+	let result = edit_scene(&handle, scene_index, |scene| {
+		if layer_index >= scene.layers.len() {
+			return Err(format!("Scene layer {}-{} not found", scene_index, layer_index).into());
 		}
-
-		scenes.push(Scene { layers });
+		scene.layers.remove(layer_index);
+		Ok(())
+	});
+	if let Err(why) = result {
+		show_error_message(why);
 	}
-
-	scenes
 }
 
-pub fn save_scenes(scenes: &[Scene]) -> Result<(Vec<u8>, Vec<u8>, Vec<u8>), Box<dyn Error>> {
-	let mut scene_offsets = Vec::new();
-	let mut layer_offsets = Vec::new();
-	let mut layer_data = Vec::new();
-
-	for scene in scenes {
-
-		scene_offsets.extend_from_slice(&(layer_offsets.len() as u16 / 2).to_le_bytes());
-		for layer in &scene.layers {
-			let mut bitmask: u16 = 0;
-			let mut this_layer_data = vec![0, 0];
-			layer_offsets.extend_from_slice(&(layer_data.len() as u16 / 2).to_le_bytes());
-
-			if layer.x != 0 {
-				bitmask |= 1 << 0;
-				this_layer_data.extend_from_slice(&layer.x.to_le_bytes());
-			}
+// layers are drawn in order, so moving one changes what it's drawn over
+#[tauri::command]
+pub fn move_scene_layer(handle: AppHandle, scene_index: usize, layer_index: usize, new_index: usize) {
+	let result = edit_scene(&handle, scene_index, |scene| {
+		if layer_index >= scene.layers.len() || new_index >= scene.layers.len() {
+			return Err(format!("Can't move scene layer {}-{} to {}: the scene has only {}", scene_index, layer_index, new_index, scene.layers.len()).into());
+		}
+		let layer = scene.layers.remove(layer_index);
+		scene.layers.insert(new_index, layer);
+		Ok(())
+	});
+	if let Err(why) = result {
+		show_error_message(why);
+	}
+}
 
-			if layer.y != 0 {
-				bitmask |= 1 << 1;
-				this_layer_data.extend_from_slice(&layer.y.to_le_bytes());
-			}
+fn edit_scene(handle: &AppHandle, scene_index: usize, edit: impl FnOnce(&mut Scene) -> Result<(), Box<dyn Error>>) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
 
-			if let Some(image_id) = &layer.image_id {
-				bitmask |= 1 << 2;
-				this_layer_data.extend_from_slice(&image_id.to_word().to_le_bytes());
-			}
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	let scene = data_pack.scenes.get_mut(scene_index).ok_or(format!("Scene {} not found", scene_index))?;
 
-			if layer.unknown1 != 0 {
-				bitmask |= 1 << 3;
-				this_layer_data.extend_from_slice(&layer.unknown1.to_le_bytes());
-			}
+	let old_scene = scene.clone();
+	edit(scene)?;
 
-			if layer.unknown2 != 0 {
-				bitmask |= 1 << 4;
-				this_layer_data.extend_from_slice(&layer.unknown2.to_le_bytes());
-			}
+	let scenes = data_pack.scenes.clone();
+	drop(data_pack_opt);
 
-			if layer.unknown3 != 0 {
-				bitmask |= 1 << 5;
-				this_layer_data.extend_from_slice(&layer.unknown3.to_le_bytes());
-			}
+	record_edit(handle, Edit::Scene(scene_index, old_scene));
+	handle.emit("update_scenes", (&scenes, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
 
-			if layer.subimage_index != 0 {
-				bitmask |= 1 << 6;
-				this_layer_data.extend_from_slice(&layer.subimage_index.to_le_bytes());
-			}
+	Ok(())
+}
 
-			if layer.unknown4 != 0 {
-				bitmask |= 1 << 7;
-				this_layer_data.extend_from_slice(&layer.unknown4.to_le_bytes());
-			}
+// scenes are numbered by position, so new ones go at the end where they can't renumber any others
+fn append_scene(handle: &AppHandle, source_index: Option<usize>) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
 
-			if layer.unknown5 != 0 {
-				bitmask |= 1 << 8;
-				this_layer_data.extend_from_slice(&layer.unknown5.to_le_bytes());
-			}
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
 
-			if layer.unknown6 != 0 {
-				bitmask |= 1 << 9;
-				this_layer_data.extend_from_slice(&layer.unknown6.to_le_bytes());
-			}
+	let max_scene_count = if get_new_entity_card_id(&bin_type, data_pack).is_some() { 0x100 } else { 0x8000 };
+	if data_pack.scenes.len() >= max_scene_count {
+		return Err("There are no free scene IDs left".into());
+	}
 
-			if layer.unknown7 != 0 {
-				bitmask |= 1 << 10;
-				this_layer_data.extend_from_slice(&layer.unknown7.to_le_bytes());
-			}
+	let new_scene = match source_index {
+		Some(index) => data_pack.scenes.get(index).ok_or(format!("Scene {} not found", index))?.clone(),
+		None => Scene { layers: Vec::new() }
+	};
 
-			if layer.unknown8 != 0 {
-				bitmask |= 1 << 11;
-				this_layer_data.extend_from_slice(&layer.unknown8.to_le_bytes());
-			}
+	let old_scenes = data_pack.scenes.clone();
+	data_pack.scenes.push(new_scene);
 
-			if layer.flag1 {
-				bitmask |= 1 << 12;
-			}
+	let scenes = data_pack.scenes.clone();
+	drop(data_pack_opt);
 
-			if layer.flag2 {
-				bitmask |= 1 << 13;
-			}
+	record_edit(handle, Edit::Scenes(old_scenes));
+	handle.emit("update_scenes", (&scenes, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
 
-			if layer.flag3 {
-				bitmask |= 1 << 14;
-			}
+	Ok(())
+}
 
-			if layer.flag4 {
-				bitmask |= 1 << 15;
-			}
+fn delete_scene_at(handle: &AppHandle, index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
 
-			this_layer_data.splice(0..2, bitmask.to_le_bytes().into_iter());
-			layer_data.extend_from_slice(&this_layer_data);
+	let references = {
+		let data_pack_opt = data_state.data_pack.lock().unwrap();
+		let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+		if index >= data_pack.scenes.len() {
+			return Err(format!("Scene {} not found", index).into());
+		}
+		get_scene_references(data_pack, index, get_new_entity_card_id(&bin_type, data_pack))
+	};
+
+	if !references.is_empty() {
+		let mut lines = vec![format!("Scene {} is still in use:", index)];
+		lines.extend(references);
+		lines.push("Games in this scene will be left without one. Delete it anyway?".to_string());
+		let dialog_result = MessageDialog::new()
+			.set_level(MessageLevel::Warning)
+			.set_title("Delete Scene")
+			.set_description(lines.join("\n"))
+			.set_buttons(MessageButtons::OkCancel)
+			.show();
+		if dialog_result != MessageDialogResult::Ok {
+			return Ok(());
 		}
-
-		layer_data.extend_from_slice(&[0, 0]);
 	}
 
-	scene_offsets.extend_from_slice(&(layer_offsets.len() as u16 / 2).to_le_bytes());
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
 
-	Ok((scene_offsets, layer_offsets, layer_data))
-}
+	let old_scenes = data_pack.scenes.clone();
+	let old_items = data_pack.items.clone();
+	let card_id = get_new_entity_card_id(&bin_type, data_pack);
+	remove_scene(data_pack, index, card_id)?;
 
-#[tauri::command]
-pub fn update_scene_layer(handle: AppHandle, scene_index: usize, layer_index: usize, new_layer: SceneLayer) -> Option<SceneLayer> {
-	let data_state: State<DataState> = handle.state();
+	let items = data_pack.items.clone();
+	let scenes = data_pack.scenes.clone();
+	drop(data_pack_opt);
 
-	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-	if let Some(data_pack) = data_pack_opt.as_mut() {
-		if let Some(scene) = data_pack.scenes.get_mut(scene_index) {
-			if let Some(layer) = scene.layers.get_mut(layer_index) {
-				*layer = new_layer;
-				set_file_modified(&handle, true);
-				update_window_title(&handle);
-				return Some(layer.clone());
-			}
-		}
-	}
+	record_edit(handle, Edit::Batch(vec![Edit::Scenes(old_scenes), Edit::Items(old_items)]));
+	handle.emit("update_items", (&items, false))?;
+	handle.emit("update_scenes", (&scenes, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
 
-	None
+	Ok(())
 }
@@ -1,116 +1,214 @@
 use std::error::Error;
-use serde::{ Serialize, Deserialize };
-use tauri::{ AppHandle, Manager, State };
 
-use super::EntityId;
-use crate::{ DataState, update_window_title };
-use crate::data_view::{ DataView, words_to_bytes };
-use crate::text::{ Text, FontState };
+use tauri::{ AppHandle, Manager, State, Emitter };
+
+use rfd::{ MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
+
+use smartypants_core::data_pack::{ DataPack, get_next_entity_id };
+use smartypants_core::data_pack::tamastring::{ TamaString, get_tamastring_references };
+
+use crate::{ DataState, update_window_title, show_error_message };
+use crate::text::FontState;
 use crate::file::set_file_modified;
+use crate::history::{ Edit, record_edit };
+use crate::data_pack::get_new_entity_card_id;
 
-#[derive(Clone, Serialize, Deserialize)]
-pub struct TamaString {
-	pub id: EntityId,
-	pub expression: u16,
-	pub field1: u16,
-	pub field2: u16,
-	pub value: Text
-}
+#[tauri::command]
+pub fn update_tamastring(handle: AppHandle, index: usize, new_tamastring: TamaString) -> Option<TamaString> {
+	let data_state: State<DataState> = handle.state();
+	let font_state: State<FontState> = handle.state();
+	let char_codes = &font_state.char_codes.lock().unwrap();
 
-impl TamaString {
-	pub fn to_words(&self) -> Vec<u16> {
-		let mut data: Vec<u16> = vec![
-			self.id.to_word(),
-			self.expression,
-			self.field1,
-			self.field2
-		];
-		for word in &self.value.data {
-			data.push(*word);
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	if let Some(data_pack) = data_pack_opt.as_mut() {
+		if let Some(tamastring) = data_pack.tamastrings.get_mut(index) {
+			let old_tamastring = tamastring.clone();
+
+			tamastring.expression = new_tamastring.expression;
+			tamastring.field1 = new_tamastring.field1;
+			tamastring.field2 = new_tamastring.field2;
+			tamastring.value.set_string(char_codes, &new_tamastring.value.string);
+
+			let tamastring = tamastring.clone();
+			drop(data_pack_opt);
+			record_edit(&handle, Edit::TamaString(index, old_tamastring));
+			set_file_modified(&handle, true);
+			update_window_title(&handle);
+			return Some(tamastring);
 		}
-		data.push(0);
-		data
 	}
 
-	pub fn set_card_id(&mut self, old_card_id: u8, new_card_id: u8) {
-		self.id.set_card_id(old_card_id, new_card_id);
+	None
+}
+
+#[tauri::command]
+pub fn insert_tamastring(handle: AppHandle, index: usize) {
+	if let Err(why) = insert_tamastring_at(&handle, index) {
+		show_error_message(why);
 	}
 }
 
-pub fn get_tamastrings(handle: &AppHandle, data: &DataView) -> Vec<TamaString> {
-	let font_state: State<FontState> = handle.state();
-	let char_codes = &font_state.char_codes.lock().unwrap();
+#[tauri::command]
+pub fn delete_tamastring(handle: AppHandle, index: usize) {
+	if let Err(why) = delete_tamastring_at(&handle, index) {
+		show_error_message(why);
+	}
+}
 
-	let mut strings = Vec::new();
+#[tauri::command]
+pub fn move_tamastring(handle: AppHandle, index: usize, new_index: usize) {
+	if let Err(why) = move_tamastring_to(&handle, index, new_index) {
+		show_error_message(why);
+	}
+}
 
-	let mut i = 0;
-	while i + 10 <= data.len() {
-		let id = EntityId::new(data.get_u16(i));
+// every later string moves down one, so anything that may refer to them is confirmed first
+fn insert_tamastring_at(handle: &AppHandle, index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
+
+	let lines = {
+		let data_pack_opt = data_state.data_pack.lock().unwrap();
+		let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+		get_moved_references(data_pack, (index..data_pack.tamastrings.len()).map(|i| (i, i + 1)))
+	};
+	if !confirm_references("Insert Dialog String", lines) {
+		return Ok(());
+	}
 
-		let expression = data.get_u16(i+2);
-		let field1 = data.get_u16(i+4);
-		let field2 = data.get_u16(i+6);
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	if index > data_pack.tamastrings.len() {
+		return Err(format!("Can't insert a dialog string at {}: there are only {}", index, data_pack.tamastrings.len()).into());
+	}
 
-		let mut text_data = Vec::new();
-		let mut str_len = 0;
+	let card_id = get_new_entity_card_id(&bin_type, data_pack);
+	let new_id = get_next_entity_id(data_pack.tamastrings.iter().map(|tamastring| &tamastring.id), card_id)
+		.ok_or("There are no free dialog string IDs left")?;
 
-		while i + 8 + str_len*2 < data.len() && data.get_u16(i + 8 + str_len*2) > 0 {
-			let word = data.get_u16(i + 8 + str_len*2);
-			text_data.push(word);
-			str_len += 1;
-		}
-		i += 10 + str_len*2;
-
-		strings.push(TamaString {
-			id,
-			expression,
-			field1,
-			field2,
-			value: Text::from_data(char_codes, &text_data)
-		});
-	}
+	let old_tamastrings = data_pack.tamastrings.clone();
+	data_pack.tamastrings.insert(index, TamaString::new(new_id));
+
+	let tamastrings = data_pack.tamastrings.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::TamaStrings(old_tamastrings));
+	handle.emit("update_tamastrings", (&tamastrings, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
 
-	strings
+	Ok(())
 }
 
-pub fn save_tamastrings(tamastrings: &[TamaString]) -> Result<(Vec<u8>, Vec<u8>), Box<dyn Error>> {
-	let mut words: Vec<u16> = Vec::new();
-	let mut offsets: Vec<u16> = Vec::new();
-
-	for tamastring in tamastrings {
-		offsets.push(words.len() as u16);
-		words.push(tamastring.id.to_word());
-		words.push(tamastring.expression);
-		words.push(tamastring.field1);
-		words.push(tamastring.field2);
-		words.extend_from_slice(&tamastring.value.data);
-		words.push(0);
+fn delete_tamastring_at(handle: &AppHandle, index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+
+	let lines = {
+		let data_pack_opt = data_state.data_pack.lock().unwrap();
+		let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+		let tamastring = data_pack.tamastrings.get(index).ok_or(format!("Dialog string {} not found", index))?;
+		let references = get_tamastring_references(data_pack, index);
+		let mut lines = Vec::new();
+		if !references.is_empty() {
+			lines.push(format!("Dialog string {} is still in use:", tamastring.id.entity_id));
+			lines.extend(references.into_iter().map(|reference| format!("  {}", reference)));
+		}
+		lines.extend(get_moved_references(data_pack, (index + 1..data_pack.tamastrings.len()).map(|i| (i, i - 1))));
+		lines
+	};
+	if !confirm_references("Delete Dialog String", lines) {
+		return Ok(());
+	}
+
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	if index >= data_pack.tamastrings.len() {
+		return Err(format!("Dialog string {} not found", index).into());
 	}
 
-	offsets.push(0xFFFF);
+	let old_tamastrings = data_pack.tamastrings.clone();
+	data_pack.tamastrings.remove(index);
 
-	Ok((words_to_bytes(&offsets), words_to_bytes(&words)))
+	let tamastrings = data_pack.tamastrings.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::TamaStrings(old_tamastrings));
+	handle.emit("update_tamastrings", (&tamastrings, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
 }
 
-#[tauri::command]
-pub fn update_tamastring(handle: AppHandle, index: usize, new_tamastring: TamaString) -> Option<TamaString> {
+// strings keep their IDs when moved, but anything that refers to them by position is confirmed first
+fn move_tamastring_to(handle: &AppHandle, index: usize, new_index: usize) -> Result<(), Box<dyn Error>> {
 	let data_state: State<DataState> = handle.state();
-	let font_state: State<FontState> = handle.state();
-	let char_codes = &font_state.char_codes.lock().unwrap();
+
+	let lines = {
+		let data_pack_opt = data_state.data_pack.lock().unwrap();
+		let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+		if index >= data_pack.tamastrings.len() || new_index >= data_pack.tamastrings.len() {
+			return Err(format!("Can't move dialog string {} to {}: there are only {}", index, new_index, data_pack.tamastrings.len()).into());
+		}
+		let moves: Vec<(usize, usize)> = if index < new_index {
+			(index + 1..=new_index).map(|i| (i, i - 1)).collect()
+		} else {
+			(new_index..index).map(|i| (i, i + 1)).collect()
+		};
+		get_moved_references(data_pack, std::iter::once((index, new_index)).chain(moves))
+	};
+	if index != new_index && !confirm_references("Move Dialog String", lines) {
+		return Ok(());
+	}
 
 	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-	if let Some(data_pack) = data_pack_opt.as_mut() {
-		if let Some(tamastring) = data_pack.tamastrings.get_mut(index) {
-			tamastring.expression = new_tamastring.expression;
-			tamastring.field1 = new_tamastring.field1;
-			tamastring.field2 = new_tamastring.field2;
-			tamastring.value.set_string(char_codes, &new_tamastring.value.string);
+	let data_pack = data_pack_opt.as_mut().ok_or("No data pack found")?;
+	if index >= data_pack.tamastrings.len() || new_index >= data_pack.tamastrings.len() {
+		return Err(format!("Can't move dialog string {} to {}: there are only {}", index, new_index, data_pack.tamastrings.len()).into());
+	}
+	if index == new_index {
+		return Ok(());
+	}
 
-			set_file_modified(&handle, true);
-			update_window_title(&handle);
-			return Some(tamastring.clone());
+	let old_tamastrings = data_pack.tamastrings.clone();
+	let tamastring = data_pack.tamastrings.remove(index);
+	data_pack.tamastrings.insert(new_index, tamastring);
+
+	let tamastrings = data_pack.tamastrings.clone();
+	drop(data_pack_opt);
+
+	record_edit(handle, Edit::TamaStrings(old_tamastrings));
+	handle.emit("update_tamastrings", (&tamastrings, true))?;
+	set_file_modified(handle, true);
+	update_window_title(handle);
+
+	Ok(())
+}
+
+// the strings whose position changes, each with whatever may still refer to it
+fn get_moved_references(data_pack: &DataPack, moves: impl Iterator<Item = (usize, usize)>) -> Vec<String> {
+	let mut lines = Vec::new();
+	for (index, new_index) in moves {
+		let Some(tamastring) = data_pack.tamastrings.get(index) else { continue };
+		let references = get_tamastring_references(data_pack, index);
+		if !references.is_empty() {
+			lines.push(format!("Dialog string {} moves from position {} to {}, but is still in use:", tamastring.id.entity_id, index, new_index));
+			lines.extend(references.into_iter().map(|reference| format!("  {}", reference)));
 		}
 	}
+	lines
+}
 
-	None
+fn confirm_references(title: &str, mut lines: Vec<String>) -> bool {
+	if lines.is_empty() {
+		return true;
+	}
+	lines.push("Continue anyway?".to_string());
+	let dialog_result = MessageDialog::new()
+		.set_level(MessageLevel::Warning)
+		.set_title(title)
+		.set_description(lines.join("\n"))
+		.set_buttons(MessageButtons::OkCancel)
+		.show();
+	dialog_result == MessageDialogResult::Ok
 }
@@ -1,5 +1,5 @@
 use std::error::Error;
-use std::path::{ PathBuf, Path };
+use std::path::{ Path, PathBuf };
 use std::fs::File;
 use std::io::prelude::*;
 
@@ -8,9 +8,13 @@ use tauri::async_runtime::spawn;
 
 use rfd::FileDialog;
 
-use crate::{ DataState, ImageState, show_error_message, show_spinner, hide_spinner };
+use smartypants_core::export::{ export_strings_to, export_images_to, export_image_spritesheet_to };
+use smartypants_core::project::{ ProjectInfo, PROJECT_VERSION, export_project_to };
+use smartypants_core::patch::{ create_patch, get_patch_format };
+
+use crate::{ DataState, ImageState, BinType, show_error_message, show_spinner, hide_spinner };
 use crate::text::FontState;
-use crate::file::FileState;
+use crate::file::{ FileState, get_saved_data };
 
 #[tauri::command]
 pub fn export_strings(handle: AppHandle) {
@@ -40,8 +44,14 @@ pub fn export_strings(handle: AppHandle) {
 		if let Some(path) = file_result {
 			show_spinner(&handle);
 			spawn(async move {
-				if let Err(why) = export_strings_to(&handle, &path) {
-					show_error_message(why);
+				let data_state: State<DataState> = handle.state();
+				let result = {
+					let menu_strings = data_state.menu_strings.lock().unwrap();
+					let data_pack = data_state.data_pack.lock().unwrap();
+					export_strings_to(&path, menu_strings.as_deref(), data_pack.as_ref())
+				};
+				if let Err(why) = result {
+					show_error_message(why.into());
 				}
 				hide_spinner(&handle);
 			});
@@ -49,92 +59,6 @@ pub fn export_strings(handle: AppHandle) {
 	}
 }
 
-pub fn export_strings_to(handle: &AppHandle, path: &PathBuf) -> Result<(), Box<dyn Error>> {
-	let mut wtr = csv::Writer::from_path(path)?;
-	let data_state: State<DataState> = handle.state();
-
-	let blank_line = ["", "", ""];
-
-	wtr.write_record(["ID", "Field", "Original Text"])?;
-
-	let menu_strings_opt = data_state.menu_strings.lock().unwrap();
-	if let Some(menu_strings) = menu_strings_opt.as_ref() {
-
-		wtr.write_record(blank_line)?;
-		wtr.write_record(["MENUS", "", ""])?;
-		wtr.write_record(blank_line)?;
-
-		for (i, str) in menu_strings.iter().enumerate() {
-			let mut id_written = false;
-			let pages = str.string.split("<hr>");
-			for page in pages {
-				let lines = page.split("<br>");
-				for line in lines {
-					if id_written {
-						wtr.write_record(["", "", line])?;
-					} else {
-						wtr.write_record([&i.to_string(), "", line])?;
-						id_written = true;
-					}
-				}
-				wtr.write_record(blank_line)?;
-			}
-			wtr.write_record(blank_line)?;
-		}
-	}
-
-	let data_pack_opt = data_state.data_pack.lock().unwrap();
-	if let Some(data_pack) = data_pack_opt.as_ref() {
-
-		wtr.write_record(blank_line)?;
-		wtr.write_record(["STRINGS", "", ""])?;
-		wtr.write_record(blank_line)?;
-
-		for (i, tamastring) in data_pack.tamastrings.iter().enumerate() {
-			let mut id_written = false;
-			let pages = tamastring.value.string.split("<hr>");
-			for page in pages {
-				let lines = page.split("<br>");
-				for line in lines {
-					if id_written {
-						wtr.write_record(["", "", line])?;
-					} else {
-						wtr.write_record([&i.to_string(), "", line])?;
-						id_written = true;
-					}
-				}
-				wtr.write_record(blank_line)?;
-			}
-			wtr.write_record(blank_line)?;
-		}
-
-		wtr.write_record(blank_line)?;
-		wtr.write_record(["ITEMS", "", ""])?;
-		wtr.write_record(blank_line)?;
-
-		for (i, item) in data_pack.items.iter().enumerate() {
-			wtr.write_record([&i.to_string(), "Name:", &item.name.string])?;
-			wtr.write_record(blank_line)?;
-		}
-
-		wtr.write_record(blank_line)?;
-		wtr.write_record(["CHARACTERS", "", ""])?;
-		wtr.write_record(blank_line)?;
-
-		for (i, char) in data_pack.characters.iter().enumerate() {
-			wtr.write_record([&i.to_string(), "Name:", &char.name.string])?;
-			wtr.write_record(["", "Pronoun:", &char.pronoun.string])?;
-			wtr.write_record(["", "Statement Ending:", &char.statement.string])?;
-			wtr.write_record(["", "Question Ending 1:", &char.question1.string])?;
-			wtr.write_record(["", "Question Ending 2:", &char.question2.string])?;
-			wtr.write_record(blank_line)?;
-		}
-	}
-
-	wtr.flush()?;
-	Ok(())
-}
-
 #[tauri::command]
 pub fn export_images(handle: AppHandle) {
 	let file_state: State<FileState> = handle.state();
@@ -162,8 +86,12 @@ pub fn export_images(handle: AppHandle) {
 		if let Some(path) = file_result {
 			show_spinner(&handle);
 			spawn(async move {
-				let image_state: State<ImageState> = handle.state();
-				if let Err(why) = export_images_to(&handle, &image_state, &path) {
+				let data_state: State<DataState> = handle.state();
+				let result: Result<(), Box<dyn Error>> = match data_state.sprite_pack.lock().unwrap().as_ref() {
+					Some(sprite_pack) => export_images_to(sprite_pack, &path).map_err(Into::into),
+					None => Err("No sprite pack found".into())
+				};
+				if let Err(why) = result {
 					show_error_message(why);
 				}
 				hide_spinner(&handle);
@@ -172,16 +100,6 @@ pub fn export_images(handle: AppHandle) {
 	}
 }
 
-pub fn export_images_to(handle: &AppHandle, image_state: &ImageState, path: &Path) -> Result<(), Box<dyn Error>> {
-	let base_name = path.file_stem().unwrap().to_string_lossy();
-	let image_count = image_state.images.lock().unwrap().len();
-	for i in 0..image_count {
-		let image_path = path.with_file_name(format!("{}-{}", base_name, i)).with_extension("png");
-		export_image_spritesheet_to(handle, i, &image_path)?;
-	}
-	Ok(())
-}
-
 #[tauri::command]
 pub fn export_image_spritesheet(handle: AppHandle, image_index: usize) {
 	let file_state: State<FileState> = handle.state();
@@ -204,7 +122,12 @@ pub fn export_image_spritesheet(handle: AppHandle, image_index: usize) {
 	if let Some(path) = file_result {
 		show_spinner(&handle);
 		spawn(async move {
-			if let Err(why) = export_image_spritesheet_to(&handle, image_index, &path) {
+			let data_state: State<DataState> = handle.state();
+			let result: Result<(), Box<dyn Error>> = match data_state.sprite_pack.lock().unwrap().as_ref() {
+				Some(sprite_pack) => export_image_spritesheet_to(sprite_pack, image_index, &path).map_err(Into::into),
+				None => Err("No sprite pack found".into())
+			};
+			if let Err(why) = result {
 				show_error_message(why);
 			}
 			hide_spinner(&handle);
@@ -212,17 +135,112 @@ pub fn export_image_spritesheet(handle: AppHandle, image_index: usize) {
 	}
 }
 
-fn export_image_spritesheet_to(handle: &AppHandle, image_index: usize, path: &Path) -> Result<(), Box<dyn Error>> {
+#[tauri::command]
+pub fn export_project(handle: AppHandle) {
+	let file_state: State<FileState> = handle.state();
 	let data_state: State<DataState> = handle.state();
-	let sprite_pack_guard = data_state.sprite_pack.lock().unwrap();
-	let sprite_pack = sprite_pack_guard.as_ref().ok_or("No sprite pack found")?;
-	let image_set = sprite_pack.image_sets.get(image_index)
-		.ok_or(format!("Unable to find image definition {}", image_index))?;
-	let spritesheet = match image_set.to_spritesheet() {
-		Ok(spritesheet) => spritesheet,
-		Err(why) => return Err(format!("Image Def {}: {}", image_index, why).into())
+
+	let no_data = data_state.data_pack.lock().unwrap().is_none();
+	if no_data {
+		show_error_message("No data to export".into());
+
+	} else {
+		let mut file_dialog = FileDialog::new();
+
+		if let Some(base_path) = file_state.base_path.lock().unwrap().as_ref() {
+			file_dialog = file_dialog.set_directory(base_path);
+		}
+
+		let file_result = file_dialog.pick_folder();
+
+		if let Some(path) = file_result {
+			show_spinner(&handle);
+			spawn(async move {
+				if let Err(why) = export_project_from_state(&handle, &path) {
+					show_error_message(why);
+				}
+				hide_spinner(&handle);
+			});
+		}
+	}
+}
+
+fn export_project_from_state(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+
+	let bin_type = data_state.bin_type.lock().unwrap().clone().ok_or("Invalid bin type")?;
+	let info = ProjectInfo {
+		version: PROJECT_VERSION,
+		use_patch_header: match bin_type {
+			BinType::Firmware => Some(*data_state.use_patch_header.lock().unwrap()),
+			BinType::SmaCard => None
+		},
+		bin_type,
+		bin_size: data_state.bin_size.lock().unwrap().clone().ok_or("Undefined card size")?
 	};
-	spritesheet.save(path)?;
+
+	let card_header = data_state.card_header.lock().unwrap();
+	let pack_table = data_state.pack_table.lock().unwrap();
+	let data_pack = data_state.data_pack.lock().unwrap();
+	let sprite_pack = data_state.sprite_pack.lock().unwrap();
+	let menu_strings = data_state.menu_strings.lock().unwrap();
+
+	export_project_to(
+		path,
+		&info,
+		card_header.as_ref(),
+		pack_table.as_ref(),
+		data_pack.as_ref().ok_or("No data pack found")?,
+		sprite_pack.as_ref().ok_or("No sprite pack found")?,
+		menu_strings.as_deref()
+	)?;
+	Ok(())
+}
+
+#[tauri::command]
+pub fn export_patch(handle: AppHandle) {
+	let file_state: State<FileState> = handle.state();
+	let data_state: State<DataState> = handle.state();
+
+	let original_size = data_state.original_data.lock().unwrap().as_ref().map(|original_data| original_data.len());
+	match original_size {
+		Some(original_size) => {
+			let extension = get_patch_format(original_size).extension();
+
+			let mut file_dialog = FileDialog::new()
+				.add_filter("patch", &[extension]);
+
+			if let Some(base_path) = file_state.base_path.lock().unwrap().as_ref() {
+				file_dialog = file_dialog.set_directory(base_path);
+			}
+
+			if let Some(file_path) = file_state.file_path.lock().unwrap().as_ref() {
+				if let Some(file_stem) = file_path.file_stem() {
+					file_dialog = file_dialog.set_file_name(format!("{}.{}", file_stem.to_string_lossy(), extension));
+				}
+			}
+
+			if let Some(path) = file_dialog.save_file() {
+				show_spinner(&handle);
+				spawn(async move {
+					if let Err(why) = export_patch_to(&handle, &path) {
+						show_error_message(why);
+					}
+					hide_spinner(&handle);
+				});
+			}
+		},
+		None => show_error_message("Open the original BIN file before exporting a patch".into())
+	}
+}
+
+fn export_patch_to(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let new_data = get_saved_data(handle)?;
+	let original_data = data_state.original_data.lock().unwrap().clone().ok_or("No original data found for current file")?;
+	let (_, patch) = create_patch(&original_data, &new_data)?;
+	let mut file = File::create(path)?;
+	file.write_all(&patch)?;
 	Ok(())
 }
 
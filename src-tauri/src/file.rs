@@ -1,30 +1,48 @@
 use std::fs;
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{ Path, PathBuf };
 use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
 use serde::Serialize;
 
 use tauri::{ AppHandle, Manager, State, Emitter };
-use tauri::async_runtime::spawn;
-
-use rfd::{ FileDialog, MessageButtons, MessageDialog, MessageDialogResult };
+use tauri::async_runtime::{ spawn, spawn_blocking };
+use tauri::path::BaseDirectory;
+
+use rfd::{ FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
+
+use smartypants_core::data_view::DataView;
+use smartypants_core::data_pack::DataPack;
+use smartypants_core::sprite_pack::image_def::ImageSummary;
+use smartypants_core::text::{ Text, CharEncoding };
+use smartypants_core::smacard::{ CardHeader, TamaSmaCard, read_card, save_card };
+use smartypants_core::firmware::{ Firmware, read_firmware_with_layout, save_firmware, get_firmware_budgets };
+use smartypants_core::layout::{ FirmwareLayout, read_layout_folder, detect_firmware_layout };
+use smartypants_core::{ get_bin_type, get_card_size };
+use smartypants_core::detect::{ Detection, detect_bin_type };
+use smartypants_core::verify::{ self, RoundTripReport };
+use smartypants_core::safe_write;
+use smartypants_core::size_report::{ SizeBudget, get_card_budgets };
+use smartypants_core::known_dumps::{ KnownDump, read_known_dumps, identify_dump };
 
 use crate::{ DataState, ImageState, BinType, BinSize, show_spinner, hide_spinner, show_error_message, update_window_title, update_card_size_menu };
-use crate::data_view::DataView;
-use crate::data_pack::DataPack;
-use crate::sprite_pack::image_def::ImageSummary;
-use crate::text::{ Text, FontState, CharEncoding, EncodingLanguage };
-use crate::smacard::{ CardHeader, read_card, save_card };
-use crate::firmware::{ read_firmware, save_firmware };
+use crate::text::{ FontState, EncodingLanguage };
+use crate::history::{ clear_history, mark_saved };
+use crate::config::ConfigState;
+use crate::smacard::warn_about_header_issues;
 
 #[derive(Default)]
 pub struct FileState {
 	pub is_modified: Mutex<bool>,
 	pub file_path: Mutex<Option<PathBuf>>,
-	pub base_path: Mutex<Option<PathBuf>>
+	pub base_path: Mutex<Option<PathBuf>>,
+	pub budget_generation: Mutex<u64>
 }
 
+const SIZE_BUDGET_DELAY: Duration = Duration::from_millis(300);
+
 #[derive(Clone, Serialize)]
 struct FrontendData {
 	encoding_language: EncodingLanguage,
@@ -34,7 +52,8 @@ struct FrontendData {
 	data_pack: Option<DataPack>,
 	image_sets: Vec<ImageSummary>,
 	menu_strings: Option<Vec<Text>>,
-	use_patch_header: bool
+	use_patch_header: bool,
+	identification: Option<String>
 }
 
 #[tauri::command]
@@ -49,107 +68,157 @@ pub fn open_bin(handle: AppHandle) {
 		}
 
 		if let Some(path) = file_dialog.pick_file() {
-			show_spinner(&handle);
+			open_path(handle, path);
+		}
+	}
+}
 
-			spawn(async move {
-				let file_state: State<FileState> = handle.state();
-				let data_state: State<DataState> = handle.state();
-				let image_state: State<ImageState> = handle.state();
-				let font_state: State<FontState> = handle.state();
-
-				let raw_data = fs::read(&path).unwrap();
-				*data_state.original_data.lock().unwrap() = Some(raw_data.clone());
-				let data = DataView::new(&raw_data);
-
-				let bin_type = if data.len() == 16777216 {
-					BinType::Firmware
-				} else {
-					BinType::SmaCard
-				};
-				*data_state.bin_type.lock().unwrap() = Some(bin_type.clone());
-
-				match bin_type {
-					BinType::SmaCard => {
-						match read_card(&handle, &data) {
-							Ok(card) => {
-								*data_state.card_header.lock().unwrap() = Some(card.header.clone());
-
-								*data_state.use_patch_header.lock().unwrap() = false;
-
-								*data_state.data_pack.lock().unwrap() = Some(card.data_pack.clone());
-
-								match card.sprite_pack.get_image_data() {
-									Ok(image_data) => *image_state.images.lock().unwrap() = image_data,
-									Err(why) => show_error_message(why)
-								}
-								*data_state.sprite_pack.lock().unwrap() = Some(card.sprite_pack);
-
-								*data_state.bin_size.lock().unwrap() = Some(if data.len() <= 0x20000 {
-									BinSize::Card128KB
-								} else if data.len() <= 0x100000 {
-									BinSize::Card1MB
-								} else if data.len() <= 0x200000 {
-									BinSize::Card2MB
-								} else {
-									BinSize::TooBig
-								});
-								update_card_size_menu(&handle);
-
-								send_data_to_frontend(&handle);
-
-								handle.emit("show_choose_encoding_dialog", ()).unwrap();
-							},
-							Err(why) => show_error_message(why)
-						}
-					},
+pub fn open_path(handle: AppHandle, path: PathBuf) {
+	show_spinner(&handle);
 
-					BinType::Firmware => {
-						match read_firmware(&handle, &data) {
-							Ok(firmware) => {
-								*data_state.card_header.lock().unwrap() = None;
+	spawn(async move {
+		let file_state: State<FileState> = handle.state();
 
-								*data_state.use_patch_header.lock().unwrap() = firmware.use_patch_header;
+		match load_bin(&handle, &path) {
+			Ok(false) => hide_spinner(&handle),
+			Ok(true) => {
+				clear_history(&handle);
+				mark_saved(&handle);
+				*file_state.is_modified.lock().unwrap() = false;
+				*file_state.file_path.lock().unwrap() = Some(path.to_path_buf());
+				*file_state.base_path.lock().unwrap() = path.parent().map(|parent| parent.to_path_buf());
+				hide_spinner(&handle);
+				update_window_title(&handle);
+				warn_about_header_issues(&handle);
+				warn_about_unknown_base(&handle);
+			},
+			Err(why) => {
+				show_error_message(why);
+				hide_spinner(&handle);
+				update_window_title(&handle);
+			}
+		}
+	});
+}
 
-								*data_state.data_pack.lock().unwrap() = Some(firmware.data_pack.clone());
+// returns false if the file type was unclear and the user cancelled
+fn load_bin(handle: &AppHandle, path: &Path) -> Result<bool, Box<dyn Error>> {
+	let raw_data = fs::read(path)?;
+	let detection = detect_bin_type(&DataView::new(&raw_data));
+	let bin_type = if detection.is_ambiguous {
+		match choose_bin_type(&detection) {
+			Some(bin_type) => bin_type,
+			None => return Ok(false)
+		}
+	} else {
+		detection.bin_type
+	};
+	load_bin_data_as(handle, raw_data, bin_type)?;
+	handle.emit("show_choose_encoding_dialog", ()).unwrap();
+	Ok(true)
+}
 
-								if let Ok(image_data) = firmware.sprite_pack.get_image_data() {
-									*image_state.images.lock().unwrap() = image_data;
-								}
+fn choose_bin_type(detection: &Detection) -> Option<BinType> {
+	let best_guess = match detection.bin_type {
+		BinType::Firmware => "firmware",
+		BinType::SmaCard => "a TamaSma card"
+	};
+	let description = format!("Smarty Pants can't tell what kind of file this is. Its best guess is {}.\n\n{}", best_guess, detection.describe());
+	let dialog_result = MessageDialog::new()
+		.set_level(MessageLevel::Warning)
+		.set_title("Unrecognized file")
+		.set_description(description)
+		.set_buttons(MessageButtons::YesNoCancelCustom("Open as Firmware".to_string(), "Open as Card".to_string(), "Cancel".to_string()))
+		.show();
+	match dialog_result {
+		MessageDialogResult::Yes => Some(BinType::Firmware),
+		MessageDialogResult::No => Some(BinType::SmaCard),
+		MessageDialogResult::Custom(label) if label == "Open as Firmware" => Some(BinType::Firmware),
+		MessageDialogResult::Custom(label) if label == "Open as Card" => Some(BinType::SmaCard),
+		_ => None
+	}
+}
 
-								*data_state.sprite_pack.lock().unwrap() = Some(firmware.sprite_pack.clone());
+pub fn load_bin_data(handle: &AppHandle, raw_data: Vec<u8>) -> Result<(), Box<dyn Error>> {
+	let bin_type = get_bin_type(&DataView::new(&raw_data));
+	load_bin_data_as(handle, raw_data, bin_type)
+}
 
-								if let Some(small_font_images) = image_state.images.lock().unwrap().get(98) {
-									font_state.small_font_images.lock().unwrap().clone_from(small_font_images);
-								}
-								if let Some(large_font_images) = image_state.images.lock().unwrap().get(99) {
-									font_state.large_font_images.lock().unwrap().clone_from(large_font_images);
-								}
+pub fn load_bin_data_as(handle: &AppHandle, raw_data: Vec<u8>, bin_type: BinType) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let image_state: State<ImageState> = handle.state();
+	let font_state: State<FontState> = handle.state();
 
-								*data_state.menu_strings.lock().unwrap() = Some(firmware.menu_strings.clone());
+	let char_codes = font_state.char_codes.lock().unwrap().clone();
+	let layouts = get_user_layouts(handle)?;
 
-								*data_state.bin_size.lock().unwrap() = Some(BinSize::Firmware);
+	let data = DataView::new(&raw_data);
 
-								update_card_size_menu(&handle);
+	match bin_type {
+		BinType::SmaCard => {
+			let card = read_card(&data, &char_codes)?;
 
-								send_data_to_frontend(&handle);
+			*data_state.card_header.lock().unwrap() = Some(card.header.clone());
 
-								handle.emit("show_choose_encoding_dialog", ()).unwrap();
-							},
-							Err(why) => show_error_message(why)
-						}
-					}
-				}
+			*data_state.pack_table.lock().unwrap() = Some(card.pack_table.clone());
 
-				*file_state.is_modified.lock().unwrap() = false;
-				*file_state.file_path.lock().unwrap() = Some(path.to_path_buf());
-				*file_state.base_path.lock().unwrap() = Some(path.parent().unwrap().to_path_buf());
+			*data_state.use_patch_header.lock().unwrap() = false;
 
-				hide_spinner(&handle);
+			*data_state.firmware_layout.lock().unwrap() = None;
 
-				update_window_title(&handle);
-			});
+			*data_state.data_pack.lock().unwrap() = Some(card.data_pack.clone());
+
+			match card.sprite_pack.get_image_data() {
+				Ok(image_data) => *image_state.images.lock().unwrap() = image_data,
+				Err(why) => show_error_message(why.into())
+			}
+			*data_state.sprite_pack.lock().unwrap() = Some(card.sprite_pack);
+
+			*data_state.bin_size.lock().unwrap() = Some(get_card_size(&data));
+		},
+
+		BinType::Firmware => {
+			let layout = detect_firmware_layout(&data.data, &layouts)?;
+			let firmware = read_firmware_with_layout(&data, &char_codes, &layout)?;
+
+			*data_state.card_header.lock().unwrap() = None;
+
+			*data_state.pack_table.lock().unwrap() = None;
+
+			*data_state.use_patch_header.lock().unwrap() = firmware.use_patch_header;
+
+			*data_state.firmware_layout.lock().unwrap() = Some(firmware.layout.clone());
+
+			*data_state.data_pack.lock().unwrap() = Some(firmware.data_pack.clone());
+
+			if let Ok(image_data) = firmware.sprite_pack.get_image_data() {
+				*image_state.images.lock().unwrap() = image_data;
+			}
+
+			*data_state.sprite_pack.lock().unwrap() = Some(firmware.sprite_pack.clone());
+
+			if let Some(small_font_images) = image_state.images.lock().unwrap().get(98) {
+				font_state.small_font_images.lock().unwrap().clone_from(small_font_images);
+			}
+			if let Some(large_font_images) = image_state.images.lock().unwrap().get(99) {
+				font_state.large_font_images.lock().unwrap().clone_from(large_font_images);
+			}
+
+			*data_state.menu_strings.lock().unwrap() = Some(firmware.menu_strings.clone());
+
+			*data_state.bin_size.lock().unwrap() = Some(BinSize::Firmware);
 		}
 	}
+
+	*data_state.identification.lock().unwrap() = identify_dump(&raw_data, &bin_type, &get_known_dumps(handle)?, &layouts);
+	*data_state.original_data.lock().unwrap() = Some(raw_data);
+	*data_state.bin_type.lock().unwrap() = Some(bin_type);
+
+	update_card_size_menu(handle);
+
+	send_data_to_frontend(handle);
+
+	Ok(())
 }
 
 pub fn send_data_to_frontend(handle: &AppHandle) {
@@ -170,10 +239,12 @@ pub fn send_data_to_frontend(handle: &AppHandle) {
 		data_pack: data_state.data_pack.lock().unwrap().clone(),
 		image_sets,
 		menu_strings: data_state.menu_strings.lock().unwrap().clone(),
-		use_patch_header: *data_state.use_patch_header.lock().unwrap()
+		use_patch_header: *data_state.use_patch_header.lock().unwrap(),
+		identification: data_state.identification.lock().unwrap().as_ref().map(|identification| identification.describe())
 	};
 
 	handle.emit("update_data", frontend_data).unwrap();
+	update_size_budget(handle);
 }
 
 #[tauri::command]
@@ -243,7 +314,17 @@ pub fn save_bin_as(handle: AppHandle) {
 	}
 }
 
-pub fn save(handle: &AppHandle, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+pub fn save(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
+	let config_state: State<ConfigState> = handle.state();
+	let backup_count = *config_state.backup_count.lock().unwrap();
+	let new_data = get_saved_data(handle)?;
+	safe_write::write_file(path, &new_data, backup_count)?;
+	mark_saved(handle);
+	set_file_modified(handle, false);
+	Ok(())
+}
+
+pub fn get_saved_data(handle: &AppHandle) -> Result<Vec<u8>, Box<dyn Error>> {
 	let data_state: State<DataState> = handle.state();
 
 	let bin_type_base = data_state.bin_type.lock().unwrap();
@@ -252,29 +333,202 @@ pub fn save(handle: &AppHandle, path: &PathBuf) -> Result<(), Box<dyn Error>> {
 		BinType::Firmware => {
 			let original_data_base = data_state.original_data.lock().unwrap();
 			let original_data = original_data_base.as_ref().ok_or("No original data found for current file")?;
-			let new_data = save_firmware(handle, original_data)?;
+			let firmware = get_firmware(handle)?;
+			let patch_header = if firmware.use_patch_header {
+				let header_path = handle.path().resolve("resources/patch_header.bin", BaseDirectory::Resource)?;
+				Some(fs::read(header_path)?)
+			} else {
+				None
+			};
+			let new_data = save_firmware(&firmware, original_data, patch_header.as_deref())?;
 			if original_data.len() == new_data.len() {
-				fs::write(path, &new_data)?;
-				set_file_modified(handle, false);
+				Ok(new_data)
 			} else {
-				return Err(format!("New data is {} bytes, but original is {} bytes", new_data.len(), original_data.len()).into());
+				Err(format!("New data is {} bytes, but original is {} bytes", new_data.len(), original_data.len()).into())
 			}
 		},
 
 		BinType::SmaCard => {
-			let new_data = save_card(handle)?;
-			fs::write(path, new_data)?;
-			set_file_modified(handle, false);
+			let card = get_card(handle)?;
+			let bin_size = data_state.bin_size.lock().unwrap().clone().ok_or("Undefined card size")?;
+			Ok(save_card(&card, &bin_size)?)
 		}
 	}
+}
+
+// layouts in resources/layouts are tried before the built-in ones, so a new firmware revision only needs a file dropped in
+pub fn get_user_layouts(handle: &AppHandle) -> Result<Vec<FirmwareLayout>, Box<dyn Error>> {
+	let layout_dir = handle.path().resolve("resources/layouts", BaseDirectory::Resource)?;
+	if layout_dir.is_dir() {
+		Ok(read_layout_folder(&layout_dir)?)
+	} else {
+		Ok(Vec::new())
+	}
+}
+
+pub fn get_known_dumps(handle: &AppHandle) -> Result<Vec<KnownDump>, Box<dyn Error>> {
+	let known_dumps_path = handle.path().resolve("resources/known_dumps.json", BaseDirectory::Resource)?;
+	if known_dumps_path.is_file() {
+		Ok(read_known_dumps(&known_dumps_path)?)
+	} else {
+		Ok(Vec::new())
+	}
+}
+
+pub fn get_firmware(handle: &AppHandle) -> Result<Firmware, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let firmware = Firmware {
+		data_pack: data_state.data_pack.lock().unwrap().clone().ok_or("Unable to save firmware: missing data pack")?,
+		sprite_pack: data_state.sprite_pack.lock().unwrap().clone().ok_or("Unable to save firmware: missing sprite pack")?,
+		menu_strings: data_state.menu_strings.lock().unwrap().clone().ok_or("Unable to save firmware: missing menu strings")?,
+		use_patch_header: *data_state.use_patch_header.lock().unwrap(),
+		layout: data_state.firmware_layout.lock().unwrap().clone().ok_or("Unable to save firmware: missing firmware layout")?
+	};
+	Ok(firmware)
+}
+
+pub fn get_card(handle: &AppHandle) -> Result<TamaSmaCard, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let card = TamaSmaCard {
+		header: data_state.card_header.lock().unwrap().clone().ok_or("Unable to save TamaSma Card: missing header")?,
+		pack_table: data_state.pack_table.lock().unwrap().clone().unwrap_or_default(),
+		data_pack: data_state.data_pack.lock().unwrap().clone().ok_or("Unable to save TamaSma Card: missing data pack")?,
+		sprite_pack: data_state.sprite_pack.lock().unwrap().clone().ok_or("Unable to save TamaSma Card: missing sprite pack")?
+	};
+	Ok(card)
+}
+
+#[tauri::command]
+pub fn verify_round_trip(handle: AppHandle) {
+	let data_state: State<DataState> = handle.state();
+	let no_data = data_state.original_data.lock().unwrap().is_none();
+	if no_data {
+		show_error_message("Open a BIN file to verify first".into());
+
+	} else {
+		show_spinner(&handle);
+		spawn(async move {
+			let result = verify_original_data(&handle);
+			hide_spinner(&handle);
+
+			match result {
+				Ok(report) => show_round_trip_report(&report),
+				Err(why) => show_error_message(format!("Unable to verify round trip: {}", why).into())
+			}
+		});
+	}
+}
+
+// reads the original bin back as the type it was opened as, which the user may have chosen
+fn verify_original_data(handle: &AppHandle) -> Result<RoundTripReport, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let font_state: State<FontState> = handle.state();
+	let char_codes = font_state.char_codes.lock().unwrap().clone();
+	let original_data = data_state.original_data.lock().unwrap().clone().unwrap_or_default();
+	let bin_type = data_state.bin_type.lock().unwrap().clone().ok_or("Invalid bin type")?;
+	Ok(verify::verify_round_trip(&original_data, &bin_type, &char_codes, &get_user_layouts(handle)?)?)
+}
 
+// a project is rebuilt from its parts, so check that what it saves as reads back and saves the same way
+pub fn verify_rebuilt_bin(handle: &AppHandle) -> Result<(), Box<dyn Error>> {
+	let font_state: State<FontState> = handle.state();
+	let char_codes = font_state.char_codes.lock().unwrap().clone();
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone().ok_or("Invalid bin type")?;
+	let saved_data = get_saved_data(handle)?;
+	let report = verify::verify_round_trip(&saved_data, &bin_type, &char_codes, &get_user_layouts(handle)?)?;
+	if !report.is_identical() {
+		show_round_trip_report(&report);
+	}
 	Ok(())
 }
 
+fn show_round_trip_report(report: &RoundTripReport) {
+	let description = if report.is_identical() {
+		format!("Saving this file without edits reproduces the original {} bytes exactly. It is safe to edit.", report.original_size)
+	} else {
+		let mut lines = Vec::new();
+		if report.original_size != report.saved_size {
+			lines.push(format!("Size changes from {} to {} bytes.", report.original_size, report.saved_size));
+		}
+		lines.push(format!("{} differing byte range(s):", report.differences.len()));
+		for range in report.differences.iter().take(20) {
+			lines.push(format!("0x{:06X}-0x{:06X} ({} bytes): {}", range.start, range.end - 1, range.end - range.start, range.region));
+		}
+		if report.differences.len() > 20 {
+			lines.push(format!("...and {} more", report.differences.len() - 20));
+		}
+		lines.join("\n")
+	};
+
+	let _ = MessageDialog::new()
+		.set_level(if report.is_identical() { MessageLevel::Info } else { MessageLevel::Warning })
+		.set_title("Verify Round Trip")
+		.set_description(description)
+		.set_buttons(MessageButtons::Ok)
+		.show();
+}
+
 pub fn set_file_modified(handle: &AppHandle, value: bool) {
 	let file_state: State<FileState> = handle.state();
 	*file_state.is_modified.lock().unwrap() = value;
 	update_window_title(handle);
+	update_size_budget(handle);
+}
+
+// shown once when the file is opened, rather than in the middle of an edit
+fn warn_about_unknown_base(handle: &AppHandle) {
+	let data_state: State<DataState> = handle.state();
+	let is_firmware = matches!(*data_state.bin_type.lock().unwrap(), Some(BinType::Firmware));
+	let is_unknown = data_state.identification.lock().unwrap().is_none();
+	if is_firmware && is_unknown {
+		MessageDialog::new()
+			.set_level(MessageLevel::Warning)
+			.set_title("Unrecognized firmware")
+			.set_description("This firmware doesn't match any known dump, so it may already be modified or may not be a Tamagotchi Smart firmware. Keep a copy of the original before saving over it.")
+			.set_buttons(MessageButtons::Ok)
+			.show();
+	}
+}
+
+// saving the sprite pack to measure it takes a moment, so a burst of edits is measured once, after the last one
+pub fn update_size_budget(handle: &AppHandle) {
+	let file_state: State<FileState> = handle.state();
+	let generation = {
+		let mut budget_generation = file_state.budget_generation.lock().unwrap();
+		*budget_generation += 1;
+		*budget_generation
+	};
+
+	let handle = handle.clone();
+	spawn_blocking(move || {
+		thread::sleep(SIZE_BUDGET_DELAY);
+		let file_state: State<FileState> = handle.state();
+		if *file_state.budget_generation.lock().unwrap() != generation {
+			return;
+		}
+		let budgets = get_size_budgets(&handle).unwrap_or_default();
+		handle.emit("update_size_budget", budgets).unwrap();
+	});
+}
+
+fn get_size_budgets(handle: &AppHandle) -> Result<Vec<SizeBudget>, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let bin_type = data_state.bin_type.lock().unwrap().clone();
+	match bin_type {
+		Some(BinType::Firmware) => {
+			let firmware = get_firmware(handle)?;
+			let original_data_base = data_state.original_data.lock().unwrap();
+			let original_data = original_data_base.as_ref().ok_or("No original data found for current file")?;
+			Ok(get_firmware_budgets(&firmware, original_data)?)
+		},
+		Some(BinType::SmaCard) => {
+			let card = get_card(handle)?;
+			let bin_size = data_state.bin_size.lock().unwrap().clone().ok_or("Undefined card size")?;
+			Ok(get_card_budgets(&card, &bin_size)?)
+		},
+		None => Ok(Vec::new())
+	}
 }
 
 pub fn continue_if_modified(handle: &AppHandle) -> bool {
@@ -0,0 +1,387 @@
+use std::error::Error;
+use std::sync::Mutex;
+
+use tauri::{ AppHandle, Manager, State, Emitter };
+
+use smartypants_core::smacard::CardHeader;
+use smartypants_core::data_pack::DataPack;
+use smartypants_core::data_pack::tamastring::TamaString;
+use smartypants_core::data_pack::item::Item;
+use smartypants_core::data_pack::character::Character;
+use smartypants_core::data_pack::frame::{ Frame, FrameGroup };
+use smartypants_core::data_pack::scene::{ Scene, SceneLayer };
+use smartypants_core::sprite_pack::image_def::ImageSet;
+use smartypants_core::text::Text;
+
+use crate::{ DataState, update_window_title, show_error_message };
+use crate::file::set_file_modified;
+use crate::import::{ update_image, update_image_sets };
+
+const MAX_HISTORY: usize = 100;
+
+// each edit holds the value that was replaced, so applying it restores that value
+// and gives back the edit needed to go the other way
+pub enum Edit {
+	CardHeader(CardHeader),
+	DataPack(DataPack),
+	MenuString(usize, Text),
+	MenuStrings(Vec<Text>),
+	TamaString(usize, TamaString),
+	TamaStrings(Vec<TamaString>),
+	Item(usize, Item),
+	Items(Vec<Item>),
+	Character(usize, Character),
+	Characters(Vec<Character>),
+	Frame(usize, usize, Frame),
+	FrameGroups(Vec<FrameGroup>),
+	Scene(usize, Scene),
+	Scenes(Vec<Scene>),
+	SceneLayer(usize, usize, SceneLayer),
+	ImageSet(usize, ImageSet),
+	ImageSets(Vec<ImageSet>),
+	Batch(Vec<Edit>)
+}
+
+// which parts of the UI need to hear about an applied edit
+#[derive(PartialEq)]
+enum Update {
+	CardHeader,
+	DataPack,
+	MenuStrings,
+	TamaStrings,
+	Items,
+	Characters,
+	FrameGroups,
+	Scenes,
+	ImageSet(usize),
+	ImageSets
+}
+
+// saved_depth is how many edits were on the undo stack when the file was last saved,
+// or None once that state can't be reached by undoing or redoing
+#[derive(Default)]
+pub struct HistoryState {
+	pub undo_stack: Mutex<Vec<Edit>>,
+	pub redo_stack: Mutex<Vec<Edit>>,
+	pub saved_depth: Mutex<Option<usize>>
+}
+
+pub fn record_edit(handle: &AppHandle, edit: Edit) {
+	let history_state: State<HistoryState> = handle.state();
+	let mut undo_stack = history_state.undo_stack.lock().unwrap();
+	let mut saved_depth = history_state.saved_depth.lock().unwrap();
+	if saved_depth.is_some_and(|depth| depth > undo_stack.len()) {
+		// the saved state was on the redo stack, which a new edit throws away
+		*saved_depth = None;
+	}
+	undo_stack.push(edit);
+	if undo_stack.len() > MAX_HISTORY {
+		undo_stack.remove(0);
+		*saved_depth = saved_depth.and_then(|depth| depth.checked_sub(1));
+	}
+	history_state.redo_stack.lock().unwrap().clear();
+}
+
+pub fn clear_history(handle: &AppHandle) {
+	let history_state: State<HistoryState> = handle.state();
+	history_state.undo_stack.lock().unwrap().clear();
+	history_state.redo_stack.lock().unwrap().clear();
+	*history_state.saved_depth.lock().unwrap() = None;
+}
+
+pub fn mark_saved(handle: &AppHandle) {
+	let history_state: State<HistoryState> = handle.state();
+	let depth = history_state.undo_stack.lock().unwrap().len();
+	*history_state.saved_depth.lock().unwrap() = Some(depth);
+}
+
+fn is_at_saved_point(handle: &AppHandle) -> bool {
+	let history_state: State<HistoryState> = handle.state();
+	let depth = history_state.undo_stack.lock().unwrap().len();
+	let saved_depth = *history_state.saved_depth.lock().unwrap();
+	saved_depth == Some(depth)
+}
+
+#[tauri::command]
+pub fn undo(handle: AppHandle) {
+	let history_state: State<HistoryState> = handle.state();
+	let edit_opt = history_state.undo_stack.lock().unwrap().pop();
+	if let Some(edit) = edit_opt {
+		let mut updates = Vec::new();
+		match apply_edit(&handle, edit, &mut updates) {
+			Ok(inverse) => {
+				history_state.redo_stack.lock().unwrap().push(inverse);
+				set_file_modified(&handle, !is_at_saved_point(&handle));
+				update_window_title(&handle);
+				if let Err(why) = send_updates(&handle, &updates) {
+					show_error_message(why);
+				}
+			},
+			Err(why) => show_error_message(why)
+		}
+	}
+}
+
+#[tauri::command]
+pub fn redo(handle: AppHandle) {
+	let history_state: State<HistoryState> = handle.state();
+	let edit_opt = history_state.redo_stack.lock().unwrap().pop();
+	if let Some(edit) = edit_opt {
+		let mut updates = Vec::new();
+		match apply_edit(&handle, edit, &mut updates) {
+			Ok(inverse) => {
+				history_state.undo_stack.lock().unwrap().push(inverse);
+				set_file_modified(&handle, !is_at_saved_point(&handle));
+				update_window_title(&handle);
+				if let Err(why) = send_updates(&handle, &updates) {
+					show_error_message(why);
+				}
+			},
+			Err(why) => show_error_message(why)
+		}
+	}
+}
+
+// the UI is only told about the change once the inverse is safely on a stack, so a failed
+// update can't leave an edit that has been applied but can no longer be undone
+fn apply_edit(handle: &AppHandle, edit: Edit, updates: &mut Vec<Update>) -> Result<Edit, Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+
+	match edit {
+		Edit::CardHeader(mut header) => {
+			let mut header_opt = data_state.card_header.lock().unwrap();
+			let current = header_opt.as_mut().ok_or("Unable to undo: no card header found")?;
+			std::mem::swap(current, &mut header);
+			add_update(updates, Update::CardHeader);
+			Ok(Edit::CardHeader(header))
+		},
+
+		Edit::DataPack(mut data_pack) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let current = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			std::mem::swap(current, &mut data_pack);
+			add_update(updates, Update::DataPack);
+			Ok(Edit::DataPack(data_pack))
+		},
+
+		Edit::MenuString(index, mut menu_string) => {
+			let mut menu_strings_opt = data_state.menu_strings.lock().unwrap();
+			let menu_strings = menu_strings_opt.as_mut().ok_or("Unable to undo: no menu strings found")?;
+			let current = menu_strings.get_mut(index).ok_or(format!("Unable to undo: menu string {} not found", index))?;
+			std::mem::swap(current, &mut menu_string);
+			add_update(updates, Update::MenuStrings);
+			Ok(Edit::MenuString(index, menu_string))
+		},
+
+		Edit::MenuStrings(mut menu_strings) => {
+			let mut menu_strings_opt = data_state.menu_strings.lock().unwrap();
+			let current = menu_strings_opt.as_mut().ok_or("Unable to undo: no menu strings found")?;
+			std::mem::swap(current, &mut menu_strings);
+			add_update(updates, Update::MenuStrings);
+			Ok(Edit::MenuStrings(menu_strings))
+		},
+
+		Edit::TamaString(index, mut tamastring) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			let current = data_pack.tamastrings.get_mut(index).ok_or(format!("Unable to undo: string {} not found", index))?;
+			std::mem::swap(current, &mut tamastring);
+			add_update(updates, Update::TamaStrings);
+			Ok(Edit::TamaString(index, tamastring))
+		},
+
+		Edit::TamaStrings(mut tamastrings) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			std::mem::swap(&mut data_pack.tamastrings, &mut tamastrings);
+			add_update(updates, Update::TamaStrings);
+			Ok(Edit::TamaStrings(tamastrings))
+		},
+
+		Edit::Item(index, mut item) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			let current = data_pack.items.get_mut(index).ok_or(format!("Unable to undo: item {} not found", index))?;
+			std::mem::swap(current, &mut item);
+			add_update(updates, Update::Items);
+			Ok(Edit::Item(index, item))
+		},
+
+		Edit::Items(mut items) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			std::mem::swap(&mut data_pack.items, &mut items);
+			add_update(updates, Update::Items);
+			Ok(Edit::Items(items))
+		},
+
+		Edit::Character(index, mut character) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			let current = data_pack.characters.get_mut(index).ok_or(format!("Unable to undo: character {} not found", index))?;
+			std::mem::swap(current, &mut character);
+			add_update(updates, Update::Characters);
+			Ok(Edit::Character(index, character))
+		},
+
+		Edit::Characters(mut characters) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			std::mem::swap(&mut data_pack.characters, &mut characters);
+			add_update(updates, Update::Characters);
+			Ok(Edit::Characters(characters))
+		},
+
+		Edit::Frame(group_index, frame_index, mut frame) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			let current = data_pack.frame_groups.get_mut(group_index)
+				.and_then(|frame_group| frame_group.frames.get_mut(frame_index))
+				.ok_or(format!("Unable to undo: frame {}-{} not found", group_index, frame_index))?;
+			std::mem::swap(current, &mut frame);
+			add_update(updates, Update::FrameGroups);
+			Ok(Edit::Frame(group_index, frame_index, frame))
+		},
+
+		Edit::FrameGroups(mut frame_groups) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			std::mem::swap(&mut data_pack.frame_groups, &mut frame_groups);
+			add_update(updates, Update::FrameGroups);
+			Ok(Edit::FrameGroups(frame_groups))
+		},
+
+		Edit::Scene(index, mut scene) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			let current = data_pack.scenes.get_mut(index).ok_or(format!("Unable to undo: scene {} not found", index))?;
+			std::mem::swap(current, &mut scene);
+			add_update(updates, Update::Scenes);
+			Ok(Edit::Scene(index, scene))
+		},
+
+		Edit::Scenes(mut scenes) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			std::mem::swap(&mut data_pack.scenes, &mut scenes);
+			add_update(updates, Update::Scenes);
+			Ok(Edit::Scenes(scenes))
+		},
+
+		Edit::SceneLayer(scene_index, layer_index, mut layer) => {
+			let mut data_pack_opt = data_state.data_pack.lock().unwrap();
+			let data_pack = data_pack_opt.as_mut().ok_or("Unable to undo: no data pack found")?;
+			let current = data_pack.scenes.get_mut(scene_index)
+				.and_then(|scene| scene.layers.get_mut(layer_index))
+				.ok_or(format!("Unable to undo: scene layer {}-{} not found", scene_index, layer_index))?;
+			std::mem::swap(current, &mut layer);
+			add_update(updates, Update::Scenes);
+			Ok(Edit::SceneLayer(scene_index, layer_index, layer))
+		},
+
+		Edit::ImageSet(index, mut image_set) => {
+			let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+			let sprite_pack = sprite_pack_opt.as_mut().ok_or("Unable to undo: no sprite pack found")?;
+			let current = sprite_pack.image_sets.get_mut(index).ok_or(format!("Unable to undo: image {} not found", index))?;
+			std::mem::swap(current, &mut image_set);
+			add_update(updates, Update::ImageSet(index));
+			Ok(Edit::ImageSet(index, image_set))
+		},
+
+		Edit::ImageSets(mut image_sets) => {
+			let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+			let sprite_pack = sprite_pack_opt.as_mut().ok_or("Unable to undo: no sprite pack found")?;
+			std::mem::swap(&mut sprite_pack.image_sets, &mut image_sets);
+			add_update(updates, Update::ImageSets);
+			Ok(Edit::ImageSets(image_sets))
+		},
+
+		Edit::Batch(edits) => {
+			let inverses = apply_batch(edits, |edit| apply_edit(handle, edit, updates))?;
+			Ok(Edit::Batch(inverses))
+		}
+	}
+}
+
+// a batch is undone last edit first, and its inverses are kept in that same order,
+// so applying them last to first again redoes the batch in the order it was made
+fn apply_batch<T, E>(edits: Vec<T>, apply: impl FnMut(T) -> Result<T, E>) -> Result<Vec<T>, E> {
+	edits.into_iter().rev().map(apply).collect()
+}
+
+fn add_update(updates: &mut Vec<Update>, update: Update) {
+	if !updates.contains(&update) {
+		updates.push(update);
+	}
+}
+
+fn send_updates(handle: &AppHandle, updates: &[Update]) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+
+	for update in updates {
+		match update {
+			Update::CardHeader => {
+				let header_opt = data_state.card_header.lock().unwrap();
+				handle.emit("update_card_header", header_opt.as_ref().ok_or("No card header found")?)?;
+			},
+			Update::DataPack => {
+				let data_pack_opt = data_state.data_pack.lock().unwrap();
+				handle.emit("update_data_pack", data_pack_opt.as_ref().ok_or("No data pack found")?)?;
+			},
+			Update::MenuStrings => {
+				let menu_strings_opt = data_state.menu_strings.lock().unwrap();
+				handle.emit("update_menu_strings", (menu_strings_opt.as_ref().ok_or("No menu strings found")?, false))?;
+			},
+			Update::TamaStrings | Update::Items | Update::Characters | Update::FrameGroups | Update::Scenes => {
+				let data_pack_opt = data_state.data_pack.lock().unwrap();
+				let data_pack = data_pack_opt.as_ref().ok_or("No data pack found")?;
+				match update {
+					Update::TamaStrings => handle.emit("update_tamastrings", (&data_pack.tamastrings, false))?,
+					Update::Items => handle.emit("update_items", (&data_pack.items, false))?,
+					Update::Characters => handle.emit("update_characters", (&data_pack.characters, false))?,
+					Update::FrameGroups => handle.emit("update_frame_groups", (&data_pack.frame_groups, false))?,
+					_ => handle.emit("update_scenes", (&data_pack.scenes, false))?
+				}
+			},
+			Update::ImageSet(index) => {
+				let summary = {
+					let sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+					let sprite_pack = sprite_pack_opt.as_ref().ok_or("No sprite pack found")?;
+					sprite_pack.image_sets.get(*index).ok_or(format!("Image {} not found", index))?.to_summary()
+				};
+				update_image(handle, *index)?;
+				handle.emit("update_image", index)?;
+				handle.emit("update_image_set", (index, summary))?;
+			},
+			Update::ImageSets => update_image_sets(handle)?
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// each edit holds the value it replaced in a single cell
+	fn apply_test_edit(cell: &mut u8, old_value: u8) -> Result<u8, ()> {
+		Ok(std::mem::replace(cell, old_value))
+	}
+
+	#[test]
+	fn batch_with_overlapping_edits_redoes_in_order() {
+		// the batch set the cell from 1 to 2, then from 2 to 3
+		let mut cell = 3;
+		let batch = vec![1, 2];
+
+		let inverses = apply_batch(batch, |edit| apply_test_edit(&mut cell, edit)).unwrap();
+		assert_eq!(cell, 1);
+
+		let batch = apply_batch(inverses, |edit| apply_test_edit(&mut cell, edit)).unwrap();
+		assert_eq!(cell, 3);
+
+		apply_batch(batch, |edit| apply_test_edit(&mut cell, edit)).unwrap();
+		assert_eq!(cell, 1);
+	}
+}
@@ -1,8 +1,6 @@
 use std::error::Error;
 use std::fs;
-use std::path::PathBuf;
-
-use regex::Regex;
+use std::path::{ PathBuf, Path };
 
 use image::{ ImageReader, GenericImageView, RgbaImage };
 
@@ -11,35 +9,15 @@ use tauri::async_runtime::spawn;
 
 use rfd::{ FileDialog, MessageButtons, MessageDialog, MessageDialogResult };
 
-use crate::{ DataState, BinType, ImageState, show_error_message, show_spinner, hide_spinner };
-use crate::sprite_pack::palette::Color;
-use crate::text::{ FontState, CharEncoding, EncodingLanguage, re_decode_strings, refresh_encoding_menu };
-use crate::file::{ FileState, set_file_modified };
-
-#[derive(Clone, Debug, serde::Deserialize)]
-struct TamaStringTranslation {
-	id: u16,
-	value: String,
-	line_count: usize
-}
-
-impl TamaStringTranslation {
-	pub fn new(id: u16) -> TamaStringTranslation {
-		TamaStringTranslation {
-			id,
-			value: String::new(),
-			line_count: 0
-		}
-	}
-}
+use smartypants_core::import;
+use smartypants_core::project::import_project_from;
+use smartypants_core::patch::apply_patch;
+use smartypants_core::text::{ CharEncoding, check_char_codes };
 
-enum StringType {
-	Unknown,
-	Menu,
-	Dialog,
-	Item,
-	Character,
-}
+use crate::{ DataState, BinType, ImageState, show_error_message, show_spinner, hide_spinner, update_window_title, update_card_size_menu };
+use crate::text::{ FontState, EncodingLanguage, re_decode_strings, refresh_encoding_menu };
+use crate::file::{ FileState, set_file_modified, continue_if_modified, send_data_to_frontend, load_bin_data, verify_rebuilt_bin };
+use crate::history::{ Edit, record_edit, clear_history };
 
 #[tauri::command]
 pub fn import_strings(handle: AppHandle) {
@@ -73,131 +51,32 @@ pub fn import_strings(handle: AppHandle) {
 	}
 }
 
-pub fn import_strings_from(handle: &AppHandle, path: &PathBuf) -> Result<(), Box<dyn Error>> {
+pub fn import_strings_from(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
 	let data_state: State<DataState> = handle.state();
 	let font_state: State<FontState> = handle.state();
 	let char_codes = &font_state.char_codes.lock().unwrap();
 
-	let mut current_string_type = StringType::Unknown;
-
-	let mut csv_reader = csv::Reader::from_path(path)?;
-	let mut temp_translation = TamaStringTranslation::new(0);
-	let mut last_line = String::new();
-
-	let add_string = |string_type: &StringType, id: u16, new_string: &str| {
-		match string_type {
-			StringType::Menu => {
-				let mut menu_strings_opt = data_state.menu_strings.lock().unwrap();
-				if let Some(menu_strings) = menu_strings_opt.as_mut() {
-					if let Some(menu_string) = menu_strings.get_mut(id as usize) {
-						menu_string.set_string(char_codes, new_string);
-					}
-				}
-			},
-
-			StringType::Dialog => {
-				let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-				if let Some(data_pack) = data_pack_opt.as_mut() {
-					if let Some(tamastring) = data_pack.tamastrings.get_mut(id as usize) {
-						tamastring.value.set_string(char_codes, new_string);
-					}
-				}
-			},
-
-			StringType::Item => {
-				let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-				if let Some(data_pack) = data_pack_opt.as_mut() {
-					if let Some(item) = data_pack.items.get_mut(id as usize) {
-						item.name.set_string(char_codes, new_string);
-					}
-				}
-			},
-
-			StringType::Character => {
-				let substrings = new_string.split("<br>");
-
-				let mut new_name = String::new();
-				let mut new_pronoun = String::new();
-				let mut new_statement = String::new();
-				let mut new_question1 = String::new();
-				let mut new_question2 = String::new();
-
-				for (i, substring) in substrings.enumerate() {
-					match i {
-						0 => new_name = substring.to_string(),
-						1 => new_pronoun = substring.to_string(),
-						2 => new_statement = substring.to_string(),
-						3 => new_question1 = substring.to_string(),
-						4 => new_question2 = substring.to_string(),
-						_ => {}
-					}
-				}
-
-				let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-				if let Some(data_pack) = data_pack_opt.as_mut() {
-					if let Some(character) = data_pack.characters.get_mut(id as usize) {
-						character.name.set_string(char_codes, &new_name);
-						character.pronoun.set_string(char_codes, &new_pronoun);
-						character.statement.set_string(char_codes, &new_statement);
-						character.question1.set_string(char_codes, &new_question1);
-						character.question2.set_string(char_codes, &new_question2);
-					}
-				}
-			},
-			_ => {}
-		}
-	};
-
-	for result in csv_reader.records() {
-		let record = result?;
-
-		if let Some(id) = record.get(0) {
-
-			if let Ok(id) = id.parse::<u16>() {
-				if id > 0 {
-					add_string(&current_string_type, temp_translation.id, &temp_translation.value);
-				}
-
-				temp_translation = TamaStringTranslation::new(id);
-				if let Some(line) = record.get(2) {
-					temp_translation.value = line.to_string();
-					last_line = line.to_string();
-				}
+	let mut menu_strings_opt = data_state.menu_strings.lock().unwrap();
+	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
 
-			} else if id.is_empty() && !temp_translation.value.is_empty() {
-				if let Some(line) = record.get(2) {
-					if !line.is_empty() {
-						temp_translation.line_count += 1;
-						if last_line.is_empty() {
-							temp_translation.value = format!("{}<hr>{}", temp_translation.value, line);
-						} else {
-							temp_translation.value = format!("{}<br>{}", temp_translation.value, line);
-						}
-					}
-					last_line = line.to_string();
-				}
-			} else {
-				add_string(&current_string_type, temp_translation.id, &temp_translation.value);
-				match id.to_uppercase().as_str() {
-					"MENUS" => current_string_type = StringType::Menu,
-					"DIALOG" | "STRINGS" => current_string_type = StringType::Dialog,
-					"ITEMS" => current_string_type = StringType::Item,
-					"CHARACTERS" => current_string_type = StringType::Character,
-					_ => {}
-				}
-			}
-		}
+	let mut edits = Vec::new();
+	if let Some(menu_strings) = menu_strings_opt.as_ref() {
+		edits.push(Edit::MenuStrings(menu_strings.clone()));
+	}
+	if let Some(data_pack) = data_pack_opt.as_ref() {
+		edits.push(Edit::TamaStrings(data_pack.tamastrings.clone()));
+		edits.push(Edit::Items(data_pack.items.clone()));
+		edits.push(Edit::Characters(data_pack.characters.clone()));
 	}
 
-	add_string(&current_string_type, temp_translation.id, &temp_translation.value);
+	import::import_strings_from(path, char_codes, menu_strings_opt.as_mut(), data_pack_opt.as_mut())?;
+	record_edit(handle, Edit::Batch(edits));
 
-	let mut menu_strings_opt = data_state.menu_strings.lock().unwrap();
-	if let Some(menu_strings) = menu_strings_opt.as_mut() {
+	if let Some(menu_strings) = menu_strings_opt.as_ref() {
 		handle.emit("update_menu_strings", (&menu_strings, false)).unwrap();
 	}
 
-	let mut data_pack_opt = data_state.data_pack.lock().unwrap();
-	if let Some(data_pack) = data_pack_opt.as_mut() {
+	if let Some(data_pack) = data_pack_opt.as_ref() {
 		handle.emit("update_tamastrings", (&data_pack.tamastrings, false)).unwrap();
 		handle.emit("update_items", (&data_pack.items, false)).unwrap();
 		handle.emit("update_characters", (&data_pack.characters, false)).unwrap();
@@ -230,21 +109,20 @@ pub fn import_images(handle: AppHandle) {
 	}
 }
 
-fn import_images_from(handle: &AppHandle, path: &PathBuf) -> Result<(), Box<dyn Error>> {
-	let re = Regex::new(r".+-(\d+).[Pp][Nn][Gg]$")?;
-	for entry in path.read_dir()? {
-		let entry = entry?;
-		let entry_path = entry.path();
-		if let Some(filename) = entry_path.file_name() {
-			if let Some(caps) = re.captures(&filename.to_string_lossy()) {
-				if let Some(image_index_str) = caps.get(1) {
-					if let Ok(image_index) = usize::from_str_radix(image_index_str.as_str(), 10) {
-						import_image_spritesheet_from(&handle, image_index, &entry_path)?;
-					}
-				}
-			}
-		}
+fn import_images_from(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let (image_indexes, old_image_sets) = {
+		let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+		let sprite_pack = sprite_pack_opt.as_mut().ok_or("Can't find sprite pack")?;
+		let old_image_sets = sprite_pack.image_sets.clone();
+		(import::import_images_from(sprite_pack, path)?, old_image_sets)
+	};
+	let mut edits = Vec::new();
+	for image_index in image_indexes {
+		edits.push(Edit::ImageSet(image_index, old_image_sets[image_index].clone()));
+		update_image(handle, image_index)?;
 	}
+	record_edit(handle, Edit::Batch(edits));
 	Ok(())
 }
 
@@ -273,73 +151,162 @@ pub fn import_image_spritesheet(handle: AppHandle, image_index: usize) {
 	}
 }
 
-fn import_image_spritesheet_from(handle: &AppHandle, image_index: usize, path: &PathBuf) -> Result<(), Box<dyn Error>> {
-	let spritesheet = ImageReader::open(path)?.decode()?;
+fn import_image_spritesheet_from(handle: &AppHandle, image_index: usize, path: &Path) -> Result<(), Box<dyn Error>> {
 	let data_state: State<DataState> = handle.state();
-	let font_state: State<FontState> = handle.state();
-	let image_state: State<ImageState> = handle.state();
+	{
+		let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+		let sprite_pack = sprite_pack_opt.as_mut().ok_or("Can't find sprite pack")?;
+		let old_image_set = sprite_pack.image_sets.get(image_index)
+			.ok_or(format!("Can't find image def for image {}", image_index))?
+			.clone();
+		import::import_image_spritesheet_from(sprite_pack, image_index, path)?;
+		record_edit(handle, Edit::ImageSet(image_index, old_image_set));
+	}
+	update_image(handle, image_index)
+}
 
-	let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
-	let sprite_pack = sprite_pack_opt.as_mut().ok_or("Can't find sprite pack")?;
-	let image_set = sprite_pack.image_sets.get_mut(image_index)
-		.ok_or(format!("Can't find image def for image {}", image_index))?;
+#[tauri::command]
+pub fn import_project(handle: AppHandle) {
+	if continue_if_modified(&handle) {
+		let file_state: State<FileState> = handle.state();
+
+		let mut file_dialog = FileDialog::new();
 
-	let palette_count = image_set.palettes.len();
-	let subimage_count = image_set.subimages.len();
+		if let Some(base_path) = file_state.base_path.lock().unwrap().as_ref() {
+			file_dialog = file_dialog.set_directory(base_path);
+		}
 
-	// make sure the spritesheet has the expected dimensions
-	let expected_width = image_set.width * subimage_count as u32;
-	let expected_height = image_set.height * palette_count as u32;
-	if spritesheet.width() != expected_width || spritesheet.height() != expected_height {
-		return Err(format!("Spritesheet does not match expected dimensions: {}x{}", expected_width, expected_height).into());
+		if let Some(path) = file_dialog.pick_folder() {
+			show_spinner(&handle);
+			spawn(async move {
+				match import_project_to_state(&handle, &path) {
+					Ok(()) => {
+						let file_state: State<FileState> = handle.state();
+						clear_history(&handle);
+						*file_state.file_path.lock().unwrap() = None;
+						*file_state.base_path.lock().unwrap() = path.parent().map(|parent| parent.to_path_buf());
+						set_file_modified(&handle, true);
+						hide_spinner(&handle);
+						if let Err(why) = verify_rebuilt_bin(&handle) {
+							show_error_message(format!("Unable to verify the imported project: {}", why).into());
+						}
+					},
+					Err(why) => {
+						hide_spinner(&handle);
+						show_error_message(why);
+					}
+				}
+				update_window_title(&handle);
+			});
+		}
 	}
+}
 
-	let mut images = image_state.images.lock().unwrap();
-	let subimage_imgs = images.get_mut(image_index)
-		.ok_or(format!("Can't find subimages for image {}", image_index))?;
+fn import_project_to_state(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let image_state: State<ImageState> = handle.state();
+	let font_state: State<FontState> = handle.state();
+
+	let char_codes = font_state.char_codes.lock().unwrap().clone();
+	let project = import_project_from(path, &char_codes)?;
 
-	// get main palette
-	let img = spritesheet.view(0, 0, expected_width, image_set.height).to_image();
-	let mut main_palette = Vec::new();
-	let mut color_indexes = Vec::new();
-	for (i, color) in img.pixels().enumerate() {
-		let color = Color::from_rgba(color);
-		if !main_palette.contains(&color) {
-			main_palette.push(color.clone());
-			color_indexes.push(i);
+	// firmware projects only hold the data and sprite packs, so the rest comes from the open firmware
+	if let BinType::Firmware = project.info.bin_type {
+		let has_firmware = matches!(*data_state.bin_type.lock().unwrap(), Some(BinType::Firmware))
+			&& data_state.original_data.lock().unwrap().is_some();
+		if !has_firmware {
+			return Err("Open the firmware this project was exported from before importing it".into());
 		}
+	} else {
+		*data_state.original_data.lock().unwrap() = None;
+		*data_state.identification.lock().unwrap() = None;
 	}
 
-	// get additional palettes
-	image_set.palettes = vec![main_palette];
-	if palette_count > 1 {
-		for i in 1..palette_count {
-			let y = i as u32 * image_set.height;
-			let mut palette = Vec::new();
-			let img = spritesheet.view(0, y, expected_width, image_set.height).to_image();
-			let pixels: Vec<Color> = img.pixels().map(|p| Color::from_rgba(p)).collect();
-			for color_index in &color_indexes {
-				palette.push(pixels[*color_index]);
-			}
-			image_set.palettes.push(palette);
+	let image_data = project.sprite_pack.get_image_data()?;
+	*image_state.images.lock().unwrap() = image_data;
+	if let BinType::Firmware = project.info.bin_type {
+		if let Some(small_font_images) = image_state.images.lock().unwrap().get(98) {
+			font_state.small_font_images.lock().unwrap().clone_from(small_font_images);
+		}
+		if let Some(large_font_images) = image_state.images.lock().unwrap().get(99) {
+			font_state.large_font_images.lock().unwrap().clone_from(large_font_images);
 		}
 	}
 
-	// get pixel data and images
-	for (i, subimage) in image_set.subimages.iter_mut().enumerate() {
-		let x = i as u32 * image_set.width;
-		let img = spritesheet.view(x, 0, image_set.width, image_set.height).to_image();
-		subimage.pixel_data = Vec::new();
-		for color in img.pixels() {
-			let color = Color::from_rgba(color);
-			let pixel = image_set.palettes[0].iter().position(|c| *c == color).ok_or("Can't find color")?;
-			subimage.pixel_data.push(pixel as u32);
+	*data_state.bin_type.lock().unwrap() = Some(project.info.bin_type);
+	*data_state.bin_size.lock().unwrap() = Some(project.info.bin_size);
+	*data_state.use_patch_header.lock().unwrap() = project.info.use_patch_header.unwrap_or(false);
+	*data_state.card_header.lock().unwrap() = project.card_header;
+	*data_state.pack_table.lock().unwrap() = project.pack_table;
+	*data_state.data_pack.lock().unwrap() = Some(project.data_pack);
+	*data_state.sprite_pack.lock().unwrap() = Some(project.sprite_pack);
+	*data_state.menu_strings.lock().unwrap() = project.menu_strings;
+
+	update_card_size_menu(handle);
+	send_data_to_frontend(handle);
+
+	Ok(())
+}
+
+#[tauri::command]
+pub fn import_patch(handle: AppHandle) {
+	let data_state: State<DataState> = handle.state();
+	let no_data = data_state.original_data.lock().unwrap().is_none();
+	if no_data {
+		show_error_message("Open the BIN file this patch was made for first".into());
+
+	} else if continue_if_modified(&handle) {
+		let file_state: State<FileState> = handle.state();
+
+		let mut file_dialog = FileDialog::new()
+			.add_filter("patch", &["ips", "bps"]);
+
+		if let Some(base_path) = file_state.base_path.lock().unwrap().as_ref() {
+			file_dialog = file_dialog.set_directory(base_path);
+		}
+
+		if let Some(path) = file_dialog.pick_file() {
+			show_spinner(&handle);
+			spawn(async move {
+				match import_patch_from(&handle, &path) {
+					Ok(()) => {
+						let file_state: State<FileState> = handle.state();
+						clear_history(&handle);
+						*file_state.file_path.lock().unwrap() = None;
+						set_file_modified(&handle, true);
+					},
+					Err(why) => show_error_message(why)
+				}
+				hide_spinner(&handle);
+				update_window_title(&handle);
+			});
 		}
 	}
+}
+
+fn import_patch_from(handle: &AppHandle, path: &Path) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let original_data = data_state.original_data.lock().unwrap().clone().ok_or("No original data found for current file")?;
+	let new_data = apply_patch(&original_data, &fs::read(path)?)?;
+	load_bin_data(handle, new_data)
+}
+
+pub fn update_image(handle: &AppHandle, image_index: usize) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let font_state: State<FontState> = handle.state();
+	let image_state: State<ImageState> = handle.state();
+
+	let sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+	let sprite_pack = sprite_pack_opt.as_ref().ok_or("Can't find sprite pack")?;
+	let image_set = sprite_pack.image_sets.get(image_index)
+		.ok_or(format!("Can't find image def for image {}", image_index))?;
+
+	let mut images = image_state.images.lock().unwrap();
+	let subimage_imgs = images.get_mut(image_index)
+		.ok_or(format!("Can't find subimages for image {}", image_index))?;
 
-	// update images
 	let mut new_subimage_imgs = Vec::new();
-	for i in 0..palette_count {
+	for i in 0..image_set.palettes.len() {
 		new_subimage_imgs = [new_subimage_imgs, image_set.to_images(i)?].concat();
 	}
 	*subimage_imgs = new_subimage_imgs;
@@ -355,6 +322,22 @@ fn import_image_spritesheet_from(handle: &AppHandle, image_index: usize, path: &
 	Ok(())
 }
 
+// for when image sets are added or removed rather than changed in place
+pub fn update_image_sets(handle: &AppHandle) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let image_state: State<ImageState> = handle.state();
+
+	let summaries = {
+		let sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
+		let sprite_pack = sprite_pack_opt.as_ref().ok_or("Can't find sprite pack")?;
+		*image_state.images.lock().unwrap() = sprite_pack.get_image_data()?;
+		sprite_pack.image_sets.iter().map(|image_set| image_set.to_summary()).collect::<Vec<_>>()
+	};
+
+	handle.emit("update_image_sets", summaries)?;
+	Ok(())
+}
+
 pub fn spritesheet_to_images(path: &PathBuf, subimage_count: u32, palette_count: u32) -> Result<Vec<RgbaImage>, Box<dyn Error>> {
 	let spritesheet = ImageReader::open(path)?.decode()?;
 	let mut subimages = Vec::new();
@@ -431,6 +414,7 @@ pub fn import_encoding_from(handle: &AppHandle, path: &PathBuf) -> Result<(), Bo
 
 	let font_state: State<FontState> = handle.state();
 	let char_codes: Vec<CharEncoding> = serde_json::from_str(&file_string)?;
+	check_char_codes(&char_codes)?;
 
 	*font_state.char_codes.lock().unwrap() = char_codes;
 	*font_state.encoding_language.lock().unwrap() = EncodingLanguage::Custom;
@@ -0,0 +1,52 @@
+use std::error::Error;
+use std::path::{ Path, PathBuf };
+
+use tauri::{ AppHandle, Manager, State, Emitter };
+use tauri::async_runtime::spawn;
+use tauri::path::BaseDirectory;
+
+use rfd::FileDialog;
+
+use smartypants_core::library::scan_library;
+
+use crate::{ show_spinner, hide_spinner, show_error_message };
+use crate::file::{ FileState, open_path, continue_if_modified };
+
+pub fn open_library(handle: AppHandle) {
+	let file_state: State<FileState> = handle.state();
+
+	let mut file_dialog = FileDialog::new()
+		.set_title("Choose a folder of card dumps");
+	if let Some(base_path) = file_state.base_path.lock().unwrap().as_ref() {
+		file_dialog = file_dialog.set_directory(base_path);
+	}
+
+	if let Some(folder) = file_dialog.pick_folder() {
+		rescan_library(handle, folder);
+	}
+}
+
+#[tauri::command]
+pub fn rescan_library(handle: AppHandle, folder: PathBuf) {
+	show_spinner(&handle);
+	spawn(async move {
+		if let Err(why) = scan_folder(&handle, &folder) {
+			show_error_message(why);
+		}
+		hide_spinner(&handle);
+	});
+}
+
+fn scan_folder(handle: &AppHandle, folder: &Path) -> Result<(), Box<dyn Error>> {
+	let index_path = handle.path().resolve("resources/library_index.json", BaseDirectory::Resource)?;
+	let library = scan_library(folder, &index_path)?;
+	handle.emit("show_library_dialog", library)?;
+	Ok(())
+}
+
+#[tauri::command]
+pub fn open_library_entry(handle: AppHandle, path: PathBuf) {
+	if continue_if_modified(&handle) {
+		open_path(handle, path);
+	}
+}
@@ -13,9 +13,17 @@ use rfd::{ MessageLevel, MessageButtons, MessageDialog };
 
 use regex::Regex;
 
+use smartypants_core::smacard::{ CardHeader, PackTable };
+use smartypants_core::data_pack::DataPack;
+use smartypants_core::sprite_pack::SpritePack;
+use smartypants_core::text::Text;
+use smartypants_core::FormatError;
+use smartypants_core::layout::FirmwareLayout;
+use smartypants_core::known_dumps::Identification;
+pub use smartypants_core::{ BinType, BinSize };
+
 mod smacard;
 mod firmware;
-mod data_view;
 mod data_pack;
 mod sprite_pack;
 mod text;
@@ -23,24 +31,30 @@ mod file;
 mod export;
 mod import;
 mod config;
+mod history;
+mod compare;
+mod library;
 
-use data_pack::DataPack;
-use sprite_pack::SpritePack;
-use text::{ Text, FontState, set_to_preset_encoding };
-use file::{ FileState, open_bin, save_bin, save_bin_as, continue_if_modified };
+use text::{ FontState, set_to_preset_encoding };
+use file::{ FileState, open_bin, save_bin, save_bin_as, continue_if_modified, verify_round_trip, update_size_budget };
 use import::import_encoding;
 use export::export_encoding;
-use config::{ ConfigState, load_config, get_themes, set_theme, set_toolbar_visibility };
+use config::{ ConfigState, load_config, get_themes, set_theme, set_toolbar_visibility, set_backup_count };
+use history::{ HistoryState, undo, redo };
+use compare::CompareState;
 
 #[derive(Default)]
 pub struct DataState {
 	pub bin_type: Mutex<Option<BinType>>,
 	pub bin_size: Mutex<Option<BinSize>>,
-	pub card_header: Mutex<Option<smacard::CardHeader>>,
+	pub card_header: Mutex<Option<CardHeader>>,
+	pub pack_table: Mutex<Option<PackTable>>,
 	pub data_pack: Mutex<Option<DataPack>>,
 	pub sprite_pack: Mutex<Option<SpritePack>>,
 	pub menu_strings: Mutex<Option<Vec<Text>>>,
 	pub use_patch_header: Mutex<bool>,
+	pub firmware_layout: Mutex<Option<FirmwareLayout>>,
+	pub identification: Mutex<Option<Identification>>,
 	pub original_data: Mutex<Option<Vec<u8>>>,
 }
 
@@ -49,47 +63,65 @@ pub struct ImageState {
 	pub images: Mutex<Vec<Vec<image::RgbaImage>>>
 }
 
-#[derive(Debug, Clone, serde::Serialize)]
-pub enum BinType {
-	Firmware,
-	SmaCard
-}
-
-#[derive(Debug, Clone, PartialEq, serde::Serialize)]
-pub enum BinSize {
-	Firmware,
-	Card128KB,
-	Card1MB,
-	Card2MB,
-	TooBig
-}
-
 fn main() {
 	let app = Builder::default()
 		.invoke_handler(tauri::generate_handler![
 			open_bin,
 			save_bin,
 			save_bin_as,
+			verify_round_trip,
+			compare::compare_bins,
+			compare::export_comparison,
 			export::export_strings,
 			export::export_images,
 			export::export_image_spritesheet,
 			export::export_encoding,
+			export::export_project,
+			export::export_patch,
 			import::import_strings,
 			import::import_images,
 			import::import_image_spritesheet,
 			import::import_encoding,
+			import::import_project,
+			import::import_patch,
 			try_quit,
+			history::undo,
+			history::redo,
 			firmware::set_patch_header,
 			data_pack::item::update_item,
+			data_pack::item::add_item,
+			data_pack::item::duplicate_item,
+			data_pack::item::delete_item,
 			data_pack::character::update_character,
+			data_pack::character::add_character,
+			data_pack::character::duplicate_character,
+			data_pack::character::delete_character,
 			data_pack::tamastring::update_tamastring,
+			data_pack::tamastring::insert_tamastring,
+			data_pack::tamastring::delete_tamastring,
+			data_pack::tamastring::move_tamastring,
 			data_pack::frame::update_frame,
+			data_pack::frame::add_frame_group,
+			data_pack::frame::set_frame_explicit,
+			data_pack::frame::insert_frame_layer,
+			data_pack::frame::delete_frame_layer,
+			data_pack::frame::move_frame_layer,
 			data_pack::scene::update_scene_layer,
+			data_pack::scene::add_scene,
+			data_pack::scene::duplicate_scene,
+			data_pack::scene::delete_scene,
+			data_pack::scene::insert_scene_layer,
+			data_pack::scene::delete_scene_layer,
+			data_pack::scene::move_scene_layer,
 			sprite_pack::image_def::update_image_set,
 			firmware::update_menu_string,
+			smacard::create_new_card,
+			library::rescan_library,
+			library::open_library_entry,
 			smacard::clear_device_ids,
 			smacard::update_card_id,
 			smacard::update_build_date,
+			smacard::update_card_header,
 			text::validate_string,
 			text::decode_string_js,
 			text::get_default_char_codes,
@@ -103,11 +135,15 @@ fn main() {
 		.manage(ImageState::default())
 		.manage(FontState::default())
 		.manage(ConfigState::default())
+		.manage(HistoryState::default())
+		.manage(CompareState::default())
 
 		.menu(|handle| {
 			Menu::with_id_and_items(handle, "main", &[
 				&Submenu::with_id_and_items(handle, "file", "File", true, &[
+					&MenuItem::with_id(handle, "new_card", "New Card...", true, Some("CmdOrCtrl+N"))?,
 					&MenuItem::with_id(handle, "open", "Open", true, Some("CmdOrCtrl+O"))?,
+					&MenuItem::with_id(handle, "open_library", "Card Library...", true, None::<&str>)?,
 					&PredefinedMenuItem::separator(handle)?,
 					&MenuItem::with_id(handle, "save", "Save", true, Some("CmdOrCtrl+S"))?,
 					&MenuItem::with_id(handle, "save_as", "Save As...", true, Some("CmdOrCtrl+Shift+S"))?,
@@ -116,17 +152,35 @@ fn main() {
 					&Submenu::with_id_and_items(handle, "import", "Import", true, &[
 						&MenuItem::with_id(handle, "import_strings", "Import Strings", true, None::<&str>)?,
 						&MenuItem::with_id(handle, "import_images", "Import Images", true, None::<&str>)?,
+						&PredefinedMenuItem::separator(handle)?,
+						&MenuItem::with_id(handle, "import_project", "Import Project...", true, None::<&str>)?,
+						&MenuItem::with_id(handle, "import_patch", "Apply Patch...", true, None::<&str>)?,
 					])?,
 
 					&Submenu::with_id_and_items(handle, "export", "Export", true, &[
 						&MenuItem::with_id(handle, "export_strings", "Export Strings", true, None::<&str>)?,
 						&MenuItem::with_id(handle, "export_images", "Export Images", true, None::<&str>)?,
+						&PredefinedMenuItem::separator(handle)?,
+						&MenuItem::with_id(handle, "export_project", "Export Project...", true, None::<&str>)?,
+						&MenuItem::with_id(handle, "export_patch", "Export Patch...", true, None::<&str>)?,
 					])?,
 
 					&PredefinedMenuItem::separator(handle)?,
 					&MenuItem::with_id(handle, "quit", "Quit", true, Some("CmdOrCtrl+Q"))?,
 				])?,
 
+				&Submenu::with_id_and_items(handle, "edit", "Edit", true, &[
+					&MenuItem::with_id(handle, "undo", "Undo", true, Some("CmdOrCtrl+Z"))?,
+					&MenuItem::with_id(handle, "redo", "Redo", true, Some("CmdOrCtrl+Shift+Z"))?,
+				])?,
+
+				&Submenu::with_id_and_items(handle, "tools", "Tools", true, &[
+					&MenuItem::with_id(handle, "verify_round_trip", "Verify Round Trip", true, None::<&str>)?,
+					&MenuItem::with_id(handle, "compare_bins", "Compare Bins...", true, None::<&str>)?,
+					&MenuItem::with_id(handle, "repair_header", "Repair Header", true, None::<&str>)?,
+					&MenuItem::with_id(handle, "size_report", "Size Report", true, None::<&str>)?,
+				])?,
+
 				&Submenu::with_id_and_items(handle, "config", "Config", true, &[
 					&Submenu::with_id_and_items(handle, "text_encoding", "Encoding", true, &[
 						&CheckMenuItem::with_id(handle, "encoding_jp", "Japanese", true, true, None::<&str>)?,
@@ -146,6 +200,18 @@ fn main() {
 						&CheckMenuItem::with_id(handle, "card_size_128kb", "128KB", true, false, None::<&str>)?,
 						&CheckMenuItem::with_id(handle, "card_size_1mb", "1MB", true, false, None::<&str>)?,
 						&CheckMenuItem::with_id(handle, "card_size_2mb", "2MB", true, false, None::<&str>)?,
+						&PredefinedMenuItem::separator(handle)?,
+						&CheckMenuItem::with_id(handle, "card_size_auto", "Auto (Smallest That Fits)", true, false, None::<&str>)?,
+					])?,
+
+					&PredefinedMenuItem::separator(handle)?,
+
+					&Submenu::with_id_and_items(handle, "backups", "Backups Kept", true, &[
+						&CheckMenuItem::with_id(handle, "backups_0", "None", true, false, None::<&str>)?,
+						&CheckMenuItem::with_id(handle, "backups_1", "1", true, false, None::<&str>)?,
+						&CheckMenuItem::with_id(handle, "backups_3", "3", true, true, None::<&str>)?,
+						&CheckMenuItem::with_id(handle, "backups_5", "5", true, false, None::<&str>)?,
+						&CheckMenuItem::with_id(handle, "backups_10", "10", true, false, None::<&str>)?,
 					])?,
 				])?,
 
@@ -182,18 +248,32 @@ fn main() {
 				let handle = handle.clone();
 
 				match id.as_str() {
+					"new_card" => smacard::show_new_card_dialog(handle),
 					"open" => open_bin(handle),
+					"open_library" => library::open_library(handle),
 					"save" => save_bin(handle),
 					"save_as" => save_bin_as(handle),
 
 					"import_strings" => import::import_strings(handle),
 					"import_images" => import::import_images(handle),
+					"import_project" => import::import_project(handle),
+					"import_patch" => import::import_patch(handle),
 
 					"export_strings" => export::export_strings(handle),
 					"export_images" => export::export_images(handle),
+					"export_project" => export::export_project(handle),
+					"export_patch" => export::export_patch(handle),
 
 					"quit" => try_quit(handle),
 
+					"undo" => undo(handle),
+					"redo" => redo(handle),
+
+					"verify_round_trip" => verify_round_trip(handle),
+					"compare_bins" => compare::compare_bins(handle),
+					"repair_header" => smacard::repair_header(handle),
+					"size_report" => smacard::show_size_report(handle),
+
 					"encoding_jp" => set_to_preset_encoding(handle, "jp"),
 					"encoding_en" => set_to_preset_encoding(handle, "en"),
 					"encoding_latin" => set_to_preset_encoding(handle, "latin"),
@@ -205,6 +285,13 @@ fn main() {
 					"card_size_128kb" => set_card_size(&handle, BinSize::Card128KB),
 					"card_size_1mb" => set_card_size(&handle, BinSize::Card1MB),
 					"card_size_2mb" => set_card_size(&handle, BinSize::Card2MB),
+					"card_size_auto" => set_card_size(&handle, BinSize::Auto),
+
+					"backups_0" => set_backup_count(&handle, 0),
+					"backups_1" => set_backup_count(&handle, 1),
+					"backups_3" => set_backup_count(&handle, 3),
+					"backups_5" => set_backup_count(&handle, 5),
+					"backups_10" => set_backup_count(&handle, 10),
 
 					"show_toolbar" => set_toolbar_visibility(&handle, None),
 
@@ -290,6 +377,7 @@ fn main() {
 
 	app.run(move |handle, event| {
 		match &event {
+			#[allow(clippy::collapsible_match)]
 			RunEvent::ExitRequested { api, code, .. } => {
 				if !code.is_some_and(|c| c == 99) {
 					api.prevent_exit();
@@ -307,9 +395,13 @@ fn main() {
 
 pub fn show_error_message(why: Box<dyn Error>) {
 	println!("ERROR: {}", why);
+	let title = match why.downcast_ref::<FormatError>() {
+		Some(format_error) => format_error.title(),
+		None => "Error".to_string()
+	};
 	let _ = MessageDialog::new()
 		.set_level(MessageLevel::Error)
-		.set_title("Error")
+		.set_title(title)
 		.set_description(format!("{}", why))
 		.set_buttons(MessageButtons::Ok)
 		.show();
@@ -342,8 +434,14 @@ pub fn update_window_title(handle: &AppHandle) {
 		None => None
 	};
 
+	let data_state: State<DataState> = handle.state();
+	let build_name = match data_state.identification.lock().unwrap().as_ref() {
+		Some(identification) => format!(" - {}", identification.describe()),
+		None => String::new()
+	};
+
 	match file_name {
-		Some(file_name) => window.set_title(&format!("Smarty Pants - {}{}", file_name, modified_indicator)).unwrap(),
+		Some(file_name) => window.set_title(&format!("Smarty Pants - {}{}{}", file_name, modified_indicator, build_name)).unwrap(),
 		None => window.set_title("Smarty Pants").unwrap()
 	}
 }
@@ -352,6 +450,7 @@ fn set_card_size(handle: &AppHandle, new_value: BinSize) {
 	let data_state: State<DataState> = handle.state();
 	*data_state.bin_size.lock().unwrap() = Some(new_value);
 	update_card_size_menu(handle);
+	update_size_budget(handle);
 }
 
 fn update_card_size_menu(handle: &AppHandle) {
@@ -371,6 +470,9 @@ fn update_card_size_menu(handle: &AppHandle) {
 				if let Some(MenuItemKind::Check(card_size_2mb_item)) = card_size_menu.get("card_size_2mb") {
 					card_size_2mb_item.set_checked(card_size.is_some_and(|v| *v == BinSize::Card2MB)).unwrap();
 				}
+				if let Some(MenuItemKind::Check(card_size_auto_item)) = card_size_menu.get("card_size_auto") {
+					card_size_auto_item.set_checked(card_size.is_some_and(|v| *v == BinSize::Auto)).unwrap();
+				}
 			}
 		}
 	}
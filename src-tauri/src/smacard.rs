@@ -1,274 +1,169 @@
 use std::error::Error;
-use std::num::Wrapping;
 
-use serde::Serialize;
-
-use md5::{ Md5, Digest };
+use serde::Deserialize;
 
 use tauri::{ AppHandle, State, Manager, Emitter };
 use tauri::async_runtime::spawn;
 
-use crate::{ DataState, BinSize, update_window_title, show_spinner, hide_spinner };
-use crate::data_view::{ DataView, bytes_to_words };
-use crate::data_pack::{ DataPack, get_data_pack, save_data_pack };
-use crate::sprite_pack::SpritePack;
-use crate::file::set_file_modified;
-
-#[derive(Clone, Serialize)]
-pub struct CardHeader {
-	sector_count: u16,
-	checksum: u16,
-	device_ids: [u32; 3],
+use rfd::{ MessageButtons, MessageDialog, MessageDialogResult, MessageLevel };
+
+use smartypants_core::data_view::DataView;
+use smartypants_core::smacard::{ CardHeader, CardType, new_card, read_card_header, check_card_header, repair_card_header };
+use smartypants_core::safe_write;
+use smartypants_core::size_report::get_card_size_report;
+
+use crate::{ DataState, ImageState, BinType, BinSize, update_window_title, show_spinner, hide_spinner, show_error_message, update_card_size_menu };
+use crate::file::{ FileState, get_card, set_file_modified, continue_if_modified, send_data_to_frontend };
+use crate::config::ConfigState;
+use crate::history::{ Edit, record_edit, clear_history };
+
+#[derive(Deserialize)]
+pub struct NewCardInfo {
+	card_id: u8,
 	vendor_id: String,
 	product_id: String,
 	card_type: CardType,
-	card_id: u16,
 	year: u16,
 	month: u16,
 	day: u16,
-	revision: u16,
-	md5: [u8; 16]
-}
-
-#[derive(Clone, Serialize)]
-pub enum CardType {
-	TamaSmaCard,
-	PromoTreasure,
-	PromoItem,
-	Unknown
+	revision: u16
 }
 
-#[derive(Clone)]
-pub struct TamaSmaCard {
-	pub header: CardHeader,
-	pub data_pack: DataPack,
-	pub sprite_pack: SpritePack
-}
-
-pub fn read_card(handle: &AppHandle, data: &DataView) -> Result<TamaSmaCard, Box<dyn Error>> {
-	let header = read_card_header(data)?;
-	let (data_pack, sprite_pack) = read_card_packs(handle, &data.chunk(0x1000, data.len() - 0x1000))?;
-	Ok(TamaSmaCard { header, data_pack, sprite_pack })
-}
-
-pub fn read_card_header(data: &DataView) -> Result<CardHeader, Box<dyn Error>> {
-	if data.len() < 80 {
-		return Err("Unable to read card data: too short for header".into());
+pub fn show_new_card_dialog(handle: AppHandle) {
+	if continue_if_modified(&handle) {
+		handle.emit("show_new_card_dialog", ()).unwrap();
 	}
+}
 
-	let sector_count = data.get_u16(0);
-
-	let checksum = data.get_u16(2);
-
-	let device_ids = [
-		data.get_u32(4),
-		data.get_u32(8),
-		data.get_u32(12)
-	];
-
-	let mut vendor_id = String::new();
-	for i in 0..16 {
-		vendor_id.push(data.get_u8(i+16).into());
+// shown after opening a card, but the card stays open whichever button is picked
+pub fn warn_about_header_issues(handle: &AppHandle) {
+	let data_state: State<DataState> = handle.state();
+	if !matches!(*data_state.bin_type.lock().unwrap(), Some(BinType::SmaCard)) {
+		return;
 	}
-
-	let mut product_id = String::new();
-	for i in 0..16 {
-		product_id.push(data.get_u8(i+32).into());
+	let issues = match data_state.original_data.lock().unwrap().as_ref() {
+		Some(original_data) => check_card_header(original_data),
+		None => return
+	};
+	if issues.is_empty() {
+		return;
 	}
 
-	let card_type = match data.get_u16(48) {
-		0 => CardType::TamaSmaCard,
-		1 => CardType::PromoTreasure,
-		2 => CardType::PromoItem,
-		_ => CardType::Unknown
+	let mut lines = vec!["This card's header doesn't match its contents. The file may be corrupt, or it may have been saved by a tool that doesn't update these fields.".to_string()];
+	lines.extend(issues.iter().map(|issue| issue.describe()));
+
+	let dialog_result = MessageDialog::new()
+		.set_level(MessageLevel::Warning)
+		.set_title("Card header problems")
+		.set_description(lines.join("\n"))
+		.set_buttons(MessageButtons::OkCancelCustom("Repair Header".to_string(), "Ignore".to_string()))
+		.show();
+	let repair = match dialog_result {
+		MessageDialogResult::Ok => true,
+		MessageDialogResult::Custom(label) => label == "Repair Header",
+		_ => false
 	};
-
-	let card_id = data.get_u16(50);
-
-	let year = data.get_u16(54);
-	let month = data.get_u16(56);
-	let day = data.get_u16(58);
-	let revision = data.get_u16(60);
-
-	let mut md5 = [0; 16];
-	for (i, byte) in md5.iter_mut().enumerate() {
-		*byte = data.get_u8(i+64);
+	if repair {
+		repair_header(handle.clone());
 	}
-
-	Ok(CardHeader {
-		sector_count,
-		checksum,
-		device_ids,
-		vendor_id,
-		product_id,
-		card_type,
-		card_id,
-		year,
-		month,
-		day,
-		revision,
-		md5
-	})
 }
 
-pub fn read_card_packs(handle: &AppHandle, data: &DataView) -> Result<(DataPack, SpritePack), Box<dyn Error>> {
-	if data.len() < 68 {
-		return Err("Unable to read card data: too short for pack info".into());
-	}
-
-	let pack_count = data.get_u16(2) as usize;
-	if pack_count < 2 {
-		return Err("Unable to read card data: too few packs".into());
-	}
-
-	let mut data_pack_opt: Option<DataPack> = None;
-	let mut sprite_pack_opt: Option<SpritePack> = None;
-
-	for i in 0..pack_count {
-		let pack_offset = data.get_u32(i*16+8) as usize;
-		let pack_size = data.get_u32(i*16+16) as usize;
-
-		if pack_offset > 0 && pack_size > 0 {
-			let pack_data = data.chunk(pack_offset, pack_size);
-			match i {
-				0 => data_pack_opt = Some(get_data_pack(handle, &pack_data)?),
-				1 => sprite_pack_opt = Some(SpritePack::from_data(&pack_data)?),
-				_ => {}
-			}
-		}
-	}
-
-	if let Some(data_pack) = data_pack_opt {
-		if let Some(sprite_pack) = sprite_pack_opt {
-			Ok((data_pack, sprite_pack))
-		} else {
-			Err("Unable to read card data: sprite pack not found".into())
-		}
-	} else {
-		Err("Unable to read card data: data pack not found".into())
+// fixes the header in the file on disk, leaving the rest of the file and any unsaved edits alone
+pub fn repair_header(handle: AppHandle) {
+	if let Err(why) = repair_header_in_file(&handle) {
+		show_error_message(why);
 	}
 }
 
-pub fn save_card(handle: &AppHandle) -> Result<Vec<u8>, Box<dyn Error>> {
+fn repair_header_in_file(handle: &AppHandle) -> Result<(), Box<dyn Error>> {
 	let data_state: State<DataState> = handle.state();
+	let file_state: State<FileState> = handle.state();
+	let config_state: State<ConfigState> = handle.state();
 
-	let data_pack_offset = 68;
-	let data_pack_opt = data_state.data_pack.lock().unwrap();
-	let data_pack = data_pack_opt.as_ref().ok_or("Unable to save TamaSma Card: missing data pack")?;
-	let mut data_pack_data = save_data_pack(data_pack, data_pack_offset)?;
-	data_pack_data.extend_from_slice(&[0, 0]);
-
-	let mut sprite_pack_opt = data_state.sprite_pack.lock().unwrap();
-	let sprite_pack = sprite_pack_opt.as_mut().ok_or("Unable to save TamaSma Card: missing sprite pack")?;
-	let sprite_pack_data = sprite_pack.as_bytes()?;
-
-	let mut sprite_pack_offset = data_pack_offset + data_pack_data.len();
-	while sprite_pack_offset % 32 != 0 {
-		sprite_pack_offset += 1;
+	if !matches!(*data_state.bin_type.lock().unwrap(), Some(BinType::SmaCard)) {
+		return Err("Only TamaSma card headers can be repaired".into());
 	}
-	let padded_data_pack_size = sprite_pack_offset - data_pack_offset;
-	data_pack_data.resize(padded_data_pack_size, 0);
-
-	let mut pack_summary = 0x3232_u16.to_le_bytes().to_vec();
-	pack_summary.extend_from_slice(&4_u16.to_le_bytes());
-
-	pack_summary.extend_from_slice(&[0, 0, 0, 0]);
-	pack_summary.extend_from_slice(&(data_pack_offset as u32).to_le_bytes());
-	pack_summary.extend_from_slice(&(data_pack_data.len() as u32).to_le_bytes());
-	pack_summary.extend_from_slice(&(data_pack_data.len() as u32 - 2).to_le_bytes());
-
-	pack_summary.extend_from_slice(&[0, 0, 0, 0]);
-	pack_summary.extend_from_slice(&(sprite_pack_offset as u32).to_le_bytes());
-	pack_summary.extend_from_slice(&(sprite_pack_data.len() as u32).to_le_bytes());
-	pack_summary.extend_from_slice(&(sprite_pack_data.len() as u32).to_le_bytes());
-
-	pack_summary.extend_from_slice(&[0; 32]);
-
-	let pack_data = [pack_summary, data_pack_data, sprite_pack_data].concat();
-
-	let mut header_opt = data_state.card_header.lock().unwrap();
-	let header = header_opt.as_mut().ok_or("Unable to save TamaSma Card: missing header")?;
-	header.sector_count = match data_state.bin_size.lock().unwrap().as_ref().ok_or("Undefined card size")? {
-		BinSize::Card128KB => 31,
-		BinSize::Card1MB => 255,
-		BinSize::Card2MB => 511,
-		_ => return Err("Invalid TamaSma card size".into())
-	};
-	let header_data = save_card_header(header)?;
-
-	let mut data = [header_data, pack_data].concat();
-
-	let padding_len = match data_state.bin_size.lock().unwrap().as_ref().ok_or("Undefined card size")? {
-		BinSize::Card128KB => 0x20000 - 16,
-		BinSize::Card1MB => 0x100000 - 16,
-		BinSize::Card2MB => 0x200000 - 16,
-		_ => return Err("Invalid TamaSma card size".into())
-	};
-	if padding_len < data.len() {
-		return Err("Data is too large to fit onto TamaSma Card".into());
+	let file_path = file_state.file_path.lock().unwrap().clone().ok_or("Save the card first: there's no file to repair")?;
+	let original_data = data_state.original_data.lock().unwrap().clone().ok_or("No original data found for current file")?;
+
+	let issues = check_card_header(&original_data);
+	if issues.is_empty() {
+		let _ = MessageDialog::new()
+			.set_level(MessageLevel::Info)
+			.set_title("Repair Header")
+			.set_description("The card header already matches its contents.")
+			.set_buttons(MessageButtons::Ok)
+			.show();
+		return Ok(());
 	}
-	let padding = vec![0_u8; padding_len - data.len()];
-	data.extend_from_slice(&padding);
 
-	data.extend_from_slice(&[50, 132, 171, 86, 34, 17, 220, 254, 142, 107, 85, 255, 181, 16, 127, 51]);
+	let repaired_data = repair_card_header(&original_data)?;
+	let backup_count = *config_state.backup_count.lock().unwrap();
+	safe_write::write_file(&file_path, &repaired_data, backup_count)?;
 
-	let checksum = calc_checksum(&data[1000..]);
-	data.splice(2..4, checksum.to_le_bytes());
+	let repaired_header = read_card_header(&DataView::new(&repaired_data))?;
+	if let Some(header) = data_state.card_header.lock().unwrap().as_mut() {
+		header.sector_count = repaired_header.sector_count;
+		header.checksum = repaired_header.checksum;
+		header.md5 = repaired_header.md5;
+		handle.emit("update_card_header", header.clone())?;
+	}
+	*data_state.original_data.lock().unwrap() = Some(repaired_data);
 
-	let mut hasher = Md5::new();
-	hasher.update(&data[0..64]);
-	let md5_result = hasher.finalize();
-	data.splice(64..80, md5_result);
+	let _ = MessageDialog::new()
+		.set_level(MessageLevel::Info)
+		.set_title("Repair Header")
+		.set_description(format!("Fixed {} header problem(s) in {}.", issues.len(), file_path.display()))
+		.set_buttons(MessageButtons::Ok)
+		.show();
 
-	Ok(data)
+	Ok(())
 }
 
-pub fn save_card_header(header: &CardHeader) -> Result<Vec<u8>, Box<dyn Error>> {
-	let mut data: Vec<u8> = Vec::new();
-
-	data.extend_from_slice(&header.sector_count.to_le_bytes());
-	data.extend_from_slice(&header.checksum.to_le_bytes());
-	data.extend_from_slice(&header.device_ids[0].to_le_bytes());
-	data.extend_from_slice(&header.device_ids[1].to_le_bytes());
-	data.extend_from_slice(&header.device_ids[2].to_le_bytes());
-	data.extend_from_slice(header.vendor_id.as_bytes());
-	data.extend_from_slice(header.product_id.as_bytes());
-
-	let card_type = match header.card_type {
-		CardType::TamaSmaCard => 0_u16,
-		CardType::PromoTreasure => 1_u16,
-		CardType::PromoItem => 2_u16,
-		_ => { return Err("Invalid card type".into()); }
-	};
-	data.extend_from_slice(&card_type.to_le_bytes());
-
-	data.extend_from_slice(&header.card_id.to_le_bytes());
-
-	data.extend_from_slice(&[0, 0]);
-
-	data.extend_from_slice(&header.year.to_le_bytes());
-	data.extend_from_slice(&header.month.to_le_bytes());
-	data.extend_from_slice(&header.day.to_le_bytes());
-	data.extend_from_slice(&header.revision.to_le_bytes());
-
-	data.extend_from_slice(&[0, 0]);
-
-	data.extend_from_slice(&header.md5);
-
-	let padding_len = 0x1000 - data.len();
-	let padding = vec![0_u8; padding_len];
-	data.extend_from_slice(&padding);
-
-	Ok(data)
+#[tauri::command]
+pub fn create_new_card(handle: AppHandle, info: NewCardInfo) -> bool {
+	match create_new_card_in_state(&handle, info) {
+		Ok(()) => {
+			let file_state: State<FileState> = handle.state();
+			clear_history(&handle);
+			*file_state.file_path.lock().unwrap() = None;
+			set_file_modified(&handle, true);
+			true
+		},
+		Err(why) => {
+			show_error_message(why);
+			false
+		}
+	}
 }
 
-fn calc_checksum(data: &[u8]) -> u16 {
-	let words = bytes_to_words(data);
-	let mut checksum = Wrapping(0_u16);
-	for word in words {
-		checksum += Wrapping(word);
-	}
-	checksum.0
+fn create_new_card_in_state(handle: &AppHandle, info: NewCardInfo) -> Result<(), Box<dyn Error>> {
+	let data_state: State<DataState> = handle.state();
+	let image_state: State<ImageState> = handle.state();
+
+	let mut header = CardHeader::new(info.card_id, info.card_type, &info.vendor_id, &info.product_id)?;
+	header.year = info.year;
+	header.month = info.month;
+	header.day = info.day;
+	header.revision = info.revision;
+	let card = new_card(header);
+
+	*data_state.bin_type.lock().unwrap() = Some(BinType::SmaCard);
+	*data_state.bin_size.lock().unwrap() = Some(BinSize::Card128KB);
+	*data_state.use_patch_header.lock().unwrap() = false;
+	*data_state.card_header.lock().unwrap() = Some(card.header);
+	*data_state.pack_table.lock().unwrap() = Some(card.pack_table);
+	*data_state.data_pack.lock().unwrap() = Some(card.data_pack);
+	*data_state.sprite_pack.lock().unwrap() = Some(card.sprite_pack);
+	*data_state.menu_strings.lock().unwrap() = None;
+	*data_state.original_data.lock().unwrap() = None;
+	*data_state.identification.lock().unwrap() = None;
+	image_state.images.lock().unwrap().clear();
+
+	update_card_size_menu(handle);
+	send_data_to_frontend(handle);
+
+	Ok(())
 }
 
 #[tauri::command]
@@ -276,10 +171,12 @@ pub fn clear_device_ids(handle: AppHandle) {
 	let data_state: State<DataState> = handle.state();
 	let mut header_opt = data_state.card_header.lock().unwrap();
 	if let Some(header) = header_opt.as_mut() {
+		record_edit(&handle, Edit::CardHeader(header.clone()));
 		header.device_ids = [0, 0, 0];
+		handle.emit("update_card_header", header.clone()).unwrap();
+		drop(header_opt);
 		set_file_modified(&handle, true);
 		update_window_title(&handle);
-		handle.emit("update_card_header", header.clone()).unwrap();
 	}
 }
 
@@ -289,18 +186,26 @@ pub fn update_card_id(handle: AppHandle, new_card_id: u8) {
 	spawn(async move {
 		let data_state: State<DataState> = handle.state();
 
+		let mut edits = Vec::new();
+
 		let mut header_opt = data_state.card_header.lock().unwrap();
 		if let Some(header) = header_opt.as_mut() {
+			edits.push(Edit::CardHeader(header.clone()));
 			header.card_id = new_card_id as u16;
 		}
 
 		let mut data_pack_opt = data_state.data_pack.lock().unwrap();
 		if let Some(data_pack) = data_pack_opt.as_mut() {
+			edits.push(Edit::DataPack(data_pack.clone()));
 			let old_card_id = data_pack.card_id as u8;
 			data_pack.set_card_id(old_card_id, new_card_id);
 			handle.emit("update_data_pack", data_pack.clone()).unwrap();
 		}
 
+		drop(data_pack_opt);
+		drop(header_opt);
+		record_edit(&handle, Edit::Batch(edits));
+
 		set_file_modified(&handle, true);
 		update_window_title(&handle);
 		hide_spinner(&handle);
@@ -312,12 +217,51 @@ pub fn update_build_date(handle: AppHandle, new_year: u16, new_month: u16, new_d
 	let data_state: State<DataState> = handle.state();
 	let mut header_opt = data_state.card_header.lock().unwrap();
 	if let Some(header) = header_opt.as_mut() {
+		record_edit(&handle, Edit::CardHeader(header.clone()));
 		header.year = new_year;
 		header.month = new_month;
 		header.day = new_day;
 		header.revision = new_revision;
+		handle.emit("update_card_header", header.clone()).unwrap();
+		drop(header_opt);
 		set_file_modified(&handle, true);
 		update_window_title(&handle);
-		handle.emit("update_card_header", header.clone()).unwrap();
 	}
 }
+
+pub fn show_size_report(handle: AppHandle) {
+	let data_state: State<DataState> = handle.state();
+	if !matches!(*data_state.bin_type.lock().unwrap(), Some(BinType::SmaCard)) {
+		show_error_message("Open a TamaSma card to see its size report".into());
+		return;
+	}
+
+	show_spinner(&handle);
+	spawn(async move {
+		match get_card(&handle).and_then(|card| Ok(get_card_size_report(&card)?)) {
+			Ok(report) => handle.emit("show_size_report_dialog", report).unwrap(),
+			Err(why) => show_error_message(why)
+		}
+		hide_spinner(&handle);
+	});
+}
+
+// returns the validation error so the dialog can show it next to the fields
+#[tauri::command]
+pub fn update_card_header(handle: AppHandle, vendor_id: String, product_id: String, card_type: CardType) -> Result<(), String> {
+	let data_state: State<DataState> = handle.state();
+	let mut header_opt = data_state.card_header.lock().unwrap();
+	let header = header_opt.as_mut().ok_or("No card header to edit")?;
+
+	let mut new_header = header.clone();
+	new_header.set_ids_and_type(&vendor_id, &product_id, card_type).map_err(|why| why.to_string())?;
+
+	record_edit(&handle, Edit::CardHeader(header.clone()));
+	*header = new_header;
+	handle.emit("update_card_header", header.clone()).unwrap();
+	drop(header_opt);
+	set_file_modified(&handle, true);
+	update_window_title(&handle);
+
+	Ok(())
+}
@@ -0,0 +1,39 @@
+class CompareDialog extends EditDialog {
+	static open(comparison) {
+		document.getElementById('edit-dialog-title').innerText = 'Compare Bins'
+
+		const body = document.getElementById('edit-dialog-body')
+
+		const description = div({ className: 'compare-summary' })
+		description.innerText = `${comparison.old_name} → ${comparison.new_name}: ${comparison.summary}`
+		body.append(description)
+
+		for (const section of comparison.sections) {
+			EditDialog.addSectionTitle(section.title)
+			for (const entity of section.entities) {
+				const entityTitle = div({ className: 'compare-entity' })
+				entityTitle.innerText = `${entity.title} ${entity.kind}`
+				body.append(entityTitle)
+				for (const field of entity.fields) {
+					const fieldEl = div({ className: 'compare-field' })
+					fieldEl.innerText = field
+					body.append(fieldEl)
+				}
+			}
+		}
+
+		document.getElementById('edit-dialog-actions').append(
+			button({ className: 'text', title: 'Export JSON', onclick: () => tauri_invoke('export_comparison', { format: 'json' }) }, 'Export JSON'),
+			button({ className: 'text', title: 'Export Markdown', onclick: () => tauri_invoke('export_comparison', { format: 'markdown' }) }, 'Export Markdown'),
+			button({ id: 'edit-ok-button', className: 'text', title: 'Ok', onclick: CompareDialog.close }, 'Ok')
+		)
+		document.getElementById('edit-dialog').classList.add('open')
+	}
+
+	static setup() {
+		tauri_listen('show_compare_dialog', event => {
+			EditDialog.close()
+			CompareDialog.open(event.payload)
+		})
+	}
+}
@@ -79,6 +79,24 @@ class EditDialog {
 		EditDialog.updateStringPreview(name)
 	}
 
+	static addTextInput(title, name, value, maxlength) {
+		document.getElementById('edit-dialog-body').append(
+			label({ id: `label-${name}` }, [
+				span(title),
+				input({ id: `edit-${name}`, value, maxlength, pattern: '[\\x20-\\x7E]*' }),
+				span({ className: 'validation-error' }, 'ASCII characters only')
+			])
+		)
+	}
+
+	static checkTextValue(name) {
+		return document.getElementById(`edit-${name}`).checkValidity()
+	}
+
+	static getTextValue(name) {
+		return document.getElementById(`edit-${name}`).value
+	}
+
 	static addIntInput(title, name, value, min, max) {
 		document.getElementById('edit-dialog-body').append(
 			label({ id: `label-${name}` }, [
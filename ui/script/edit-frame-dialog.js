@@ -1,9 +1,24 @@
+const FRAME_LAYER_TYPES = [
+	'Unknown',
+	'Face',
+	'Npc',
+	'Body',
+	'HeadAccessory',
+	'FaceAccessory',
+	'BodyAccessory',
+	'DirtClouds',
+	'HandAccessory'
+]
+
 class EditFrameDialog extends EditDialog {
 	static open(groupIndex, frameIndex, frame) {
 		document.getElementById('edit-dialog-title').innerText = `Edit Frame ${groupIndex}-${frameIndex}: ${frameNames[frameIndex]}`
 
 		frame.Explicit.forEach((layer, layerIndex) => {
-			EditDialog.addSectionTitle(`Layer ${layerIndex}: ${layer.layer_type}`)
+			EditDialog.addSectionTitle(`Layer ${layerIndex}`)
+			EditDialog.addDropdown('Type', `layer-type-${layerIndex}`, layer.layer_type != null ? layer.layer_type : '', [
+				{ title: '-', value: '' }
+			].concat(FRAME_LAYER_TYPES.map(layerType => ({ title: layerType, value: layerType }))))
 			EditDialog.addIntInput('X Offset', `x-${layerIndex}`, layer.x, -128, 128)
 			EditDialog.addIntInput('Y Offset', `y-${layerIndex}`, layer.y, -128, 128)
 			EditDialog.addIdInput('Image ID', `image-id-${layerIndex}`, layer.image_id)
@@ -44,7 +59,7 @@ class EditFrameDialog extends EditDialog {
 			const newFrame = {
 				Explicit: frame.Explicit.map((layer, layerIndex) => {
 					return {
-						layer_type: layer.layer_type,
+						layer_type: EditDialog.getDropdownValue(`layer-type-${layerIndex}`) || null,
 						x: EditDialog.getIntValue(`x-${layerIndex}`),
 						y: EditDialog.getIntValue(`y-${layerIndex}`),
 						image_id: EditDialog.getIdValue(`image-id-${layerIndex}`),
@@ -58,13 +73,7 @@ class EditFrameDialog extends EditDialog {
 
 			tauri_invoke('update_frame', { groupIndex, frameIndex, newFrame }).then(result => {
 				if (result != null) {
-					cardData.data_pack.frame_groups[groupIndex].frames[frameIndex] = result
-					sections.frames = setupFrames()
-					viewFrames()
-					const frameEl = document.getElementById(`frame-${groupIndex}-${frameIndex}`)
-					if (frameEl != null) {
-						frameEl.scrollIntoView()
-					}
+					updateFrame(groupIndex, frameIndex, result)
 				}
 			})
 
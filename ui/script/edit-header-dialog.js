@@ -55,3 +55,46 @@ class EditBuildDateDialog extends EditDialog {
 		}
 	}
 }
+
+class EditCardHeaderDialog extends EditDialog {
+	static open() {
+		document.getElementById('edit-dialog-title').innerText = 'Edit Card Header'
+
+		const header = cardData.card_header
+		EditDialog.addTextInput('Vendor ID', 'vendor-id', stripHeaderId(header.vendor_id), 16)
+		EditDialog.addTextInput('Product ID', 'product-id', stripHeaderId(header.product_id), 16)
+		EditDialog.addDropdown('Card Type', 'card-type', header.card_type, [
+			{ value: 'TamaSmaCard', title: 'TamaSma Card' },
+			{ value: 'PromoTreasure', title: 'Promo (Treasure)' },
+			{ value: 'PromoItem', title: 'Promo (Item)' }
+		])
+		EditDialog.addDescription('header-error', '')
+
+		document.getElementById('edit-dialog-actions').append(
+			button({ id: 'edit-cancel-button', className: 'text', title: 'Cancel', onclick: EditCardHeaderDialog.close }, 'Cancel'),
+		)
+		document.getElementById('edit-dialog-actions').append(
+			button({ id: 'edit-ok-button', className: 'text', title: 'Ok', onclick: EditCardHeaderDialog.submit }, 'Ok')
+		)
+		document.getElementById('edit-dialog').classList.add('open')
+	}
+
+	static submit() {
+		if (EditDialog.checkTextValue('vendor-id') && EditDialog.checkTextValue('product-id')) {
+			const header = cardData.card_header
+			const vendorId = padHeaderId(EditDialog.getTextValue('vendor-id'), header.vendor_id)
+			const productId = padHeaderId(EditDialog.getTextValue('product-id'), header.product_id)
+			const cardType = EditDialog.getDropdownValue('card-type')
+			tauri_invoke('update_card_header', { vendorId, productId, cardType })
+				.then(() => EditCardHeaderDialog.close())
+				.catch(error => {
+					document.getElementById('header-error').innerText = error
+				})
+		}
+	}
+}
+
+const stripHeaderId = (id) => id.replace(/[\0 ]+$/, '')
+
+// IDs must be exactly 16 bytes; keep the padding the card already uses, or spaces like official cards
+const padHeaderId = (id, originalId = '') => id.padEnd(16, originalId.endsWith('\0') ? '\0' : ' ')
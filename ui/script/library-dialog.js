@@ -0,0 +1,73 @@
+class LibraryDialog extends EditDialog {
+	static open(library) {
+		document.getElementById('edit-dialog-title').innerText = 'Card Library'
+
+		const body = document.getElementById('edit-dialog-body')
+
+		const summary = div({ className: 'compare-summary' })
+		summary.innerText = `${library.folder}: ${library.entries.length} card(s), ${library.rescanned_count} read since the last scan.`
+		if (library.duplicate_card_ids.length > 0) {
+			summary.innerText += ` Card IDs used by more than one card: ${library.duplicate_card_ids.join(', ')}`
+		}
+		body.append(summary)
+
+		body.append(table([
+			thead([
+				tr([th('File'), th('Card ID'), th('Type'), th('Build Date'), th('Vendor / Product'), th('MD5'), th('')])
+			]),
+			tbody(library.entries.map(entry => LibraryDialog.row(library, entry)))
+		]))
+
+		document.getElementById('edit-dialog-actions').append(
+			button({ className: 'text', title: 'Rescan', onclick: () => tauri_invoke('rescan_library', { folder: library.folder }) }, 'Rescan'),
+			button({ id: 'edit-ok-button', className: 'text', title: 'Close', onclick: LibraryDialog.close }, 'Close')
+		)
+		document.getElementById('edit-dialog').classList.add('open')
+	}
+
+	static row(library, entry) {
+		const header = entry.card_header
+		const fileName = entry.path.startsWith(library.folder) ? entry.path.slice(library.folder.length + 1) : entry.path
+
+		const cells = [LibraryDialog.textCell(fileName)]
+		if (header == null) {
+			const errorCell = LibraryDialog.textCell(entry.error || 'unreadable')
+			errorCell.colSpan = 5
+			cells.push(errorCell)
+		} else {
+			const cardIdCell = LibraryDialog.textCell(header.card_id)
+			if (library.duplicate_card_ids.includes(header.card_id)) {
+				cardIdCell.classList.add('library-duplicate')
+				cardIdCell.title = 'Another card uses this ID'
+			}
+			cells.push(
+				cardIdCell,
+				LibraryDialog.textCell(header.card_type),
+				LibraryDialog.textCell(`${header.year}-${header.month}-${header.day} revision ${header.revision}`),
+				LibraryDialog.textCell(`${stripHeaderId(header.vendor_id)} / ${stripHeaderId(header.product_id)}`),
+				LibraryDialog.textCell(entry.md5_ok ? 'ok' : 'mismatch')
+			)
+		}
+		cells.push(td([
+			button({ className: 'text', title: 'Open', onclick: () => {
+				LibraryDialog.close()
+				tauri_invoke('open_library_entry', { path: entry.path })
+			} }, 'Open')
+		]))
+
+		return tr(cells)
+	}
+
+	static textCell(text) {
+		const cell = td()
+		cell.innerText = text
+		return cell
+	}
+
+	static setup() {
+		tauri_listen('show_library_dialog', event => {
+			EditDialog.close()
+			LibraryDialog.open(event.payload)
+		})
+	}
+}
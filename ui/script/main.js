@@ -153,6 +153,7 @@ window.addEventListener('load', () => {
 	tauri_listen('update_tamastrings', event => {
 		cardData.data_pack.tamastrings = event.payload[0]
 		sections.tamaStrings = setupTamaStrings()
+		updateSectionCount('tamaStrings', cardData.data_pack.tamastrings.length)
 		if (event.payload[1] || currentSection === 'tamaStrings') {
 			viewTamaStrings()
 		}
@@ -161,6 +162,7 @@ window.addEventListener('load', () => {
 	tauri_listen('update_items', event => {
 		cardData.data_pack.items = event.payload[0]
 		sections.items = setupItems()
+		updateSectionCount('items', cardData.data_pack.items.length)
 		if (event.payload[1] || currentSection === 'items') {
 			viewItems()
 		}
@@ -169,15 +171,48 @@ window.addEventListener('load', () => {
 	tauri_listen('update_characters', event => {
 		cardData.data_pack.characters = event.payload[0]
 		sections.characters = setupCharacters()
+		updateSectionCount('characters', cardData.data_pack.characters.length)
 		if (event.payload[1] || currentSection === 'characters') {
 			viewCharacters()
 		}
 	})
 
+	tauri_listen('update_frame_groups', event => {
+		cardData.data_pack.frame_groups = event.payload[0]
+		sections.frames = setupFrames()
+		updateSectionCount('frames', cardData.data_pack.frame_groups.length)
+		if (event.payload[1] || currentSection === 'frames') {
+			viewFrames()
+		}
+	})
+
+	tauri_listen('update_scenes', event => {
+		cardData.data_pack.scenes = event.payload[0]
+		sections.scenes = setupScenes()
+		updateSectionCount('scenes', cardData.data_pack.scenes.length)
+		if (event.payload[1] || currentSection === 'scenes') {
+			viewScenes()
+		}
+	})
+
 	tauri_listen('update_image_set', event => {
 		updateImageSet(event.payload[0], event.payload[1])
 	})
 
+	tauri_listen('update_image_sets', event => {
+		timestamp = Date.now()
+		cardData.image_sets = event.payload
+		sections.sprites = setupSprites()
+		sections.particleEmitters = setupParticleEmitters()
+		sections.items = setupItems()
+		sections.characters = setupCharacters()
+		sections.animations = setupAnimations()
+		updateSectionCount('sprites', cardData.image_sets.length)
+		if (currentSection === 'sprites') {
+			viewSprites()
+		}
+	})
+
 	tauri_listen('update_image', event => {
 		timestamp = Date.now()
 		const imageIndex = event.payload
@@ -209,6 +244,10 @@ window.addEventListener('load', () => {
 		}
 	})
 
+	tauri_listen('update_size_budget', event => {
+		updateSizeBudget(event.payload)
+	})
+
 	tauri_listen('show_spinner', () => {
 		document.getElementById('spinner').classList.add('on')
 	})
@@ -276,6 +315,10 @@ const setupDialogs = () => {
 	AboutDialog.setup()
 	ChooseEncodingDialog.setup()
 	EditEncodingDialog.setup()
+	CompareDialog.setup()
+	NewCardDialog.setup()
+	SizeReportDialog.setup()
+	LibraryDialog.setup()
 }
 
 const closeDialogs = () => {
@@ -341,4 +384,30 @@ const viewTable9 = () => {
 
 const EDIT_ICON = '<svg viewBox="0 0 64 64"><path fill-rule="evenodd" clip-rule="evenodd" d="M40.4767 6.29289C42.4293 4.34027 45.5951 4.34028 47.5477 6.2929L56.7401 15.4853C58.6927 17.4379 58.6927 20.6037 56.7401 22.5564L30.8082 48.4883C30.2298 49.0667 29.5183 49.4943 28.7362 49.7338L15.4884 53.7892C11.6565 54.9622 8.07077 51.3765 9.24379 47.5446L13.2993 34.2968C13.5387 33.5147 13.9663 32.8032 14.5447 32.2248L40.4767 6.29289ZM44.0122 11.2426L22.6777 32.5772L30.4558 40.3553L51.7904 19.0208L44.0122 11.2426ZM16.7271 43.5971L18.7158 37.1006L25.9324 44.3172L19.4359 46.3059L16.7271 43.5971Z" fill="currentColor"/></svg>'
 const IMPORT_ICON = '<svg viewBox="0 0 64 64"><path d="M47 23C47 21.8954 46.1046 21 45 21H39C37.8954 21 37 20.1046 37 19V17C37 15.8954 37.8954 15 39 15H48C50.7614 15 53 17.2386 53 20V48C53 50.7614 50.7614 53 48 53H20C17.2386 53 15 50.7614 15 48V39C15 37.8954 15.8954 37 17 37H19C20.1046 37 21 37.8954 21 39V45C21 46.1046 21.8954 47 23 47H45C46.1046 47 47 46.1046 47 45V23Z" fill="currentColor"/><path d="M13.1213 8.87868C11.9497 7.70711 10.0503 7.70711 8.87868 8.87868C7.70711 10.0503 7.70711 11.9497 8.87868 13.1213L22.2574 26.5H14.5C12.8431 26.5 11.5 27.8431 11.5 29.5C11.5 31.1569 12.8431 32.5 14.5 32.5H27.5C30.2614 32.5 32.5 30.2614 32.5 27.5V14.5C32.5 12.8431 31.1569 11.5 29.5 11.5C27.8431 11.5 26.5 12.8431 26.5 14.5V22.2574L13.1213 8.87868Z" fill="currentColor"/></svg>'
+const DUPLICATE_ICON = '<svg viewBox="0 0 64 64"><path fill-rule="evenodd" clip-rule="evenodd" d="M24 9C21.2386 9 19 11.2386 19 14V40C19 42.7614 21.2386 45 24 45H50C52.7614 45 55 42.7614 55 40V14C55 11.2386 52.7614 9 50 9H24ZM25 15V39H49V15H25Z" fill="currentColor"/><path d="M15 22C15 20.3431 13.6569 19 12 19C10.3431 19 9 20.3431 9 22V50C9 52.7614 11.2386 55 14 55H42C43.6569 55 45 53.6569 45 52C45 50.3431 43.6569 49 42 49H15V22Z" fill="currentColor"/></svg>'
+const DELETE_ICON = '<svg viewBox="0 0 64 64"><path fill-rule="evenodd" clip-rule="evenodd" d="M26 7C23.2386 7 21 9.23858 21 12V15H12C10.3431 15 9 16.3431 9 18C9 19.6569 10.3431 21 12 21H14.2L16.6 52.4C16.8 55 19 57 21.6 57H42.4C45 57 47.2 55 47.4 52.4L49.8 21H52C53.6569 21 55 19.6569 55 18C55 16.3431 53.6569 15 52 15H43V12C43 9.23858 40.7614 7 38 7H26ZM37 15V13H27V15H37ZM20.2 21L22.5 51H41.5L43.8 21H20.2Z" fill="currentColor"/></svg>'
+const INSERT_ICON = '<svg viewBox="0 0 64 64"><path d="M35 13C35 11.3431 33.6569 10 32 10C30.3431 10 29 11.3431 29 13V29H13C11.3431 29 10 30.3431 10 32C10 33.6569 11.3431 35 13 35H29V51C29 52.6569 30.3431 54 32 54C33.6569 54 35 52.6569 35 51V35H51C52.6569 35 54 33.6569 54 32C54 30.3431 52.6569 29 51 29H35V13Z" fill="currentColor"/></svg>'
+const MOVE_UP_ICON = '<svg viewBox="0 0 64 64"><path d="M29.8787 11.8787C31.0503 10.7071 32.9497 10.7071 34.1213 11.8787L50.1213 27.8787C51.2929 29.0503 51.2929 30.9497 50.1213 32.1213C48.9497 33.2929 47.0503 33.2929 45.8787 32.1213L35 21.2426V51C35 52.6569 33.6569 54 32 54C30.3431 54 29 52.6569 29 51V21.2426L18.1213 32.1213C16.9497 33.2929 15.0503 33.2929 13.8787 32.1213C12.7071 30.9497 12.7071 29.0503 13.8787 27.8787L29.8787 11.8787Z" fill="currentColor"/></svg>'
+const MOVE_DOWN_ICON = '<svg viewBox="0 0 64 64"><path d="M29.8787 52.1213C31.0503 53.2929 32.9497 53.2929 34.1213 52.1213L50.1213 36.1213C51.2929 34.9497 51.2929 33.0503 50.1213 31.8787C48.9497 30.7071 47.0503 30.7071 45.8787 31.8787L35 42.7574V13C35 11.3431 33.6569 10 32 10C30.3431 10 29 11.3431 29 13V42.7574L18.1213 31.8787C16.9497 30.7071 15.0503 30.7071 13.8787 31.8787C12.7071 33.0503 12.7071 34.9497 13.8787 36.1213L29.8787 52.1213Z" fill="currentColor"/></svg>'
 const EXPORT_ICON = '<svg viewBox="0 0 64 64"><path fill-rule="evenodd" clip-rule="evenodd" d="M17 23C17 21.8954 17.8954 21 19 21H28C29.1046 21 30 20.1046 30 19V17C30 15.8954 29.1046 15 28 15H16C13.2386 15 11 17.2386 11 20V48C11 50.7614 13.2386 53 16 53H44C46.7614 53 49 50.7614 49 48V36C49 34.8954 48.1046 34 47 34H45C43.8954 34 43 34.8954 43 36V45C43 46.1046 42.1046 47 41 47H19C17.8954 47 17 46.1046 17 45V23Z" fill="currentColor"/><path fill-rule="evenodd" clip-rule="evenodd" d="M37 9C35.3431 9 34 10.3431 34 12C34 13.6569 35.3431 15 37 15H44.7574L26.3787 33.3787C25.2071 34.5503 25.2071 36.4497 26.3787 37.6213C27.5503 38.7929 29.4497 38.7929 30.6213 37.6213L49 19.2426V27C49 28.6569 50.3431 30 52 30C53.6569 30 55 28.6569 55 27V14C55 11.2386 52.7614 9 50 9H37Z" fill="currentColor"/></svg>'
+
+const updateSectionCount = (sectionName, count) => {
+	const tagEl = document.querySelector(`#view-${sectionName}-button .tag`)
+	if (tagEl != null) {
+		tagEl.innerText = count
+	}
+}
+
+const updateSizeBudget = (budgets) => {
+	const budgetEl = document.getElementById('size-budget')
+	budgetEl.replaceChildren()
+	budgetEl.classList.toggle('on', budgets.length > 0)
+	for (const budget of budgets) {
+		const over = budget.size - budget.capacity
+		const regionEl = span({ className: over > 0 ? 'size-budget-region over-budget' : 'size-budget-region' })
+		regionEl.innerText = over > 0
+			? `${budget.region}: ${over} bytes over budget`
+			: `${budget.region}: ${budget.size} / ${budget.capacity} bytes (${budget.capacity - budget.size} free)`
+		budgetEl.append(regionEl)
+	}
+}
@@ -0,0 +1,60 @@
+class NewCardDialog extends EditDialog {
+	static open() {
+		document.getElementById('edit-dialog-title').innerText = 'New Card'
+
+		const today = new Date()
+
+		EditDialog.addIntInput('Card ID', 'card-id', 0, 0, 127)
+		EditDialog.addTextInput('Vendor ID', 'vendor-id', '', 16)
+		EditDialog.addTextInput('Product ID', 'product-id', '', 16)
+		EditDialog.addDropdown('Card Type', 'card-type', 'TamaSmaCard', [
+			{ value: 'TamaSmaCard', title: 'TamaSma Card' },
+			{ value: 'PromoTreasure', title: 'Promo (Treasure)' },
+			{ value: 'PromoItem', title: 'Promo (Item)' }
+		])
+		EditDialog.addIntInput('Year', 'year', today.getFullYear(), 0, 3000)
+		EditDialog.addIntInput('Month', 'month', today.getMonth() + 1, 0, 12)
+		EditDialog.addIntInput('Day', 'day', today.getDate(), 0, 31)
+		EditDialog.addIntInput('Revision', 'revision', 1, 0, 256)
+
+		document.getElementById('edit-dialog-actions').append(
+			button({ id: 'edit-cancel-button', className: 'text', title: 'Cancel', onclick: NewCardDialog.close }, 'Cancel'),
+		)
+		document.getElementById('edit-dialog-actions').append(
+			button({ id: 'edit-ok-button', className: 'text', title: 'Create', onclick: NewCardDialog.submit }, 'Create')
+		)
+		document.getElementById('edit-dialog').classList.add('open')
+	}
+
+	static submit() {
+		if (EditDialog.checkIntValue('card-id') &&
+			EditDialog.checkTextValue('vendor-id') &&
+			EditDialog.checkTextValue('product-id') &&
+			EditDialog.checkIntValue('year') &&
+			EditDialog.checkIntValue('month') &&
+			EditDialog.checkIntValue('day') &&
+			EditDialog.checkIntValue('revision')
+		) {
+			const info = {
+				card_id: EditDialog.getIntValue('card-id'),
+				vendor_id: padHeaderId(EditDialog.getTextValue('vendor-id')),
+				product_id: padHeaderId(EditDialog.getTextValue('product-id')),
+				card_type: EditDialog.getDropdownValue('card-type'),
+				year: EditDialog.getIntValue('year'),
+				month: EditDialog.getIntValue('month'),
+				day: EditDialog.getIntValue('day'),
+				revision: EditDialog.getIntValue('revision')
+			}
+			tauri_invoke('create_new_card', { info }).then(success => {
+				if (success) NewCardDialog.close()
+			})
+		}
+	}
+
+	static setup() {
+		tauri_listen('show_new_card_dialog', () => {
+			EditDialog.close()
+			NewCardDialog.open()
+		})
+	}
+}
@@ -1,29 +1,34 @@
 const setupCharacters = () => {
 	const characters = cardData.data_pack.characters
-	return table([
-		thead([tr([
-			th('ID'),
-			th('Type'),
-			th('Name'),
-			th('Profile Image ID'),
-			th('Icon Image ID'),
-			th('Frame Group ID'),
-			th('Unknown ID'),
-			th('Pronoun'),
-			th('Statement Ending'),
-			th('Question Ending 1'),
-			th('Question Ending 2'),
-			th('Unknown 2'),
-			th('Unknown 3'),
-			th('Global ID'),
-			th('Unknown 4'),
-			th('Unknown 5'),
-			th('Unknown 6'),
-			th('Unknown 7'),
-			th('Gender'),
-			th('Actions')
-		])]),
-		tbody(characters.map((character, i) => renderCharacter(i, character)))
+	return div([
+		div({className: 'section-toolbar'}, [
+			button({onclick: () => tauri_invoke('add_character')}, 'Add Character')
+		]),
+		table([
+			thead([tr([
+				th('ID'),
+				th('Type'),
+				th('Name'),
+				th('Profile Image ID'),
+				th('Icon Image ID'),
+				th('Frame Group ID'),
+				th('Unknown ID'),
+				th('Pronoun'),
+				th('Statement Ending'),
+				th('Question Ending 1'),
+				th('Question Ending 2'),
+				th('Unknown 2'),
+				th('Unknown 3'),
+				th('Global ID'),
+				th('Unknown 4'),
+				th('Unknown 5'),
+				th('Unknown 6'),
+				th('Unknown 7'),
+				th('Gender'),
+				th('Actions')
+			])]),
+			tbody(characters.map((character, i) => renderCharacter(i, character)))
+		])
 	])
 }
 
@@ -52,7 +57,15 @@ const renderCharacter = (i, character) => {
 			button({
 				title: 'Edit Character', className: 'icon',
 				onclick: () => EditCharacterDialog.open(i, character)
-			}, EDIT_ICON)
+			}, EDIT_ICON),
+			button({
+				title: 'Duplicate Character', className: 'icon',
+				onclick: () => tauri_invoke('duplicate_character', { index: i })
+			}, DUPLICATE_ICON),
+			button({
+				title: 'Delete Character', className: 'icon',
+				onclick: () => tauri_invoke('delete_character', { index: i })
+			}, DELETE_ICON)
 		])
 	])
 }
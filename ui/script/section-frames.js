@@ -23,7 +23,7 @@ const drawFrameCanvases = () => {
 			if (frameCanvases[i] && frameCanvases[i][j] && frame.Explicit) {
 				const previewContext = frameCanvases[i][j].getContext('2d')
 				frame.Explicit.forEach(layer => {
-					if (!specialLayers.includes(layer.image_id.entity_id)) {
+					if (layer.image_id != null && !specialLayers.includes(layer.image_id.entity_id)) {
 						const imageIndex = layer.image_id.entity_id
 						const imageSet = cardData.image_sets[imageIndex]
 						if (imageSet && spriteImages[imageIndex]) {
@@ -48,25 +48,49 @@ const setupFrames = () => {
 
 	const frame_groups = cardData.data_pack.frame_groups
 	let el = document.createElement('div')
+	el.append(div({ className: 'section-toolbar' }, [
+		button({ onclick: () => tauri_invoke('add_frame_group', { sourceIndex: null }) }, 'Add Frame Group')
+	]))
 
 	frame_groups.forEach((frame_group, i) => {
 		let rows = []
 
 		frame_group.frames.forEach((frame, j) => {
 			let isLast = j === frame_group.frames.length - 1
-			let editButton = button({
-				title: 'Edit Frame', className: 'icon',
-				onclick: () => EditFrameDialog.open(i, j, frame)
-			}, EDIT_ICON)
 
 			if (frame === 'Implicit') {
 				rows.push(tr({ id: `frame-${i}-${j}` }, [
-					th(j),
+					th({ className: isLast ? 'bottom-left-cell' : '' }, j),
 					td(frameNames[j]),
-					td({ colspan: 10 }, '<em>Implicit</em>')
+					td({ colspan: 10 }, '<em>Implicit</em>'),
+					td({ className: isLast ? 'bottom-right-cell' : '' }, [
+						button({ onclick: () => editFrame('set_frame_explicit', i, j, { isExplicit: true }) }, 'Make Explicit')
+					])
 				]))
 
 			} else {
+				const frameActions = td({ rowspan: Math.max(frame.Explicit.length, 1), className: isLast ? 'bottom-right-cell' : '' }, [
+					frame.Explicit.length > 0 ? button({
+						title: 'Edit Frame', className: 'icon',
+						onclick: () => EditFrameDialog.open(i, j, frame)
+					}, EDIT_ICON) : '',
+					button({
+						title: 'Add Layer', className: 'icon',
+						onclick: () => editFrame('insert_frame_layer', i, j, { layerIndex: frame.Explicit.length })
+					}, INSERT_ICON),
+					button({ onclick: () => editFrame('set_frame_explicit', i, j, { isExplicit: false }) }, 'Make Implicit')
+				])
+
+				if (frame.Explicit.length === 0) {
+					rows.push(tr({ id: `frame-${i}-${j}` }, [
+						th({ className: isLast ? 'bottom-left-cell' : '' }, j),
+						td(frameNames[j]),
+						td({ colspan: 9 }, '<em>No layers</em>'),
+						td([ div({ className: 'preview' }, [ frameCanvases[i][j] ]) ]),
+						frameActions
+					]))
+				}
+
 				frame.Explicit.forEach((frameLayer, k) => {
 					let cells = []
 
@@ -78,14 +102,32 @@ const setupFrames = () => {
 					}
 
 					cells = cells.concat([
-						td({ className: 'subrow' }, frameLayer.layer_type),
-						td({ className: 'subrow' }, frameLayer.x),
-						td({ className: 'subrow' }, frameLayer.y),
+						td({ className: 'subrow' }, frameLayer.layer_type != null ? frameLayer.layer_type : '-'),
+						td({ className: 'subrow' }, frameLayer.x != null ? frameLayer.x : '-'),
+						td({ className: 'subrow' }, frameLayer.y != null ? frameLayer.y : '-'),
 						td({ className: 'subrow' }, frameLayer.image_id != null ? linkToImage(frameLayer.image_id) : '-'),
 						td({ className: 'subrow' }, frameLayer.subimage_index != null ? frameLayer.subimage_index : (frameLayer.image_id != null ? 0 : '-')),
 						td({ className: 'subrow' }, frameLayer.unknown1),
 						td({ className: 'subrow' }, frameLayer.unknown2),
-						td({ className: 'subrow' }, frameLayer.unknown3)
+						td({ className: 'subrow' }, frameLayer.unknown3),
+						td({ className: 'subrow' }, [
+							button({
+								title: 'Insert Layer Above', className: 'icon',
+								onclick: () => editFrame('insert_frame_layer', i, j, { layerIndex: k })
+							}, INSERT_ICON),
+							k > 0 ? button({
+								title: 'Move Up (Draw Earlier)', className: 'icon',
+								onclick: () => editFrame('move_frame_layer', i, j, { layerIndex: k, newIndex: k - 1 })
+							}, MOVE_UP_ICON) : '',
+							k < frame.Explicit.length - 1 ? button({
+								title: 'Move Down (Draw Later)', className: 'icon',
+								onclick: () => editFrame('move_frame_layer', i, j, { layerIndex: k, newIndex: k + 1 })
+							}, MOVE_DOWN_ICON) : '',
+							button({
+								title: 'Delete Layer', className: 'icon',
+								onclick: () => editFrame('delete_frame_layer', i, j, { layerIndex: k })
+							}, DELETE_ICON)
+						])
 					])
 
 					if (k === 0) {
@@ -93,9 +135,7 @@ const setupFrames = () => {
 							td({ rowspan: frame.Explicit.length }, [
 								div({ className: 'preview' }, [ frameCanvases[i][j] ])
 							]),
-							td({ rowspan: frame.Explicit.length, className: isLast ? 'bottom-right-cell' : '' }, [
-								editButton
-							])
+							frameActions
 						])
 					}
 
@@ -105,6 +145,9 @@ const setupFrames = () => {
 		})
 
 		el.append(div({id: `framegroup-${i}`, className: 'table-title'}, `Frame Group ${i}`))
+		el.append(div({ className: 'section-toolbar' }, [
+			button({ onclick: () => tauri_invoke('add_frame_group', { sourceIndex: i }) }, 'Duplicate Frame Group')
+		]))
 		el.append(table([
 			thead([tr([
 				th('-'),
@@ -117,6 +160,7 @@ const setupFrames = () => {
 				th('?'),
 				th('?'),
 				th('?'),
+				th('Layer Actions'),
 				th('Preview'),
 				th('Actions')
 			])]),
@@ -126,6 +170,24 @@ const setupFrames = () => {
 	return el
 }
 
+const editFrame = (command, groupIndex, frameIndex, args) => {
+	tauri_invoke(command, { groupIndex, frameIndex, ...args }).then(result => {
+		if (result != null) {
+			updateFrame(groupIndex, frameIndex, result)
+		}
+	})
+}
+
+const updateFrame = (groupIndex, frameIndex, frame) => {
+	cardData.data_pack.frame_groups[groupIndex].frames[frameIndex] = frame
+	sections.frames = setupFrames()
+	viewFrames()
+	const frameEl = document.getElementById(`frame-${groupIndex}-${frameIndex}`)
+	if (frameEl != null) {
+		frameEl.scrollIntoView()
+	}
+}
+
 const viewFrames = () => {
 	selectSection('frames')
 	contents.append(sections.frames)
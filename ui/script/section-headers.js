@@ -12,6 +12,7 @@ const setupCardHeader = () => {
 		div({className: 'table-title'}, 'TamaSma Card'),
 		table([
 			tbody([
+				setupKnownDumpRow(),
 				tr([
 					th('Device IDs'),
 					td({ className: 'button-row' }, [
@@ -25,9 +26,21 @@ const setupCardHeader = () => {
 						}, 'Reset')
 					])
 				]),
-				// tr([th('Vendor ID'), td(header.vendor_id)]),
-				// tr([th('Product ID'), td(header.product_id)]),
-				tr([th('Card Type'), td(header.card_type)]),
+				tr([th('Vendor ID'), setupHeaderIdCell(header.vendor_id)]),
+				tr([th('Product ID'), setupHeaderIdCell(header.product_id)]),
+				tr([
+					th('Card Type'),
+					td({ className: 'button-row' }, [
+						div(
+							{ style: 'flex-grow: 1; margin-right: 16px;' },
+							header.card_type
+						),
+						button({
+							title: 'Edit Card Header', className: 'icon',
+							onclick: () => EditCardHeaderDialog.open()
+						}, EDIT_ICON)
+					])
+				]),
 				tr([
 					th('Card ID'),
 					td({ className: 'button-row' }, [
@@ -62,9 +75,32 @@ const setupCardHeader = () => {
 	])
 }
 
+const setupKnownDumpRow = () => {
+	const identificationEl = td()
+	identificationEl.innerText = cardData.identification ?? 'Not a known dump'
+	return tr([th('Known Dump'), identificationEl])
+}
+
+const setupHeaderIdCell = (id) => {
+	const idEl = div({ style: 'flex-grow: 1; margin-right: 16px;' })
+	idEl.innerText = stripHeaderId(id)
+	return td({ className: 'button-row' }, [
+		idEl,
+		button({
+			title: 'Edit Card Header', className: 'icon',
+			onclick: () => EditCardHeaderDialog.open()
+		}, EDIT_ICON)
+	])
+}
+
 const setupFirmwareHeader = () => {
 	return div([
 		div({className: 'table-title'}, 'Tamagotchi Smart Firmware'),
+		table([
+			tbody([
+				setupKnownDumpRow()
+			])
+		]),
 		div({className: 'toggle-container'}, [
 			button({
 				className: cardData.use_patch_header ? 'toggle on' : 'toggle off',
@@ -1,23 +1,28 @@
 const setupItems = () => {
 	const items = cardData.data_pack.items
-	return table([
-		thead([tr([
-			th('ID'),
-			th('Type'),
-			th('Name'),
-			th('Image ID'),
-			th('Image ID (Worn)'),
-			th('Image ID (Close)'),
-			th('Animation ID?'),
-			th('Price'),
-			th('Fullness Increase?'),
-			th('Happiness Increase?'),
-			th('Unknown 3'),
-			th('Unlocked Character'),
-			th('Game Type'),
-			th('Actions')
-		])]),
-		tbody(items.map((item, i) => renderItem(i, item)))
+	return div([
+		div({className: 'section-toolbar'}, [
+			button({onclick: () => tauri_invoke('add_item')}, 'Add Item')
+		]),
+		table([
+			thead([tr([
+				th('ID'),
+				th('Type'),
+				th('Name'),
+				th('Image ID'),
+				th('Image ID (Worn)'),
+				th('Image ID (Close)'),
+				th('Animation ID?'),
+				th('Price'),
+				th('Fullness Increase?'),
+				th('Happiness Increase?'),
+				th('Unknown 3'),
+				th('Unlocked Character'),
+				th('Game Type'),
+				th('Actions')
+			])]),
+			tbody(items.map((item, i) => renderItem(i, item)))
+		])
 	])
 }
 
@@ -26,7 +31,7 @@ const renderItem = (i, item) => {
 		th(item.id.entity_id),
 		td(item.item_type),
 		td(item.name.string),
-		td(item.item_type === 'Game' ? `Scene: ${item.image_id.entity_id}` : displayImageWithLink(item.image_id, 0)),
+		td(item.item_type === 'Game' ? (item.image_id != null ? `Scene: ${item.image_id.entity_id}` : '-') : displayImageWithLink(item.image_id, 0)),
 		td(displayImageWithLink(item.worn_image_id, 0)),
 		td(displayImageWithLink(item.close_image_id, 0)),
 		td(item.animation_id != null ? item.animation_id.entity_id : '-'),
@@ -40,7 +45,15 @@ const renderItem = (i, item) => {
 			button({
 				title: 'Edit Item', className: 'icon',
 				onclick: () => EditItemDialog.open(i, item)
-			}, EDIT_ICON)
+			}, EDIT_ICON),
+			button({
+				title: 'Duplicate Item', className: 'icon',
+				onclick: () => tauri_invoke('duplicate_item', { index: i })
+			}, DUPLICATE_ICON),
+			button({
+				title: 'Delete Item', className: 'icon',
+				onclick: () => tauri_invoke('delete_item', { index: i })
+			}, DELETE_ICON)
 		])
 	])
 }
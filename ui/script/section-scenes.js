@@ -36,9 +36,17 @@ const setupScenes = () => {
 	setupSceneCanvases()
 	drawSceneCanvases()
 	let el = document.createElement('div')
+	el.append(div({ className: 'section-toolbar' }, [
+		button({ onclick: () => tauri_invoke('add_scene') }, 'Add Scene')
+	]))
 	const scenes = cardData.data_pack.scenes
 	scenes.forEach((scene, i) => {
 		el.append(div({ id: `scene-${i}`, className: 'table-title' }, `Scene ${i}`))
+		el.append(div({ className: 'section-toolbar button-row' }, [
+			button({ onclick: () => tauri_invoke('insert_scene_layer', { sceneIndex: i, layerIndex: scene.layers.length }) }, 'Add Layer'),
+			button({ onclick: () => tauri_invoke('duplicate_scene', { index: i }) }, 'Duplicate Scene'),
+			button({ onclick: () => tauri_invoke('delete_scene', { index: i }) }, 'Delete Scene')
+		]))
 		el.append(div({ className: 'preview' }, [ sceneCanvases[i] ]))
 		el.append(table([
 			thead([tr([
@@ -83,7 +91,23 @@ const setupScenes = () => {
 					button({
 						title: 'Edit Scene Layer', className: 'icon',
 						onclick: () => EditSceneLayerDialog.open(i, j, layer)
-					}, EDIT_ICON)
+					}, EDIT_ICON),
+					button({
+						title: 'Insert Layer Above', className: 'icon',
+						onclick: () => tauri_invoke('insert_scene_layer', { sceneIndex: i, layerIndex: j })
+					}, INSERT_ICON),
+					j > 0 ? button({
+						title: 'Move Up (Draw Earlier)', className: 'icon',
+						onclick: () => tauri_invoke('move_scene_layer', { sceneIndex: i, layerIndex: j, newIndex: j - 1 })
+					}, MOVE_UP_ICON) : '',
+					j < scene.layers.length - 1 ? button({
+						title: 'Move Down (Draw Later)', className: 'icon',
+						onclick: () => tauri_invoke('move_scene_layer', { sceneIndex: i, layerIndex: j, newIndex: j + 1 })
+					}, MOVE_DOWN_ICON) : '',
+					button({
+						title: 'Delete Scene Layer', className: 'icon',
+						onclick: () => tauri_invoke('delete_scene_layer', { sceneIndex: i, layerIndex: j })
+					}, DELETE_ICON)
 				])
 			])))
 		]))
@@ -1,15 +1,20 @@
 const setupTamaStrings = () => {
 	const tamaString = cardData.data_pack.tamastrings
-	return table([
-		thead([tr([
-			th('ID'),
-			th('Expression'),
-			th('Field 1'),
-			th('Field 2'),
-			th('Value'),
-			th('Actions')
-		])]),
-		tbody(tamaString.map((tamaString, i) => renderTamaString(i, tamaString)))
+	return div([
+		div({className: 'section-toolbar'}, [
+			button({onclick: () => tauri_invoke('insert_tamastring', { index: tamaString.length })}, 'Add Dialog String')
+		]),
+		table([
+			thead([tr([
+				th('ID'),
+				th('Expression'),
+				th('Field 1'),
+				th('Field 2'),
+				th('Value'),
+				th('Actions')
+			])]),
+			tbody(tamaString.map((tamaString, i) => renderTamaString(i, tamaString)))
+		])
 	])
 }
 
@@ -51,7 +56,23 @@ const renderTamaString = (i, tamaString) => {
 			button({
 				title: 'Edit Dialog String', className: 'icon',
 				onclick: () => EditTamaStringDialog.open(i, tamaString)
-			}, EDIT_ICON)
+			}, EDIT_ICON),
+			button({
+				title: 'Insert Dialog String Above', className: 'icon',
+				onclick: () => tauri_invoke('insert_tamastring', { index: i })
+			}, INSERT_ICON),
+			i > 0 ? button({
+				title: 'Move Up', className: 'icon',
+				onclick: () => tauri_invoke('move_tamastring', { index: i, newIndex: i - 1 })
+			}, MOVE_UP_ICON) : '',
+			i < cardData.data_pack.tamastrings.length - 1 ? button({
+				title: 'Move Down', className: 'icon',
+				onclick: () => tauri_invoke('move_tamastring', { index: i, newIndex: i + 1 })
+			}, MOVE_DOWN_ICON) : '',
+			button({
+				title: 'Delete Dialog String', className: 'icon',
+				onclick: () => tauri_invoke('delete_tamastring', { index: i })
+			}, DELETE_ICON)
 		])
 	])
 }
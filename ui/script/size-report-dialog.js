@@ -0,0 +1,58 @@
+class SizeReportDialog extends EditDialog {
+	static open(report) {
+		document.getElementById('edit-dialog-title').innerText = 'Size Report'
+
+		const body = document.getElementById('edit-dialog-body')
+
+		const summary = div({ className: 'compare-summary' })
+		const smallestSize = report.smallest_size == null ? 'none (too large for any card)' : formatCardSize(report.smallest_size)
+		summary.innerText = `${report.used_size} bytes used. Smallest card size that fits: ${smallestSize}`
+		body.append(summary)
+
+		let group = null
+		for (const section of report.sections) {
+			if (section.group !== group) {
+				group = section.group
+				EditDialog.addSectionTitle(group)
+			}
+			body.append(SizeReportDialog.row(section.name, `${section.size} bytes`))
+		}
+
+		EditDialog.addSectionTitle('Headroom')
+		for (const size of report.headroom) {
+			const headroom = size.headroom >= 0 ? `${size.headroom} bytes free` : `${-size.headroom} bytes over budget`
+			const rowEl = SizeReportDialog.row(`${formatCardSize(size.bin_size)} (${size.capacity} bytes)`, headroom)
+			if (size.headroom < 0) rowEl.classList.add('over-budget')
+			body.append(rowEl)
+		}
+
+		document.getElementById('edit-dialog-actions').append(
+			button({ id: 'edit-ok-button', className: 'text', title: 'Ok', onclick: SizeReportDialog.close }, 'Ok')
+		)
+		document.getElementById('edit-dialog').classList.add('open')
+	}
+
+	static row(name, value) {
+		const nameEl = span()
+		nameEl.innerText = name
+		const valueEl = span()
+		valueEl.innerText = value
+		return div({ className: 'size-report-row' }, [nameEl, valueEl])
+	}
+
+	static setup() {
+		tauri_listen('show_size_report_dialog', event => {
+			EditDialog.close()
+			SizeReportDialog.open(event.payload)
+		})
+	}
+}
+
+const formatCardSize = (binSize) => {
+	switch (binSize) {
+		case 'Card128KB': return '128KB'
+		case 'Card1MB': return '1MB'
+		case 'Card2MB': return '2MB'
+		default: return binSize
+	}
+}